@@ -0,0 +1,190 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::io::{BufRead, Write};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+use crate::day;
+use crate::input::Input;
+use crate::memory;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stats {
+    pub n: usize,
+    pub min: f64,
+    pub median: f64,
+    pub mean: f64,
+    pub stddev: f64,
+}
+
+impl Stats {
+    /// Statistics in nanoseconds over the given samples.
+    pub fn new(samples: &[Duration]) -> Self {
+        let mut ns = samples.iter().map(|d| d.as_nanos() as f64).collect::<Vec<_>>();
+        ns.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let n = ns.len();
+        let mean = ns.iter().sum::<f64>() / n as f64;
+        let median = if n % 2 == 1 { ns[n / 2] } else { (ns[n / 2 - 1] + ns[n / 2]) / 2.0 };
+        let var = if n > 1 {
+            ns.iter().map(|x| (x - mean) * (x - mean)).sum::<f64>() / (n - 1) as f64
+        } else { 0.0 };
+        Stats { n, min: ns[0], median, mean, stddev: var.sqrt() }
+    }
+}
+
+/// Timings of one part, with reading and parsing the input kept apart from
+/// solving it.
+pub struct Timing {
+    pub tag: String,
+    pub part: usize,
+    pub read: Stats,
+    pub parse: Stats,
+    pub solve: Stats,
+    /// The heap usage of the last run.
+    pub memory: memory::Usage,
+}
+
+impl Timing {
+    pub fn phases(&self) -> [(&str, &Stats); 3] {
+        [("read", &self.read), ("parse", &self.parse), ("solve", &self.solve)]
+    }
+}
+
+pub fn run(day: &dyn day::Day, part: usize, input: &Input, params: &HashMap<String, i64>,
+           warmup: usize, iterations: usize, timeout: Option<Duration>) -> io::Result<Timing> {
+    let mut reads = vec![];
+    let mut parses = vec![];
+    let mut solves = vec![];
+    let mut usage = memory::Usage::default();
+    for i in 0..warmup + iterations {
+        let start = Instant::now();
+        let bytes: Rc<[u8]> = input.read()?.into();
+        let read_done = Instant::now();
+        let open = move || Box::new(io::Cursor::new(bytes.clone())) as Box<dyn io::Read>;
+        let mut parse_done = read_done;
+        let mut run = || {
+            let parsed = day::parse(day, &open, &input.name, params, timeout);
+            parse_done = Instant::now();
+            parsed.map(|parsed| day::solve(day, part, &parsed, &input.name, params, timeout))
+        };
+        let answer = if i + 1 == warmup + iterations {
+            let (answer, u) = memory::measure(run);
+            usage = u;
+            answer
+        } else {
+            run()
+        };
+        let solve_done = Instant::now();
+        match answer {
+            Err(e) => return Err(io::Error::other(e.to_string())),
+            Ok(day::Answer::Error(e)) => return Err(io::Error::other(e)),
+            Ok(_) => (),
+        }
+        if i >= warmup {
+            reads.push(read_done - start);
+            parses.push(parse_done - read_done);
+            solves.push(solve_done - parse_done);
+        }
+    }
+    Ok(Timing {
+        tag: day.id(), part, read: Stats::new(&reads), parse: Stats::new(&parses),
+        solve: Stats::new(&solves), memory: usage,
+    })
+}
+
+pub fn human(ns: f64) -> String {
+    if ns >= 1e9 { format!("{:.2} s", ns / 1e9) }
+    else if ns >= 1e6 { format!("{:.2} ms", ns / 1e6) }
+    else if ns >= 1e3 { format!("{:.2} µs", ns / 1e3) }
+    else { format!("{:.0} ns", ns) }
+}
+
+pub fn header() {
+    println!("    day part phase      min     median       mean     stddev");
+}
+
+pub fn print(timing: &Timing, baseline: Option<&Baseline>) {
+    for (phase, stats) in &timing.phases() {
+        let change = baseline
+            .and_then(|b| b.get(&timing.tag, timing.part, phase))
+            .map(|old| format!("  {:+.1}% vs baseline", (stats.median / old.median - 1.0) * 100.0))
+            .unwrap_or_default();
+        println!("{:>7} {:>4} {:<5} {:>10} {:>10} {:>10} {:>10}{}",
+                 timing.tag, timing.part, phase, human(stats.min), human(stats.median),
+                 human(stats.mean), human(stats.stddev), change);
+    }
+}
+
+pub fn print_memory(timing: &Timing) {
+    println!("{:>7} {:>4} heap  {}", timing.tag, timing.part, timing.memory);
+}
+
+/// Saved timings, one `<tag> <part> <phase> <n> <min> <median> <mean> <stddev>`
+/// line per phase, in nanoseconds.
+pub struct Baseline {
+    stats: HashMap<(String, usize, String), Stats>,
+}
+
+impl Baseline {
+    pub fn load(path: &str) -> io::Result<Self> {
+        let lines = io::BufReader::new(fs::File::open(path)?).lines()
+            .collect::<io::Result<Vec<_>>>()?;
+        Self::read(path, 1, &lines)
+    }
+
+    /// Parses timing lines, the first of them being line `first` of `path`.
+    pub fn read(path: &str, first: usize, lines: &[String]) -> io::Result<Self> {
+        let mut stats = HashMap::new();
+        for (n, line) in lines.iter().enumerate() {
+            let f = line.split_whitespace().collect::<Vec<_>>();
+            let bad = || io::Error::new(io::ErrorKind::InvalidData,
+                                        format!("{}: line {}: malformed timing", path, first + n));
+            if f.len() != 8 { return Err(bad()); }
+            let num = |i: usize| f[i].parse::<f64>().map_err(|_| bad());
+            stats.insert(
+                (day::qualify(f[0]), f[1].parse().map_err(|_| bad())?, f[2].to_string()),
+                Stats { n: num(3)? as usize, min: num(4)?, median: num(5)?, mean: num(6)?,
+                    stddev: num(7)? });
+        }
+        Ok(Baseline { stats })
+    }
+
+    pub fn get(&self, tag: &str, part: usize, phase: &str) -> Option<&Stats> {
+        self.stats.get(&(day::qualify(tag), part, phase.to_string()))
+    }
+
+    /// The day, part and phase of every timing, in order.
+    pub fn keys(&self) -> Vec<(&str, usize, &str)> {
+        let mut keys = self.stats.keys()
+            .map(|(tag, part, phase)| (tag.as_str(), *part, phase.as_str())).collect::<Vec<_>>();
+        keys.sort();
+        keys
+    }
+}
+
+pub fn save(path: &str, timings: &[Timing]) -> io::Result<()> {
+    write(&mut fs::File::create(path)?, timings)
+}
+
+pub fn write(file: &mut impl Write, timings: &[Timing]) -> io::Result<()> {
+    for timing in timings {
+        for (phase, s) in &timing.phases() {
+            writeln!(file, "{} {} {} {} {:.0} {:.0} {:.0} {:.0}",
+                     timing.tag, timing.part, phase, s.n, s.min, s.median, s.mean, s.stddev)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats() {
+        let samples = [4, 1, 3, 2].iter().map(|&n| Duration::from_nanos(n)).collect::<Vec<_>>();
+        let s = Stats::new(&samples);
+        assert_eq!((s.n, s.min, s.median, s.mean), (4, 1.0, 2.5, 2.5));
+        assert!((s.stddev - (5.0f64 / 3.0).sqrt()).abs() < 1e-9);
+    }
+}
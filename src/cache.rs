@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use crate::day::{Answer, Day};
+
+/// Answers from earlier runs, kept in a file of lines like `<key> n 43210`.
+///
+/// The key is a hash of the day's input, its parameters and the versions of
+/// the crate and the day, so changing any of those recomputes the answer.
+/// Errors are never cached.
+#[derive(Default)]
+pub struct Cache {
+    answers: Mutex<HashMap<u64, Answer>>,
+    changed: AtomicBool,
+}
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// Continues the 64 bit FNV-1a hash of a byte string.
+pub fn fnv(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |hash, &b| (hash ^ b as u64).wrapping_mul(FNV_PRIME))
+}
+
+/// The key of a part's answer for the given input and parameter values.
+pub fn key(day: &dyn Day, part: usize, input: &[u8], params: &HashMap<String, i64>) -> u64 {
+    let hash = fnv(fnv(FNV_OFFSET, input), &[0]);
+    let mut s = format!("{} {} {} {}",
+                        env!("CARGO_PKG_VERSION"), day.id(), part, day.version());
+    for param in day.params() {
+        s += &format!(" {}={}", param.name, params.get(param.name).unwrap_or(&param.default));
+    }
+    fnv(hash, s.as_bytes())
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('\n', "\\n").replace('\r', "\\r")
+}
+
+fn unescape(s: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some(c) => unescaped.push(c),
+            None => break,
+        }
+    }
+    unescaped
+}
+
+fn encode(answer: &Answer) -> Option<String> {
+    match answer {
+        Answer::Number(n) => Some(format!("n {}", n)),
+        Answer::Text(s) => Some(format!("t {}", escape(s))),
+        Answer::Lines(lines) => Some(format!("l {}", escape(&lines.join("\n")))),
+        Answer::None | Answer::Error(_) => None,
+    }
+}
+
+fn decode(s: &str) -> Option<Answer> {
+    let (kind, value) = s.split_once(' ')?;
+    match kind {
+        "n" => value.parse().ok().map(Answer::Number),
+        "t" => Some(Answer::Text(unescape(value))),
+        "l" if value.is_empty() => Some(Answer::Lines(vec![])),
+        "l" => Some(Answer::Lines(unescape(value).split('\n').map(String::from).collect())),
+        _ => None,
+    }
+}
+
+impl Cache {
+    /// Reads the cache file, an empty cache if there is none yet.
+    pub fn load(path: &str) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(s) => Ok(Self::read(&s)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Cache::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Parses the lines of a cache file, skipping those that are not understood.
+    pub fn read(s: &str) -> Self {
+        let answers = s.lines().filter_map(|line| {
+            let (key, answer) = line.split_once(' ')?;
+            Some((u64::from_str_radix(key, 16).ok()?, decode(answer)?))
+        }).collect();
+        Cache { answers: Mutex::new(answers), changed: AtomicBool::new(false) }
+    }
+
+    pub fn get(&self, key: u64) -> Option<Answer> {
+        self.answers.lock().unwrap().get(&key).cloned()
+    }
+
+    pub fn put(&self, key: u64, answer: &Answer) {
+        if encode(answer).is_none() { return; }
+        let previous = self.answers.lock().unwrap().insert(key, answer.clone());
+        if previous.as_ref() != Some(answer) { self.changed.store(true, Ordering::Relaxed); }
+    }
+
+    /// Writes the cache file, if any answer was added.
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        if !self.changed.load(Ordering::Relaxed) { return Ok(()); }
+        let mut lines = self.answers.lock().unwrap().iter()
+            .filter_map(|(key, answer)| Some(format!("{:016x} {}", key, encode(answer)?)))
+            .collect::<Vec<_>>();
+        lines.sort();
+        let mut file = io::BufWriter::new(fs::File::create(path)?);
+        for line in lines { writeln!(file, "{}", line)?; }
+        file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day12::Day12;
+
+    #[test]
+    fn answers() {
+        let answers = [Answer::Number(-7), Answer::Text(String::from("a\\b\nc")),
+                       Answer::Lines(vec![String::from(" .X"), String::from("X. ")])];
+        for answer in &answers {
+            assert_eq!(decode(&encode(answer).unwrap()).as_ref(), Some(answer));
+        }
+        assert_eq!(encode(&Answer::Error(String::from("no solution"))), None);
+        let cache = Cache::read("00000000000000ff n 42\nxyz n 1\n0000000000000001 q 1\n");
+        assert_eq!(cache.get(0xff), Some(Answer::Number(42)));
+        assert_eq!(cache.get(1), None);
+        cache.put(2, &Answer::Error(String::from("no solution")));
+        assert_eq!(cache.get(2), None);
+        assert_eq!(fnv(FNV_OFFSET, b"a"), 0xaf63_dc4c_8601_ec8c);
+        let mut params = HashMap::new();
+        let k = key(&Day12 {}, 1, b"input", &params);
+        params.insert(String::from("steps"), 1000);
+        assert_eq!(key(&Day12 {}, 1, b"input", &params), k);
+        params.insert(String::from("steps"), 10);
+        assert_ne!(key(&Day12 {}, 1, b"input", &params), k);
+        assert_ne!(key(&Day12 {}, 2, b"input", &HashMap::new()), k);
+    }
+}
@@ -0,0 +1,358 @@
+use std::fmt;
+use std::time::Duration;
+use crate::day;
+use crate::output::Format;
+
+pub const USAGE: &str = "\
+usage: adventofcode2019 [options] <input> [selection]
+       adventofcode2019 [options] --text <input> [selection]
+       adventofcode2019 --list
+       adventofcode2019 --examples [selection]
+       adventofcode2019 generate [<year>/]<day> [--size <n>] [--seed <n>]
+       adventofcode2019 repl [<input>]
+       adventofcode2019 compare [<commit>] [--history <file>] [--baseline <file>]
+
+The input is where the days' inputs are: a prefix their tags are appended to
+(input/ reads input/01 and so on), a directory with files like 07, day07 or
+day07.txt, a pattern like inputs/day{day}.txt or - for stdin.  Inputs may be
+compressed with gzip.
+
+The selection is a comma separated list of days (7), ranges of days (1-5)
+and single parts (20:2, 1-5:1), e.g. 1-5,12,20:2.  Days of other years than
+2019 are given with the year (2018/7, 2018/1-5:1), a year alone selects all of
+its days.  Without it all days run.  The inputs of other years than 2019 are
+looked for under the year, e.g. input/2018/07.
+
+generate prints a random input for a day that has a generator, of a size that
+depends on the day, e.g. the number of moons for day 12.
+
+repl starts an interactive session to pick days, give them input, set their
+parameters and run their parts, see its help command.
+
+Every --bench run is appended to a history file, with the commit and the date.
+compare checks the last run in it against the one before, the last one of the
+given commit or a --baseline file, and fails on significant slowdowns.
+
+options:
+    --list               list the days and what their parts compute
+    --text <input>       give the input inline instead
+    --verify <file>      check the answers against a file of expected ones
+    --examples           check the answers to the puzzles' examples instead
+    --bench <n>          time every part over n runs
+    --warmup <n>         untimed runs before benchmarking (default 1)
+    --save <file>        save the benchmark timings
+    --baseline <file>    compare the benchmark timings with saved ones
+    --history <file>     where benchmark runs are kept (default .history)
+    --no-history         do not keep this benchmark run
+    -j, --jobs <n>       run the days on n threads
+    --timeout <seconds>  give up on a part that runs longer
+    --memory             report the peak heap usage and allocations of every part
+    --watch              rerun the days whose inputs change, until interrupted
+    -p, --param <tag>.<name>=<value>
+                         set a day's parameter, e.g. 12.steps=10
+    --config <file>      read parameters from a file, one per line
+    --format <format>    print the results as text (default), json, tap or csv
+    --cache <file>       where answers are cached (default .answers)
+    --no-cache           neither use nor save cached answers, as with --verify,
+                         --examples and --bench
+    --recompute          compute every answer, then cache it
+    --size <n>           the size of a generated input
+    --seed <n>           the seed of a generated input (default 1)
+    -v                   log more, -vv debug and -vvv trace messages too
+    --log <spec>         log per module like RUST_LOG, e.g. 23=debug,warn
+    -h, --help           show this text";
+
+#[derive(Debug, PartialEq)]
+pub struct UsageError(String);
+
+impl fmt::Display for UsageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { write!(f, "{}", self.0) }
+}
+
+fn error<T>(s: String) -> Result<T, UsageError> { Err(UsageError(s)) }
+
+/// Days and parts to run, as parsed from e.g. `1-5,12,20:2` or `2019/1-5`.
+#[derive(Debug, PartialEq)]
+pub struct Selection {
+    ranges: Vec<(u32, u32, u32, Option<usize>)>,
+}
+
+impl Selection {
+    pub fn all() -> Self { Selection { ranges: vec![] } }
+
+    pub fn parse(s: &str) -> Result<Self, UsageError> {
+        let day = |d: &str| match d.parse::<u32>() {
+            Ok(n) if (1..=25).contains(&n) => Ok(n),
+            _ => error(format!("invalid day \"{}\" in selection \"{}\", expected 1-25", d, s)),
+        };
+        let year = |y: &str| match y.parse::<u32>() {
+            Ok(n) if n >= 2015 => Ok(n),
+            _ => error(format!("invalid year \"{}\" in selection \"{}\"", y, s)),
+        };
+        let mut ranges = vec![];
+        for item in s.split(',') {
+            let mut split = item.splitn(2, ':');
+            let days = split.next().unwrap_or("");
+            let part = match split.next() {
+                None => None,
+                Some("1") => Some(1),
+                Some("2") => Some(2),
+                Some(p) => return error(
+                    format!("invalid part \"{}\" in selection \"{}\", expected 1 or 2", p, s)),
+            };
+            let (y, days) = match days.split_once('/') {
+                Some((y, days)) => (year(y)?, days),
+                None if days.len() == 4 && days.bytes().all(|b| b.is_ascii_digit()) =>
+                    (year(days)?, "1-25"),
+                None => (day::YEAR, days),
+            };
+            let (first, last) = match days.find('-') {
+                Some(i) => (day(&days[..i])?, day(&days[i + 1..])?),
+                None => { let d = day(days)?; (d, d) },
+            };
+            if first > last {
+                return error(format!("empty range \"{}\" in selection \"{}\"", days, s));
+            }
+            ranges.push((y, first, last, part));
+        }
+        Ok(Selection { ranges })
+    }
+
+    pub fn contains(&self, year: u32, day: u32, part: usize) -> bool {
+        self.ranges.is_empty() || self.ranges.iter().any(|&(y, first, last, p)|
+            y == year && first <= day && day <= last && p.is_none_or(|p| p == part))
+    }
+
+    pub fn parts(&self, year: u32, day: u32) -> Vec<usize> {
+        (1..=2).filter(|&part| self.contains(year, day, part)).collect()
+    }
+}
+
+pub struct Options {
+    pub input: String,
+    pub text: Option<String>,
+    pub selection: Selection,
+    pub list: bool,
+    pub help: bool,
+    pub verify: Option<String>,
+    pub examples: bool,
+    pub bench: Option<usize>,
+    pub warmup: usize,
+    pub save: Option<String>,
+    pub baseline: Option<String>,
+    pub jobs: usize,
+    pub timeout: Option<Duration>,
+    pub memory: bool,
+    pub params: Vec<String>,
+    pub config: Option<String>,
+    pub format: Format,
+    pub verbose: usize,
+    pub log: Option<String>,
+    /// The cache file, `None` with `--no-cache` and when answers are checked or timed.
+    pub cache: Option<String>,
+    pub recompute: bool,
+    pub watch: bool,
+    /// The tag of the day to generate an input for.
+    pub generate: Option<String>,
+    pub size: Option<usize>,
+    pub seed: u64,
+    /// The history file, `None` with `--no-history`.
+    pub history: Option<String>,
+    pub repl: bool,
+    /// `compare`, and the commit to compare with, if given.
+    pub compare: Option<Option<String>>,
+}
+
+impl Options {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, UsageError> {
+        let mut options = Options {
+            input: String::new(), text: None, selection: Selection::all(), list: false, help: false,
+            verify: None, examples: false, bench: None, warmup: 1, save: None, baseline: None,
+            jobs: 1, timeout: None, memory: false, params: vec![], config: None,
+            format: Format::Text, verbose: 0, log: None, cache: Some(String::from(".answers")),
+            recompute: false, watch: false, generate: None, size: None, seed: 1,
+            history: Some(String::from(".history")), repl: false, compare: None,
+        };
+        let mut args = args.into_iter();
+        let mut positional = vec![];
+        while let Some(arg) = args.next() {
+            let mut value = || args.next()
+                .ok_or_else(|| UsageError(format!("{} needs a value", arg)));
+            let number = |v: String| v.parse::<usize>()
+                .map_err(|_| UsageError(format!("{} needs a number, not \"{}\"", arg, v)));
+            match arg.as_str() {
+                "--list" => options.list = true,
+                "--text" => options.text = Some(value()?),
+                "-h" | "--help" => options.help = true,
+                "--verify" => options.verify = Some(value()?),
+                "--examples" => options.examples = true,
+                "--bench" => match number(value()?)? {
+                    0 => return error(String::from("--bench needs at least 1 run")),
+                    n => options.bench = Some(n),
+                },
+                "--warmup" => options.warmup = number(value()?)?,
+                "--save" => options.save = Some(value()?),
+                "--baseline" => options.baseline = Some(value()?),
+                "-j" | "--jobs" => options.jobs = number(value()?)?,
+                "-p" | "--param" => options.params.push(value()?),
+                "--config" => options.config = Some(value()?),
+                "--log" => options.log = Some(value()?),
+                "--cache" => options.cache = Some(value()?),
+                "--no-cache" => options.cache = None,
+                "--recompute" => options.recompute = true,
+                "--watch" => options.watch = true,
+                "--memory" => options.memory = true,
+                "--history" => options.history = Some(value()?),
+                "--no-history" => options.history = None,
+                "--size" => options.size = Some(number(value()?)?),
+                "--seed" => options.seed = number(value()?)? as u64,
+                _ if arg.len() > 1 && arg.trim_start_matches('-') == "v".repeat(arg.len() - 1) =>
+                    options.verbose += arg.len() - 1,
+                "--format" => {
+                    let v = value()?;
+                    options.format = Format::parse(&v).ok_or_else(|| UsageError(
+                        format!("{} needs one of text, json, tap or csv, not \"{}\"", arg, v)))?;
+                },
+                "--timeout" => {
+                    let v = value()?;
+                    match v.parse::<f64>() {
+                        Ok(secs) if secs > 0.0 && secs.is_finite() =>
+                            options.timeout = Some(Duration::from_secs_f64(secs)),
+                        _ => return error(
+                            format!("{} needs a positive number of seconds, not \"{}\"", arg, v)),
+                    }
+                },
+                _ if arg.starts_with('-') && arg != "-" =>
+                    return error(format!("unknown option {}", arg)),
+                _ => positional.push(arg),
+            }
+        }
+        let mut positional = positional.into_iter().peekable();
+        if positional.peek().map(String::as_str) == Some("generate") {
+            let day = positional.nth(1).ok_or_else(|| UsageError(String::from("missing day")))?;
+            let (year, d) = day.split_once('/').unwrap_or(("", &day));
+            let year = if year.is_empty() { Ok(day::YEAR) } else { year.parse::<u32>() };
+            match (year, d.parse::<u32>()) {
+                (Ok(year), Ok(n)) if (1..=25).contains(&n) =>
+                    options.generate = Some(format!("{}/{:02}", year, n)),
+                _ => return error(format!("invalid day \"{}\", expected 1-25", day)),
+            }
+        } else if positional.peek().map(String::as_str) == Some("repl") {
+            options.repl = true;
+            options.input = positional.nth(1).unwrap_or_default();
+        } else if positional.peek().map(String::as_str) == Some("compare") {
+            options.compare = Some(positional.nth(1));
+        } else if options.text.is_none() && !options.examples {
+            match positional.next() {
+                Some(input) => options.input = input,
+                None if options.list || options.help => (),
+                None => return error(String::from("missing input")),
+            }
+        }
+        if let Some(selection) = positional.next() {
+            options.selection = Selection::parse(&selection)?;
+        }
+        if let Some(extra) = positional.next() {
+            return error(format!("unexpected argument \"{}\"", extra));
+        }
+        if options.bench.is_some() && options.format != Format::Text {
+            return error(String::from("--format does not apply to --bench"));
+        }
+        if options.watch && (options.bench.is_some() || options.format != Format::Text) {
+            return error(String::from("--watch only applies to plain runs"));
+        }
+        if options.memory && options.jobs > 1 {
+            return error(String::from("--memory measures one part at a time, not with --jobs"));
+        }
+        if options.watch && options.text.is_some() {
+            return error(String::from("--watch needs input files, not --text"));
+        }
+        // Answers that are checked or timed have to be computed.
+        if options.verify.is_some() || options.examples || options.bench.is_some() {
+            options.cache = None;
+        }
+        Ok(options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(s: &str) -> Vec<String> { s.split_whitespace().map(String::from).collect() }
+
+    #[test]
+    fn selection() {
+        let s = Selection::parse("1-5,12,20:2").unwrap();
+        assert!(s.contains(2019, 1, 1) && s.contains(2019, 5, 2) && s.contains(2019, 12, 1));
+        assert!(!s.contains(2019, 6, 1) && !s.contains(2019, 20, 1) && s.contains(2019, 20, 2));
+        assert_eq!(s.parts(2019, 20), vec![2]);
+        assert_eq!(Selection::parse("07").unwrap().parts(2019, 7), vec![1, 2]);
+        assert!(Selection::all().contains(2018, 25, 2));
+        let s = Selection::parse("2018/1-5:1,2020").unwrap();
+        assert!(s.contains(2018, 3, 1) && !s.contains(2018, 3, 2) && !s.contains(2019, 3, 1));
+        assert!(s.contains(2020, 25, 2));
+        assert!(Selection::parse("2014/1").is_err());
+        assert!(Selection::parse("2018/").is_err());
+        assert!(Selection::parse("0").is_err());
+        assert!(Selection::parse("5-1").is_err());
+        assert!(Selection::parse("3:3").is_err());
+        assert!(Selection::parse("1,,2").is_err());
+        assert!(Selection::parse("1-25").unwrap().contains(2019, 25, 2));
+        let s = Selection::parse("1-12").unwrap();
+        assert!(s.contains(2019, 12, 1) && !s.contains(2019, 13, 1));
+        let s = Selection::parse("2019").unwrap();
+        assert!(s.contains(2019, 1, 1) && s.contains(2019, 25, 2) && !s.contains(2018, 1, 1));
+    }
+
+    #[test]
+    fn options() {
+        let o = Options::parse(args("input/ -j 4 --bench 3 2-3")).unwrap();
+        assert_eq!((o.input.as_str(), o.jobs, o.bench), ("input/", 4, Some(3)));
+        assert!(o.selection.contains(2019, 2, 1) && !o.selection.contains(2019, 4, 1));
+        assert!(Options::parse(args("--list")).unwrap().list);
+        assert_eq!(Options::parse(args("")).err(),
+                   Some(UsageError(String::from("missing input"))));
+        let o = Options::parse(args("--text 1,2,99 2:1")).unwrap();
+        assert_eq!((o.text.as_deref(), o.selection.parts(2019, 2)), (Some("1,2,99"), vec![1]));
+        assert!(Options::parse(args("input/ --jobs")).is_err());
+        assert!(Options::parse(args("input/ --jobs x")).is_err());
+        assert_eq!(Options::parse(args("input/ --timeout 1.5")).unwrap().timeout,
+                   Some(Duration::from_millis(1500)));
+        assert!(Options::parse(args("input/ --timeout 0")).is_err());
+        assert_eq!(Options::parse(args("input/ -p 12.steps=10 -p 24.minutes=10")).unwrap().params,
+                   vec!["12.steps=10", "24.minutes=10"]);
+        assert_eq!(Options::parse(args("input/ --format tap")).unwrap().format, Format::Tap);
+        assert!(Options::parse(args("input/ --format xml")).is_err());
+        assert!(Options::parse(args("input/ --format json --bench 3")).is_err());
+        assert!(Options::parse(args("input/ --bench 0")).is_err());
+        assert_eq!(Options::parse(args("input/ -v -vv")).unwrap().verbose, 3);
+        assert_eq!(Options::parse(args("input/")).unwrap().cache.as_deref(), Some(".answers"));
+        assert_eq!(Options::parse(args("input/ --no-cache")).unwrap().cache, None);
+        assert_eq!(Options::parse(args("input/ --verify answers")).unwrap().cache, None);
+        assert_eq!(Options::parse(args("input/ --bench 3 --cache a")).unwrap().cache, None);
+        assert!(Options::parse(args("input/ --watch 7")).unwrap().watch);
+        assert!(Options::parse(args("input/ --watch --bench 3")).is_err());
+        assert!(Options::parse(args("--text 1 --watch")).is_err());
+        assert!(Options::parse(args("input/ --memory")).unwrap().memory);
+        assert!(Options::parse(args("input/ --memory -j 2")).is_err());
+        let o = Options::parse(args("generate 7 --size 10")).unwrap();
+        assert_eq!((o.generate.as_deref(), o.size, o.seed), (Some("2019/07"), Some(10), 1));
+        assert_eq!(Options::parse(args("generate 2018/3")).unwrap().generate.as_deref(),
+                   Some("2018/03"));
+        assert!(Options::parse(args("generate 26")).is_err());
+        let o = Options::parse(args("compare abc1234 --history h")).unwrap();
+        assert_eq!((o.compare.unwrap().as_deref(), o.history.as_deref()),
+                   (Some("abc1234"), Some("h")));
+        assert_eq!(Options::parse(args("compare")).unwrap().compare, Some(None));
+        assert!(Options::parse(args("compare a b")).is_err());
+        let o = Options::parse(args("repl input/")).unwrap();
+        assert!(o.repl && o.input == "input/");
+        assert!(Options::parse(args("repl")).unwrap().repl);
+        let o = Options::parse(args("--examples 12")).unwrap();
+        assert!(o.examples && o.selection.contains(2019, 12, 1));
+        assert!(!o.selection.contains(2019, 1, 1));
+        assert!(Options::parse(args("input/ --frobnicate")).is_err());
+        assert!(Options::parse(args("input/ 1 2")).is_err());
+    }
+}
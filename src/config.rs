@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::io::BufRead;
+use crate::day;
+
+/// Parameter values that override the days' defaults, keyed by day id.
+///
+/// Values are given as `<tag>.<name>=<value>`, e.g. `12.steps=10` or
+/// `2018/12.steps=10` for a day of another year than 2019, on the
+/// command line or one per line in a file, where blank lines and lines
+/// starting with `#` are ignored.
+#[derive(Default)]
+pub struct Config {
+    values: HashMap<String, HashMap<String, i64>>,
+}
+
+impl Config {
+    pub fn load(path: &str) -> io::Result<Self> {
+        Self::read(io::BufReader::new(fs::File::open(path)?))
+    }
+
+    pub fn read(reader: impl BufRead) -> io::Result<Self> {
+        let mut config = Config::default();
+        for (n, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() || line.starts_with('#') { continue; }
+            config.set(&line).map_err(|e|
+                io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", n + 1, e)))?;
+        }
+        Ok(config)
+    }
+
+    pub fn set(&mut self, s: &str) -> Result<(), String> {
+        let bad = || format!("expected \"<tag>.<name>=<value>\", not \"{}\"", s);
+        let (key, value) = s.split_once('=').ok_or_else(bad)?;
+        let (tag, name) = key.trim().split_once('.').ok_or_else(bad)?;
+        let value = value.trim().parse::<i64>().map_err(|_| bad())?;
+        self.values.entry(day::qualify(tag)).or_default().insert(name.to_string(), value);
+        Ok(())
+    }
+
+    /// Checks that every value belongs to a parameter one of the days declares
+    /// and is within its bounds.
+    pub fn check(&self, days: &[&dyn day::Day]) -> Result<(), String> {
+        for (tag, values) in &self.values {
+            let day = days.iter().find(|d| d.id() == *tag)
+                .ok_or_else(|| format!("no day {}", tag))?;
+            for (name, &value) in values { day::check_param(*day, name, value)?; }
+        }
+        Ok(())
+    }
+
+    /// The values given for a day's parameters.
+    pub fn get(&self, tag: &str) -> HashMap<String, i64> {
+        self.values.get(&day::qualify(tag)).cloned().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day::Day;
+    use crate::day12::Day12;
+
+    #[test]
+    fn values() {
+        let mut config = Config::read("# comment\n12.steps = 10\n".as_bytes()).unwrap();
+        config.set("24.minutes=10").unwrap();
+        assert_eq!(config.get("12").get("steps"), Some(&10));
+        assert_eq!(config.get("24").get("minutes"), Some(&10));
+        assert!(config.get("01").is_empty());
+        assert_eq!(config.get("2019/12").get("steps"), Some(&10));
+        assert!(config.check(&[&Day12 {}]).is_err());
+        config.set("2018/12.steps=5").unwrap();
+        assert_eq!(config.get("2018/12").get("steps"), Some(&5));
+        assert!(Config::read("12 steps 10\n".as_bytes()).is_err());
+        assert!(config.set("12.steps=x").is_err());
+        let config = Config::read("12.stepz=10\n".as_bytes()).unwrap();
+        assert_eq!(config.check(&[&Day12 {}]).err(),
+                   Some(String::from("day 2019/12 has no parameter \"stepz\"")));
+        assert_eq!(Day12 {}.params()[0].name, "steps");
+        let mut config = Config::default();
+        config.set("1.x=3").unwrap();
+        assert_eq!(config.check(&[&Day12 {}]).err(), Some(String::from("no day 2019/01")));
+        config = Config::default();
+        config.set("12.steps=-1").unwrap();
+        assert!(config.check(&[&Day12 {}]).is_err());
+        config.set("2019/12.steps=5").unwrap();
+        assert_eq!(config.check(&[&Day12 {}]), Ok(()));
+    }
+}
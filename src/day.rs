@@ -1,7 +1,517 @@
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
 use std::io;
+use std::panic;
+use std::sync::{mpsc, Arc, Mutex, Once};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+use crate::error::{self, Error, Fault};
+use crate::generate::Rng;
 
-pub trait Day {
+#[derive(Debug, Clone, PartialEq)]
+pub enum Answer {
+    None,
+    Number(i64),
+    Text(String),
+    Lines(Vec<String>),
+    Error(String),
+}
+
+impl Answer {
+    pub fn is_error(&self) -> bool {
+        matches!(self, Answer::Error(_))
+    }
+}
+
+impl fmt::Display for Answer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Answer::None => write!(f, "-"),
+            Answer::Number(n) => write!(f, "{}", n),
+            Answer::Text(s) => write!(f, "{}", s),
+            Answer::Lines(lines) => {
+                for line in lines { write!(f, "\n{}", line)?; }
+                Ok(())
+            },
+            Answer::Error(e) => write!(f, "error: {}", e),
+        }
+    }
+}
+
+impl From<i32> for Answer {
+    fn from(n: i32) -> Self { Answer::Number(n as i64) }
+}
+
+impl From<i64> for Answer {
+    fn from(n: i64) -> Self { Answer::Number(n) }
+}
+
+impl From<usize> for Answer {
+    fn from(n: usize) -> Self { Answer::Number(n as i64) }
+}
+
+impl From<String> for Answer {
+    fn from(s: String) -> Self { Answer::Text(s) }
+}
+
+impl From<Vec<String>> for Answer {
+    fn from(lines: Vec<String>) -> Self { Answer::Lines(lines) }
+}
+
+impl<T: Into<Answer>> From<Option<T>> for Answer {
+    fn from(o: Option<T>) -> Self {
+        o.map_or(Answer::Error(String::from("no solution")), |v| v.into())
+    }
+}
+
+impl<T: Into<Answer>> From<error::Result<T>> for Answer {
+    fn from(r: error::Result<T>) -> Self {
+        r.map_or_else(|e| Answer::Error(e.to_string()), |v| v.into())
+    }
+}
+
+/// A number the puzzle fixes that a day takes as a parameter instead, e.g. how
+/// many steps to simulate, so that variants and the examples can be run.
+pub struct Param {
+    pub name: &'static str,
+    pub default: i64,
+    /// The bounds of the values the day can solve with, inclusive.
+    pub min: i64,
+    pub max: i64,
+    pub help: &'static str,
+}
+
+/// Checks that a day has the named parameter and that the value is within its bounds.
+pub fn check_param(day: &dyn Day, name: &str, value: i64) -> Result<(), String> {
+    let param = day.params().iter().find(|p| p.name == name)
+        .ok_or_else(|| format!("day {} has no parameter \"{}\"", day.id(), name))?;
+    if param.min <= value && value <= param.max { return Ok(()); }
+    let bounds = if param.max == i64::MAX {
+        format!("at least {}", param.min)
+    } else {
+        format!("{} to {}", param.min, param.max)
+    };
+    Err(format!("day {} parameter \"{}\" must be {}, not {}", day.id(), name, bounds, value))
+}
+
+/// The year of the days that are given without one, e.g. `07` for `2019/07`.
+pub const YEAR: u32 = 2019;
+
+/// A tag as given by the user, with the year added unless it has one and the
+/// day padded to two digits, so `1` and `2019/01` are the same day.
+pub fn qualify(tag: &str) -> String {
+    let (year, day) = match tag.split_once('/') {
+        Some((year, day)) => (year.to_string(), day),
+        None => (YEAR.to_string(), tag),
+    };
+    match day.parse::<u32>() {
+        Ok(day) => format!("{}/{:02}", year, day),
+        Err(_) => format!("{}/{}", year, day),
+    }
+}
+
+/// A day's input as parsed by its `Puzzle`, with the type left to the day.
+pub type Parsed = Box<dyn Any>;
+
+/// How a day solves its puzzle: the input is parsed once and both parts solve
+/// from the parsed value, so that part 2 can build on what part 1 found.
+pub trait Puzzle {
+    type Input: 'static;
+    fn parse_input(&self, input: &mut dyn io::Read) -> error::Result<Self::Input>;
+    fn part1(&self, _input: &Self::Input) -> Answer { Answer::None }
+    fn part2(&self, _input: &Self::Input) -> Answer { Answer::None }
+}
+
+/// A `Puzzle` with its input type erased, which is how the runner calls it.
+pub trait Solve {
+    fn read(&self, input: &mut dyn io::Read) -> error::Result<Parsed>;
+    fn solve(&self, part: usize, input: &Parsed) -> Answer;
+}
+
+impl<P: Puzzle> Solve for P {
+    fn read(&self, input: &mut dyn io::Read) -> error::Result<Parsed> {
+        Ok(Box::new(self.parse_input(input)?))
+    }
+
+    fn solve(&self, part: usize, input: &Parsed) -> Answer {
+        match input.downcast_ref::<P::Input>() {
+            Some(input) if part == 1 => self.part1(input),
+            Some(input) => self.part2(input),
+            None => Answer::Error(String::from("input parsed by another day")),
+        }
+    }
+}
+
+/// What the runner knows of a day, which solves it as a `Puzzle`.
+pub trait Day: Solve + Sync {
+    /// The day of the month, e.g. `07`.
     fn tag(&self) -> &str;
-    fn part1(&self, _input: &dyn Fn() -> Box<dyn io::Read>) {}
-    fn part2(&self, _input: &dyn Fn() -> Box<dyn io::Read>) {}
-}
\ No newline at end of file
+    fn year(&self) -> u32 { YEAR }
+    /// The tag with the year, e.g. `2019/07`, which is how the runner knows the day.
+    fn id(&self) -> String { format!("{}/{}", self.year(), self.tag()) }
+    fn title(&self) -> &str;
+    /// What the two parts compute, empty for a part that has no puzzle.
+    fn parts(&self) -> [&str; 2];
+    fn params(&self) -> &[Param] { &[] }
+    /// Bumped when the solution changes, so that cached answers are recomputed.
+    fn version(&self) -> u32 { 1 }
+    /// A random valid input of about the given size, if the day has a generator.
+    fn generate(&self, _rng: &mut Rng, _size: Option<usize>) -> Option<String> { None }
+    /// The value of a parameter as given for the running part, or its default.
+    fn param(&self, name: &str) -> i64 {
+        CURRENT.with(|c| c.borrow().as_ref().and_then(|c| c.0.params.get(name).copied()))
+            .or_else(|| self.params().iter().find(|p| p.name == name).map(|p| p.default))
+            .unwrap_or_else(|| panic!("day {} has no parameter \"{}\"", self.id(), name))
+    }
+}
+
+/// A day, submitted by its module with `inventory::submit!`.
+pub struct Registration(pub &'static dyn Day);
+
+inventory::collect!(Registration);
+
+/// All the registered days, by year and tag.
+pub fn registry() -> Result<Vec<&'static dyn Day>, String> {
+    ordered(inventory::iter::<Registration>.into_iter().map(|r| r.0).collect())
+}
+
+fn ordered(mut days: Vec<&dyn Day>) -> Result<Vec<&dyn Day>, String> {
+    days.sort_by(|a, b| (a.year(), a.tag()).cmp(&(b.year(), b.tag())));
+    match days.windows(2).find(|w| w[0].id() == w[1].id()) {
+        Some(w) => Err(format!("day {} is registered twice, as \"{}\" and \"{}\"",
+                               w[0].id(), w[0].title(), w[1].title())),
+        None => Ok(days),
+    }
+}
+
+/// Cancellation state of a running part, shared with the threads it spawns.
+///
+/// Cancelling is cooperative, loops that may run for long should check it.
+#[derive(Clone, Default)]
+pub struct Cancel(Arc<Context>);
+
+#[derive(Default)]
+struct Context {
+    cancelled: AtomicBool,
+    panic: Mutex<Option<String>>,
+    params: HashMap<String, i64>,
+    input: String,
+    day: String,
+    part: Option<usize>,
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<Cancel>> = const { RefCell::new(None) };
+}
+
+impl Cancel {
+    /// The state of the part running on this thread, a fresh one outside of `run`.
+    pub fn current() -> Self {
+        CURRENT.with(|c| c.borrow().clone()).unwrap_or_default()
+    }
+
+    pub fn cancel(&self) { self.0.cancelled.store(true, Ordering::Relaxed); }
+
+    pub fn is_cancelled(&self) -> bool { self.0.cancelled.load(Ordering::Relaxed) }
+
+    pub fn check(&self) -> error::Result<()> {
+        if !self.is_cancelled() { return Ok(()); }
+        Err(Error::Timeout(error::Context::current(), None))
+    }
+}
+
+/// The day running on this thread and its part, which is unknown while the
+/// input is parsed.
+pub fn running() -> Option<(String, Option<usize>)> {
+    CURRENT.with(|c| c.borrow().as_ref().map(|c| (c.0.day.clone(), c.0.part)))
+}
+
+/// The name of the input the running part reads, e.g. its file.
+pub fn input_name() -> Option<String> {
+    CURRENT.with(|c| c.borrow().as_ref().map(|c| c.0.input.clone()))
+}
+
+/// Spawns a thread that belongs to the running part, so it sees its
+/// cancellation and its panics are reported with the part.
+pub fn spawn<F, T>(f: F) -> thread::JoinHandle<T>
+    where F: FnOnce() -> T + Send + 'static, T: Send + 'static {
+    let cancel = CURRENT.with(|c| c.borrow().clone());
+    thread::spawn(move || {
+        CURRENT.with(|c| *c.borrow_mut() = cancel);
+        f()
+    })
+}
+
+/// Waits for a thread from `spawn` and gives back what it returned.  A panic
+/// in it is reported with the part already, so it only stands in for it here.
+pub fn join<T>(handle: thread::JoinHandle<error::Result<T>>) -> error::Result<T> {
+    handle.join().unwrap_or_else(|_| {
+        Err(Error::Panic(error::Context::current(), String::from("a thread panicked")))
+    })
+}
+
+/// Gives back `result`, unless it failed on a channel closed by the thread
+/// `handle` stopping, which is then joined for why it stopped.
+pub fn or_join<T, U>(result: error::Result<T>, handle: thread::JoinHandle<error::Result<U>>)
+    -> error::Result<T> {
+    match result {
+        Err(Error::Intcode(_, Fault::ChannelClosed)) => { join(handle)?; result },
+        result => result,
+    }
+}
+
+/// Receives like `Receiver::recv`, but gives up when the part is cancelled.
+pub fn recv<T>(receiver: &mpsc::Receiver<T>) -> error::Result<T> {
+    let cancel = Cancel::current();
+    loop {
+        match receiver.recv_timeout(Duration::from_millis(10)) {
+            Ok(v) => return Ok(v),
+            Err(mpsc::RecvTimeoutError::Timeout) => cancel.check()?,
+            Err(mpsc::RecvTimeoutError::Disconnected) => return Err(Error::from(mpsc::RecvError)),
+        }
+    }
+}
+
+fn install_hook() {
+    static HOOK: Once = Once::new();
+    HOOK.call_once(|| {
+        let default = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            let cancel = match CURRENT.with(|c| c.borrow().clone()) {
+                Some(cancel) => cancel,
+                None => return default(info),
+            };
+            let payload = info.payload();
+            let message = payload.downcast_ref::<&str>().copied()
+                .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+                .unwrap_or("unknown cause");
+            let location = info.location().map(|l| format!(" at {}:{}", l.file(), l.line()));
+            // The first panic is the cause, later ones tend to be threads losing their peers.
+            cancel.0.panic.lock().unwrap().get_or_insert_with(||
+                format!("panicked{}: {}", location.unwrap_or_default(), message));
+        }));
+    });
+}
+
+/// Runs a step of a day, turning a panic into an error rather than letting it
+/// take down the remaining days.
+///
+/// The step is solving `part`, or parsing without one.  The input is known as
+/// `name` and the parameters given in `params` override the day's defaults
+/// while it runs.
+/// With a timeout the step is cancelled when it runs out of time.  The step is
+/// cancelled when it returns as well, to stop any threads it left running.
+fn guarded<T>(f: impl FnOnce() -> T, day: &dyn Day, part: Option<usize>, name: &str,
+              params: &HashMap<String, i64>, timeout: Option<Duration>) -> error::Result<T> {
+    install_hook();
+    let cancel = Cancel(Arc::new(Context {
+        params: params.clone(), input: name.to_string(), day: day.id(), part,
+        ..Context::default()
+    }));
+    let (done, finished) = mpsc::channel::<()>();
+    if let Some(timeout) = timeout {
+        let cancel = cancel.clone();
+        thread::spawn(move || if let Err(mpsc::RecvTimeoutError::Timeout)
+            = finished.recv_timeout(timeout) { cancel.cancel(); });
+    }
+    CURRENT.with(|c| *c.borrow_mut() = Some(cancel.clone()));
+    let result = panic::catch_unwind(panic::AssertUnwindSafe(f));
+    CURRENT.with(|c| *c.borrow_mut() = None);
+    drop(done);
+    let timed_out = cancel.is_cancelled();
+    cancel.cancel();
+    let panic = cancel.0.panic.lock().unwrap().take();
+    let context = || error::Context { day: Some(day.id()), part, ip: None };
+    // A panic in a thread of the step fails it even when the step itself got on without it.
+    match (result, panic, timeout) {
+        (_, _, Some(timeout)) if timed_out => Err(Error::Timeout(context(), Some(timeout))),
+        (Ok(value), None, _) => Ok(value),
+        (_, panic, _) => Err(Error::Panic(context(), panic.unwrap_or_default())),
+    }
+}
+
+/// Parses the input of a day once for the parts to solve from, guarded like `run`.
+pub fn parse(day: &dyn Day, input: &dyn Fn() -> Box<dyn io::Read>, name: &str,
+             params: &HashMap<String, i64>, timeout: Option<Duration>) -> error::Result<Parsed> {
+    guarded(|| day.read(&mut *input()), day, None, name, params, timeout)?
+}
+
+/// Solves one part of a day from its parsed input, guarded like `run`.
+pub fn solve(day: &dyn Day, part: usize, input: &Parsed, name: &str,
+             params: &HashMap<String, i64>, timeout: Option<Duration>) -> Answer {
+    guarded(|| day.solve(part, input), day, Some(part), name, params, timeout)
+        .unwrap_or_else(|e| Answer::Error(e.to_string()))
+}
+
+/// Runs one part of a day, parsing its input and solving it, with a panic
+/// turned into an error answer and a timeout for each of the two steps.
+pub fn run(day: &dyn Day, part: usize, input: &dyn Fn() -> Box<dyn io::Read>, name: &str,
+           params: &HashMap<String, i64>, timeout: Option<Duration>) -> Answer {
+    match parse(day, input, name, params, timeout) {
+        Ok(parsed) => solve(day, part, &parsed, name, params, timeout),
+        Err(e) => Answer::Error(e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A day for the tests, with its parser and parts given as functions.
+    struct Fixture<I> {
+        title: &'static str,
+        year: u32,
+        params: &'static [Param],
+        parse: fn(&mut dyn io::Read) -> error::Result<I>,
+        parts: [fn(&Fixture<I>, &I) -> Answer; 2],
+    }
+
+    impl<I: 'static> Day for Fixture<I> {
+        fn tag(&self) -> &str { "00" }
+        fn year(&self) -> u32 { self.year }
+        fn title(&self) -> &str { self.title }
+        fn parts(&self) -> [&str; 2] { ["", ""] }
+        fn params(&self) -> &[Param] { self.params }
+    }
+
+    impl<I: 'static> Puzzle for Fixture<I> {
+        type Input = I;
+        fn parse_input(&self, input: &mut dyn io::Read) -> error::Result<I> { (self.parse)(input) }
+        fn part1(&self, input: &I) -> Answer { (self.parts[0])(self, input) }
+        fn part2(&self, input: &I) -> Answer { (self.parts[1])(self, input) }
+    }
+
+    /// A day of 2019 that reads nothing and has no answers.
+    fn fixture(title: &'static str) -> Fixture<()> {
+        let none = |_: &Fixture<()>, _: &()| Answer::None;
+        Fixture { title, year: YEAR, params: &[], parse: |_| Ok(()), parts: [none, none] }
+    }
+
+    /// Panics in part 1 and answers its parameter in part 2.
+    fn broken() -> Fixture<()> {
+        Fixture {
+            params: &[Param { name: "n", default: 2, min: 0, max: 9, help: "" }],
+            parts: [|_, _| "x".parse::<i64>().unwrap().into(), |day, _| day.param("n").into()],
+            ..fixture("Broken")
+        }
+    }
+
+    /// Reads lines, counting them in part 1 and joining them in part 2.
+    fn lines() -> Fixture<Vec<String>> {
+        Fixture {
+            title: "Lines", year: YEAR, params: &[],
+            parse: |input| {
+                let mut s = String::new();
+                input.read_to_string(&mut s)?;
+                if s.is_empty() { return Err(Error::input("no lines")); }
+                Ok(s.lines().map(String::from).collect())
+            },
+            parts: [|_, input| input.len().into(), |_, input| input.concat().into()],
+        }
+    }
+
+    /// Waits in part 1 on a thread that waits for a value that never comes.
+    fn stuck() -> Fixture<()> {
+        Fixture {
+            parts: [|_, _| {
+                let (sender, receiver) = mpsc::channel::<i64>();
+                let worker = spawn(move || recv(&receiver).map(|_| ()).map_err(|e| e.to_string()));
+                let result = worker.join().unwrap();
+                drop(sender);
+                result.map_or_else(Answer::Error, |_| Answer::None)
+            }, |_, _| Answer::None],
+            ..fixture("Stuck")
+        }
+    }
+
+    /// Answers part 1 after ignoring that its thread panicked.
+    fn abandoned() -> Fixture<()> {
+        Fixture {
+            parts: [|_, _| {
+                let _ = spawn(|| panic!("lost")).join();
+                Answer::Number(1)
+            }, |_, _| Answer::None],
+            ..fixture("Abandoned")
+        }
+    }
+
+    #[test]
+    fn isolated() {
+        let input = || Box::new(io::empty()) as Box<dyn io::Read>;
+        match run(&broken(), 1, &input, "", &HashMap::new(), None) {
+            Answer::Error(e) => assert!(e.starts_with("panicked at src/day.rs:"), "{}", e),
+            a => panic!("expected an error, got {:?}", a),
+        }
+        assert_eq!(run(&broken(), 2, &input, "", &HashMap::new(), None), Answer::Number(2));
+    }
+
+    #[test]
+    fn params() {
+        let input = || Box::new(io::empty()) as Box<dyn io::Read>;
+        let params = [(String::from("n"), 3)].iter().cloned().collect();
+        assert_eq!(run(&broken(), 2, &input, "", &params, None), Answer::Number(3));
+        assert_eq!(broken().part2(&()), Answer::Number(2));
+        assert_eq!(check_param(&broken(), "n", 9), Ok(()));
+        assert_eq!(check_param(&broken(), "n", -1).err(), Some(String::from(
+            "day 2019/00 parameter \"n\" must be 0 to 9, not -1")));
+        assert!(check_param(&broken(), "m", 1).is_err());
+    }
+
+    #[test]
+    fn parsed_once() {
+        let opened = std::cell::Cell::new(0);
+        let input = || {
+            opened.set(opened.get() + 1);
+            Box::new(io::Cursor::new("a\nb\n")) as Box<dyn io::Read>
+        };
+        let parsed = parse(&lines(), &input, "", &HashMap::new(), None).unwrap();
+        assert_eq!(solve(&lines(), 1, &parsed, "", &HashMap::new(), None), Answer::Number(2));
+        assert_eq!(solve(&lines(), 2, &parsed, "", &HashMap::new(), None),
+                   Answer::Text(String::from("ab")));
+        assert_eq!(opened.get(), 1);
+        assert_eq!(solve(&broken(), 1, &parsed, "", &HashMap::new(), None),
+                   Answer::Error(String::from("input parsed by another day")));
+        let empty = || Box::new(io::empty()) as Box<dyn io::Read>;
+        assert_eq!(run(&lines(), 1, &empty, "", &HashMap::new(), None),
+                   Answer::Error(String::from("no lines")));
+        let e = parse(&lines(), &empty, "", &HashMap::new(), None).err().unwrap();
+        assert_eq!(e.context(),
+                   &error::Context { day: Some(String::from("2019/00")), part: None, ip: None });
+    }
+
+    #[test]
+    fn registered() {
+        let days = registry().unwrap();
+        assert_eq!(days.len(), 25);
+        assert_eq!((days[0].id().as_str(), days[24].id().as_str()), ("2019/01", "2019/25"));
+        assert_eq!(ordered(vec![&broken(), &stuck()]).err(), Some(String::from(
+            "day 2019/00 is registered twice, as \"Broken\" and \"Stuck\"")));
+        let earlier = Fixture { year: 2018, ..fixture("Earlier") };
+        assert_eq!(ordered(vec![&stuck(), &earlier]).unwrap()[0].year(), 2018);
+        assert_eq!((qualify("07"), qualify("2018/07")),
+                   (String::from("2019/07"), String::from("2018/07")));
+        assert_eq!((qualify("7"), qualify("2018/1")),
+                   (String::from("2019/07"), String::from("2018/01")));
+    }
+
+    #[test]
+    fn thread_panic() {
+        let input = || Box::new(io::empty()) as Box<dyn io::Read>;
+        match run(&abandoned(), 1, &input, "", &HashMap::new(), None) {
+            Answer::Error(e) =>
+                assert!(e.starts_with("panicked at src/day.rs:") && e.ends_with("lost"), "{}", e),
+            a => panic!("expected an error, got {:?}", a),
+        }
+    }
+
+    #[test]
+    fn timeout() {
+        let input = || Box::new(io::empty()) as Box<dyn io::Read>;
+        assert_eq!(run(&stuck(), 1, &input, "", &HashMap::new(),
+                       Some(Duration::from_millis(50))),
+                   Answer::Error(String::from("timed out after 50ms")));
+    }
+}
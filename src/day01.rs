@@ -1,29 +1,48 @@
-use std::error;
 use std::io;
-use std::io::BufRead;
 use crate::day;
-
-pub type BoxResult<T> = Result<T, Box<dyn error::Error>>;
+use crate::error;
+use crate::parse;
 
 pub struct Day01 {}
 
+inventory::submit! { day::Registration(&Day01 {}) }
+
 impl day::Day for Day01 {
     fn tag(&self) -> &str { "01" }
 
-    fn part1(&self, input: &dyn Fn() -> Box<dyn io::Read>) {
-        println!("{:?}", self.part1_impl(&mut *input()));
+    fn title(&self) -> &str { "The Tyranny of the Rocket Equation" }
+
+    fn parts(&self) -> [&str; 2] {
+        ["fuel required for the modules",
+         "fuel required for the modules and the fuel itself"]
     }
+}
+
+impl day::Puzzle for Day01 {
+    type Input = Vec<i32>;
 
-    fn part2(&self, input: &dyn Fn() -> Box<dyn io::Read>) {
-        println!("{:?}", self.part2_impl(&mut *input()));
+    fn parse_input(&self, input: &mut dyn io::Read) -> error::Result<Vec<i32>> {
+        self.parse(input)
+    }
+
+    fn part1(&self, masses: &Vec<i32>) -> day::Answer {
+        self.part1_impl(masses).into()
+    }
+
+    fn part2(&self, masses: &Vec<i32>) -> day::Answer {
+        self.part2_impl(masses).into()
     }
 }
 
 impl Day01 {
-    fn part1_impl(self: &Self, input: &mut dyn io::Read) -> BoxResult<i32> {
-        let reader = io::BufReader::new(input);
-        Ok(reader.lines().map(|s| s.unwrap().parse::<i32>().unwrap() / 3 - 2)
-            .sum())
+    /// The module masses, one per line.
+    pub fn parse(self: &Self, input: &mut dyn io::Read) -> error::Result<Vec<i32>> {
+        let lines = parse::lines(input)?;
+        Ok(lines.iter().map(|l| l.field(&l.text, "a mass")).collect::<Result<_, _>>()?)
+    }
+
+    pub fn part1_impl(self: &Self, masses: &[i32]) -> error::Result<i32> {
+        Ok(masses.iter().map(|m| m / 3 - 2).sum())
     }
 
     fn fuel_2(&self, m: i32) -> i32 {
@@ -31,11 +50,8 @@ impl Day01 {
         if f <= 0 {0 } else { f + self.fuel_2(f) }
     }
 
-    fn part2_impl(self: &Self, input: &mut dyn io::Read) -> BoxResult<i32> {
-        let reader = io::BufReader::new(input);
-        Ok(reader.lines().map(|s|
-            self.fuel_2(s.unwrap().parse::<i32>().unwrap()))
-            .sum())
+    pub fn part2_impl(self: &Self, masses: &[i32]) -> error::Result<i32> {
+        Ok(masses.iter().map(|&m| self.fuel_2(m)).sum())
     }
 }
 
@@ -44,7 +60,8 @@ mod tests {
     use super::*;
 
     fn test1(s: &str, f: i32) {
-        assert_eq!(Day01 {}.part1_impl(&mut s.as_bytes()).unwrap(), f);
+        let d = Day01 {};
+        assert_eq!(d.part1_impl(&d.parse(&mut s.as_bytes()).unwrap()).unwrap(), f);
     }
 
     #[test]
@@ -56,7 +73,8 @@ mod tests {
     }
 
     fn test2(s: &str, f: i32) {
-        assert_eq!(Day01 {}.part2_impl(&mut s.as_bytes()).unwrap(), f);
+        let d = Day01 {};
+        assert_eq!(d.part2_impl(&d.parse(&mut s.as_bytes()).unwrap()).unwrap(), f);
     }
 
     #[test]
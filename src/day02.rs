@@ -1,39 +1,56 @@
-use simple_error::bail;
-use std::error;
 use std::io;
-use std::io::BufRead;
 use crate::day;
-
-pub type BoxResult<T> = Result<T, Box<dyn error::Error>>;
+use crate::error::{self, Error, Fault};
+use crate::parse;
 
 pub struct Day02 {}
 
+inventory::submit! { day::Registration(&Day02 {}) }
+
 impl day::Day for Day02 {
     fn tag(&self) -> &str { "02" }
 
-    fn part1(&self, input: &dyn Fn() -> Box<dyn io::Read>) {
-        println!("{:?}", self.part1_impl(&mut *input(), true, 0));
+    fn title(&self) -> &str { "1202 Program Alarm" }
+
+    fn parts(&self) -> [&str; 2] {
+        ["position 0 after restoring the 1202 program alarm state",
+         "100 * noun + verb producing 19690720"]
     }
+}
+
+impl day::Puzzle for Day02 {
+    type Input = Vec<usize>;
 
-    fn part2(&self, input: &dyn Fn() -> Box<dyn io::Read>) {
-        println!("{:?}", self.part2_impl(&mut *input(), 19690720));
+    fn parse_input(&self, input: &mut dyn io::Read) -> error::Result<Vec<usize>> {
+        self.parse(input)
+    }
+
+    fn part1(&self, p: &Vec<usize>) -> day::Answer {
+        self.part1_impl(p, true, 0).into()
+    }
+
+    fn part2(&self, p: &Vec<usize>) -> day::Answer {
+        self.part2_impl(p, 19690720).into()
     }
 }
 
 impl Day02 {
-    fn part1_impl(self: &Self, input: &mut dyn io::Read, reset: bool, i: usize)
-        -> BoxResult<usize> {
-        let reader = io::BufReader::new(input);
-        let mut p = reader.split(b',')
-            .map(|v| String::from_utf8(v.unwrap()).unwrap())
-            .map(|s| s.trim_end().parse::<usize>().unwrap())
-            .collect::<Vec<_>>();
+    /// The Intcode program.
+    pub fn parse(self: &Self, input: &mut dyn io::Read) -> error::Result<Vec<usize>> {
+        parse::numbers(input, "a non-negative integer")
+    }
+
+    pub fn part1_impl(self: &Self, p: &[usize], reset: bool, i: usize)
+        -> error::Result<usize> {
+        let mut p = p.to_vec();
         if reset {
             p[1] = 12;
             p[2] = 2;
         }
         let mut ip = 0;
+        let cancel = day::Cancel::current();
         while p[ip] != 99 {
+            cancel.check()?;
             match p[ip] {
                 1 => {
                     let a = p[p[ip + 1]];
@@ -47,26 +64,23 @@ impl Day02 {
                     let c = p[ip + 3];
                     p[c] = a * b;
                 },
-                _ => bail!("unknown opcode"),
+                op => return Err(Error::intcode(ip, Fault::UnknownOpcode(op as i64))),
             };
             ip += 4;
         }
         Ok(p[i])
     }
 
-    fn part2_impl(self: &Self, input: &mut dyn io::Read, o: usize) -> BoxResult<usize> {
-        let reader = io::BufReader::new(input);
-        let p0 = reader.split(b',')
-            .map(|v| String::from_utf8(v.unwrap()).unwrap())
-            .map(|s| s.trim_end().parse::<usize>().unwrap())
-            .collect::<Vec<_>>();
+    pub fn part2_impl(self: &Self, p0: &[usize], o: usize) -> error::Result<usize> {
+        let cancel = day::Cancel::current();
         for noun in 0..100 {
             for verb in 0..100 {
-                let mut p = p0.clone();
+                let mut p = p0.to_vec();
                 p[1] = noun;
                 p[2] = verb;
                 let mut ip = 0;
                 while p[ip] != 99 {
+                    cancel.check()?;
                     match p[ip] {
                         1 => {
                             let a = p[p[ip + 1]];
@@ -80,14 +94,14 @@ impl Day02 {
                             let c = p[ip + 3];
                             p[c] = a * b;
                         },
-                        _ => bail!("unknown opcode"),
+                        op => return Err(Error::intcode(ip, Fault::UnknownOpcode(op as i64))),
                     };
                     ip += 4;
                 }
                 if p[0] == o { return Ok(100 * noun + verb); }
             }
         }
-        bail!("not found")
+        Err(Error::no_solution("no noun and verb give the output"))
     }
 }
 
@@ -96,8 +110,8 @@ mod tests {
     use super::*;
 
     fn test1(s: &str, i: usize, v: usize) {
-        assert_eq!(
-            Day02 {}.part1_impl(&mut s.as_bytes(), false, i).unwrap(), v);
+        let d = Day02 {};
+        assert_eq!(d.part1_impl(&d.parse(&mut s.as_bytes()).unwrap(), false, i).unwrap(), v);
     }
 
     #[test]
@@ -1,45 +1,82 @@
-use simple_error::bail;
 use std::collections;
-use std::error;
 use std::io;
-use std::io::BufRead;
 use crate::day;
-
-pub type BoxResult<T> = Result<T, Box<dyn error::Error>>;
+use crate::error::{self, Error};
+use crate::generate::Rng;
+use crate::parse;
 
 pub struct Day03 {}
 
+inventory::submit! { day::Registration(&Day03 {}) }
+
 impl day::Day for Day03 {
     fn tag(&self) -> &str { "03" }
 
-    fn part1(&self, input: &dyn Fn() -> Box<dyn io::Read>) {
-        println!("{:?}", self.part1_impl(&mut *input()));
+    fn title(&self) -> &str { "Crossed Wires" }
+
+    fn parts(&self) -> [&str; 2] {
+        ["distance to the closest wire intersection",
+         "fewest combined steps to an intersection"]
+    }
+
+    fn generate(&self, rng: &mut Rng, size: Option<usize>) -> Option<String> {
+        Some(self.generate_impl(rng, size.unwrap_or(20)))
+    }
+}
+
+impl day::Puzzle for Day03 {
+    type Input = Vec<Vec<(char, i32)>>;
+
+    fn parse_input(&self, input: &mut dyn io::Read) -> error::Result<Vec<Vec<(char, i32)>>> {
+        self.parse(input)
     }
 
-    fn part2(&self, input: &dyn Fn() -> Box<dyn io::Read>) {
-        println!("{:?}", self.part2_impl(&mut *input()));
+    fn part1(&self, wires: &Vec<Vec<(char, i32)>>) -> day::Answer {
+        self.part1_impl(wires).into()
+    }
+
+    fn part2(&self, wires: &Vec<Vec<(char, i32)>>) -> day::Answer {
+        self.part2_impl(wires).into()
     }
 }
 
 impl Day03 {
     fn dist(&self, p: (i32, i32)) -> i32 { p.0.abs() + p.1.abs() }
 
-    fn part1_impl(self: &Self, input: &mut dyn io::Read)
-        -> BoxResult<Option<i32>> {
-        let reader = io::BufReader::new(input);
-        let lines = reader.lines().map(|l| l.unwrap());
+    /// Two wires of `size` moves, which start out crossing at (10, 5).
+    pub fn generate_impl(self: &Self, rng: &mut Rng, size: usize) -> String {
+        let mut wire = |start: &str| {
+            let mut moves = vec![start.to_string()];
+            moves.extend((0..size).map(|_| format!("{}{}", rng.pick(&['U', 'D', 'L', 'R']),
+                                                   rng.range(1, 100))));
+            moves.join(",")
+        };
+        format!("{}\n{}\n", wire("U5,R20"), wire("R10,U10"))
+    }
+
+    /// The wires, one per line, as moves of a direction (U, D, L or R) and a length.
+    pub fn parse(self: &Self, input: &mut dyn io::Read) -> error::Result<Vec<Vec<(char, i32)>>> {
+        let lines = parse::lines(input)?;
+        Ok(lines.iter().map(|l| l.text.split(',').map(|m| match m.chars().next() {
+            Some(d) if "UDLR".contains(d) => Ok((d, l.field(&m[1..], "a distance")?)),
+            _ => Err(l.error(m, "a move like R75")),
+        }).collect()).collect::<Result<_, _>>()?)
+    }
+
+    pub fn part1_impl(self: &Self, wires: &[Vec<(char, i32)>])
+        -> error::Result<Option<i32>> {
         let mut g = collections::HashMap::new();
         let mut md: Option<i32> = None;
-        for l in lines.enumerate() {
+        for l in wires.iter().enumerate() {
             let mut p = (0, 0);
-            for m in l.1.split(',') {
-                for _n in 1..=m[1..].parse::<i32>().unwrap() {
-                    match &m[0..1] {
-                        "U" => p = (p.0, p.1 + 1),
-                        "D" => p = (p.0, p.1 - 1),
-                        "L" => p = (p.0 - 1, p.1),
-                        "R" => p = (p.0 + 1, p.1),
-                        _ => bail!("unknown direction"),
+            for &(d, n) in l.1 {
+                for _n in 1..=n {
+                    match d {
+                        'U' => p = (p.0, p.1 + 1),
+                        'D' => p = (p.0, p.1 - 1),
+                        'L' => p = (p.0 - 1, p.1),
+                        'R' => p = (p.0 + 1, p.1),
+                        _ => return Err(Error::input(&format!("unknown direction {}", d))),
                     }
                     match g.get(&p) {
                         None => { g.insert(p, l.0); },
@@ -54,23 +91,21 @@ impl Day03 {
         Ok(md)
     }
 
-    fn part2_impl(self: &Self, input: &mut dyn io::Read)
-        -> BoxResult<Option<i32>> {
-        let reader = io::BufReader::new(input);
-        let lines = reader.lines().map(|l| l.unwrap());
+    pub fn part2_impl(self: &Self, wires: &[Vec<(char, i32)>])
+        -> error::Result<Option<i32>> {
         let mut g = collections::HashMap::new();
         let mut md: Option<i32> = None;
-        for l in lines.enumerate() {
+        for l in wires.iter().enumerate() {
             let mut p = (0, 0);
             let mut s = 0;
-            for m in l.1.split(',') {
-                for _n in 1..=m[1..].parse::<i32>().unwrap() {
-                    match &m[0..1] {
-                        "U" => p = (p.0, p.1 + 1),
-                        "D" => p = (p.0, p.1 - 1),
-                        "L" => p = (p.0 - 1, p.1),
-                        "R" => p = (p.0 + 1, p.1),
-                        _ => bail!("unknown direction"),
+            for &(d, n) in l.1 {
+                for _n in 1..=n {
+                    match d {
+                        'U' => p = (p.0, p.1 + 1),
+                        'D' => p = (p.0, p.1 - 1),
+                        'L' => p = (p.0 - 1, p.1),
+                        'R' => p = (p.0 + 1, p.1),
+                        _ => return Err(Error::input(&format!("unknown direction {}", d))),
                     }
                     s += 1;
                     match g.get(&p) {
@@ -90,11 +125,12 @@ impl Day03 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::generate;
 
     fn test1(w1: &str, w2: &str, d: i32) {
         let ws = format!("{}\n{}", w1, w2);
-        assert_eq!(
-            Day03 {}.part1_impl(&mut ws.as_bytes()).unwrap(), Some(d));
+        let day = Day03 {};
+        assert_eq!(day.part1_impl(&day.parse(&mut ws.as_bytes()).unwrap()).unwrap(), Some(d));
     }
 
     #[test]
@@ -108,8 +144,8 @@ mod tests {
 
     fn test2(w1: &str, w2: &str, d: i32) {
         let ws = format!("{}\n{}", w1, w2);
-        assert_eq!(
-            Day03 {}.part2_impl(&mut ws.as_bytes()).unwrap(), Some(d));
+        let day = Day03 {};
+        assert_eq!(day.part2_impl(&day.parse(&mut ws.as_bytes()).unwrap()).unwrap(), Some(d));
     }
 
     #[test]
@@ -120,4 +156,43 @@ mod tests {
         test2("R98,U47,R26,D63,R33,U87,L62,D20,R33,U53,R51",
               "U98,R91,D20,R16,D67,R40,U7,R15,U6,R7", 410);
     }
+
+    #[test]
+    fn properties() {
+        let day = Day03 {};
+        generate::check(20, |rng| {
+            let wires = day.parse(&mut day.generate_impl(rng, 10).as_bytes()).unwrap();
+            let crossings = crossings(&wires[0], &wires[1]);
+            assert_eq!(day.part1_impl(&wires).unwrap(),
+                       crossings.iter().map(|&((x, y), _)| x.abs() + y.abs()).min());
+            assert_eq!(day.part2_impl(&wires).unwrap(),
+                       crossings.iter().map(|&(_, steps)| steps).min());
+        });
+    }
+
+    /// Where the wires cross and the steps both took to get there, from every
+    /// point of the first wire checked against every move of the second.
+    fn crossings(a: &[(char, i32)], b: &[(char, i32)]) -> Vec<((i32, i32), i32)> {
+        let moves = |wire: &[(char, i32)]| wire.iter().scan(((0, 0), 0), |(p, s), &(d, n)| {
+            let (dx, dy) = match d { 'U' => (0, 1), 'D' => (0, -1), 'L' => (-1, 0), _ => (1, 0) };
+            let start = (*p, *s);
+            *p = (p.0 + dx * n, p.1 + dy * n);
+            *s += n;
+            Some((start.0, (dx, dy), n, start.1))
+        }).collect::<Vec<_>>();
+        let (a, b) = (moves(a), moves(b));
+        let mut crossings = vec![];
+        for &((x, y), (dx, dy), n, s) in &a {
+            for i in 1..=n {
+                let p = (x + dx * i, y + dy * i);
+                for &((bx, by), (bdx, bdy), bn, bs) in &b {
+                    let j = (p.0 - bx) * bdx + (p.1 - by) * bdy;
+                    if (1..=bn).contains(&j) && (bx + bdx * j, by + bdy * j) == p {
+                        crossings.push((p, s + i + bs + j));
+                    }
+                }
+            }
+        }
+        crossings
+    }
 }
\ No newline at end of file
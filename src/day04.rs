@@ -1,25 +1,57 @@
-use std::error;
 use std::io;
-use std::io::BufRead;
 use crate::day;
-
-pub type BoxResult<T> = Result<T, Box<dyn error::Error>>;
+use crate::error;
+use crate::parse;
 
 pub struct Day04 {}
 
+inventory::submit! { day::Registration(&Day04 {}) }
+
 impl day::Day for Day04 {
     fn tag(&self) -> &str { "04" }
 
-    fn part1(&self, input: &dyn Fn() -> Box<dyn io::Read>) {
-        println!("{:?}", self.part1_impl(&mut *input()));
+    fn title(&self) -> &str { "Secure Container" }
+
+    fn parts(&self) -> [&str; 2] {
+        ["passwords in the range with adjacent equal digits",
+         "passwords in the range with exactly two adjacent equal digits"]
+    }
+}
+
+impl day::Puzzle for Day04 {
+    type Input = (usize, usize);
+
+    fn parse_input(&self, input: &mut dyn io::Read) -> error::Result<(usize, usize)> {
+        self.parse(input)
+    }
+
+    fn part1(&self, range: &(usize, usize)) -> day::Answer {
+        self.part1_impl(*range).into()
     }
 
-    fn part2(&self, input: &dyn Fn() -> Box<dyn io::Read>) {
-        println!("{:?}", self.part2_impl(&mut *input()));
+    fn part2(&self, range: &(usize, usize)) -> day::Answer {
+        self.part2_impl(*range).into()
     }
 }
 
 impl Day04 {
+    /// The password range, given as `start-stop`.
+    pub fn parse(self: &Self, input: &mut dyn io::Read) -> error::Result<(usize, usize)> {
+        let lines = parse::lines(input)?;
+        let expected = "a range like 123456-654321";
+        let l = match lines.first() {
+            Some(l) => l,
+            None => return Err(parse::ParseError {
+                file: day::input_name(), line: 1, column: 1, text: String::new(),
+                expected: expected.to_string(),
+            }.into()),
+        };
+        match l.text.trim_end().split_once('-') {
+            Some((start, stop)) => Ok((l.field(start, "a number")?, l.field(stop, "a number")?)),
+            None => Err(l.expected(expected).into()),
+        }
+    }
+
     #[allow(dead_code)]
     fn ok_1_imperative(&self, s: &str) -> bool {
         let v = s.bytes().collect::<Vec<_>>();
@@ -38,13 +70,8 @@ impl Day04 {
         r.1 & r.2
     }
 
-    fn part1_impl(self: &Self, input: &mut dyn io::Read)
-        -> BoxResult<i32> {
-        let reader = io::BufReader::new(input);
-        let mut split = reader.split(b'-').map(|v|
-            String::from_utf8(v.unwrap()).unwrap());
-        let start = split.next().unwrap().trim_end().parse::<usize>()?;
-        let stop = split.next().unwrap().trim_end().parse::<usize>()?;
+    pub fn part1_impl(self: &Self, (start, stop): (usize, usize))
+        -> error::Result<i32> {
         let mut n = 0;
         for i in start..=stop {
             if self.ok_1_functional(format!("{:0>6}", i).as_str()) { n += 1; }
@@ -79,13 +106,8 @@ impl Day04 {
         increasing && (exactly_two || running == 2)
     }
 
-    fn part2_impl(self: &Self, input: &mut dyn io::Read)
-        -> BoxResult<i32> {
-        let reader = io::BufReader::new(input);
-        let mut split = reader.split(b'-').map(|v|
-            String::from_utf8(v.unwrap()).unwrap());
-        let start = split.next().unwrap().trim_end().parse::<usize>()?;
-        let stop = split.next().unwrap().trim_end().parse::<usize>()?;
+    pub fn part2_impl(self: &Self, (start, stop): (usize, usize))
+        -> error::Result<i32> {
         let mut n = 0;
         for i in start..=stop {
             if self.ok_2_functional(&format!("{:0>6}", i)) { n += 1; }
@@ -97,6 +119,7 @@ impl Day04 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::generate;
 
     fn test1(s: &str, b: bool) {
         assert_eq!(Day04 {}.ok_1_imperative(s), b);
@@ -121,4 +144,17 @@ mod tests {
         test2("123444", false);
         test2("111122", true);
     }
+
+    #[test]
+    fn properties() {
+        generate::check(500, |rng| {
+            let mut digits = (0..6).map(|_| b'0' + rng.below(10) as u8).collect::<Vec<_>>();
+            if !rng.one_in(4) { digits.sort(); }
+            let s = String::from_utf8(digits).unwrap();
+            let day = Day04 {};
+            assert_eq!(day.ok_1_imperative(&s), day.ok_1_functional(&s), "{}", s);
+            assert_eq!(day.ok_2_imperative(&s), day.ok_2_functional(&s), "{}", s);
+            assert!(!day.ok_2_functional(&s) || day.ok_1_functional(&s), "{}", s);
+        });
+    }
 }
\ No newline at end of file
@@ -1,26 +1,45 @@
-use simple_error::bail;
-use std::error;
 use std::io;
-use std::io::BufRead;
 use crate::day;
-
-pub type BoxResult<T> = Result<T, Box<dyn error::Error>>;
+use crate::error::{self, Error, Fault};
+use crate::parse;
 
 pub struct Day05 {}
 
+inventory::submit! { day::Registration(&Day05 {}) }
+
 impl day::Day for Day05 {
     fn tag(&self) -> &str { "05" }
 
-    fn part1(&self, input: &dyn Fn() -> Box<dyn io::Read>) {
-        println!("{:?}", self.part1_impl(&mut *input(), 1));
+    fn title(&self) -> &str { "Sunny with a Chance of Asteroids" }
+
+    fn parts(&self) -> [&str; 2] {
+        ["diagnostic code for the air conditioner unit",
+         "diagnostic code for the thermal radiator controller"]
     }
+}
+
+impl day::Puzzle for Day05 {
+    type Input = Vec<i32>;
 
-    fn part2(&self, input: &dyn Fn() -> Box<dyn io::Read>) {
-        println!("{:?}", self.part2_impl(&mut *input(), 5));
+    fn parse_input(&self, input: &mut dyn io::Read) -> error::Result<Vec<i32>> {
+        self.parse(input)
+    }
+
+    fn part1(&self, p: &Vec<i32>) -> day::Answer {
+        self.part1_impl(p, 1).into()
+    }
+
+    fn part2(&self, p: &Vec<i32>) -> day::Answer {
+        self.part2_impl(p, 5).into()
     }
 }
 
 impl Day05 {
+    /// The Intcode program.
+    pub fn parse(self: &Self, input: &mut dyn io::Read) -> error::Result<Vec<i32>> {
+        parse::numbers(input, "an integer")
+    }
+
     fn op(&self, c: i32) -> i32 { c % 100 }
 
     fn val(&self, p: &Vec<i32>, ip: usize, i: usize) -> i32 {
@@ -29,16 +48,14 @@ impl Day05 {
         v
     }
 
-    fn part1_impl(self: &Self, input: &mut dyn io::Read, i: i32)
-                  -> BoxResult<i32> {
-        let reader = io::BufReader::new(input);
-        let mut p = reader.split(b',')
-            .map(|v| String::from_utf8(v.unwrap()).unwrap())
-            .map(|s| s.trim_end().parse::<i32>().unwrap())
-            .collect::<Vec<_>>();
+    pub fn part1_impl(self: &Self, p: &[i32], i: i32)
+                  -> error::Result<i32> {
+        let mut p = p.to_vec();
         let mut ip = 0;
         let mut o = None;
+        let cancel = day::Cancel::current();
         while self.op(p[ip]) != 99 {
+            cancel.check()?;
             match self.op(p[ip]) {
                 1 => {
                     let a = self.val(&p, ip, 1);
@@ -64,22 +81,19 @@ impl Day05 {
                     o = Some(a);
                     ip += 2;
                 }
-                _ => bail!("unknown opcode {}: {}", ip, self.op(p[ip])),
+                op => return Err(Error::intcode(ip, Fault::UnknownOpcode(op as i64))),
             };
         }
-        if o.is_none() { bail!("no output"); }
-        Ok(o.unwrap())
+        o.ok_or_else(|| Error::intcode(ip, Fault::NoOutput))
     }
 
-    fn part2_impl(self: &Self, input: &mut dyn io::Read, i: i32) -> BoxResult<i32> {
-        let reader = io::BufReader::new(input);
-        let mut p = reader.split(b',')
-            .map(|v| String::from_utf8(v.unwrap()).unwrap())
-            .map(|s| s.trim_end().parse::<i32>().unwrap())
-            .collect::<Vec<_>>();
+    pub fn part2_impl(self: &Self, p: &[i32], i: i32) -> error::Result<i32> {
+        let mut p = p.to_vec();
         let mut ip = 0;
         let mut o = None;
+        let cancel = day::Cancel::current();
         while self.op(p[ip]) != 99 {
+            cancel.check()?;
             match self.op(p[ip]) {
                 1 => {
                     let a = self.val(&p, ip, 1);
@@ -129,11 +143,10 @@ impl Day05 {
                     p[c] = if a == b { 1 } else { 0 };
                     ip += 4;
                 },
-                _ => bail!("unknown opcode {}: {}", ip, self.op(p[ip])),
+                op => return Err(Error::intcode(ip, Fault::UnknownOpcode(op as i64))),
             };
         }
-        if o.is_none() { bail!("no output"); }
-        Ok(o.unwrap())
+        o.ok_or_else(|| Error::intcode(ip, Fault::NoOutput))
     }
 }
 
@@ -142,7 +155,7 @@ mod tests {
     use super::*;
 
     fn test1(s: &str, i: i32, o: Option<i32>) {
-        let r = Day05 {}.part1_impl(&mut s.as_bytes(), i);
+        let r = Day05 {}.part1_impl(&Day05 {}.parse(&mut s.as_bytes()).unwrap(), i);
         if o == None { assert!(r.is_err()); }
         else { assert_eq!(r.unwrap(), o.unwrap()); }
     }
@@ -156,7 +169,7 @@ mod tests {
 
     fn test2(s: &str, i: i32, o: i32) {
         assert_eq!(
-            Day05 {}.part2_impl(&mut s.as_bytes(), i).unwrap(), o);
+            Day05 {}.part2_impl(&Day05 {}.parse(&mut s.as_bytes()).unwrap(), i).unwrap(), o);
     }
 
     #[test]
@@ -1,40 +1,85 @@
-use simple_error::bail;
 use std::collections;
-use std::error;
 use std::io;
-use std::io::BufRead;
 use topological_sort;
 use crate::day;
-
-pub type BoxResult<T> = Result<T, Box<dyn error::Error>>;
+use crate::error::{self, Error};
+use crate::generate::Rng;
+use crate::parse;
 
 pub struct Day06 {}
 
+inventory::submit! { day::Registration(&Day06 {}) }
+
 impl day::Day for Day06 {
     fn tag(&self) -> &str { "06" }
 
-    fn part1(&self, input: &dyn Fn() -> Box<dyn io::Read>) {
-        println!("{:?}", self.part1_impl(&mut *input()));
+    fn title(&self) -> &str { "Universal Orbit Map" }
+
+    fn parts(&self) -> [&str; 2] {
+        ["total number of direct and indirect orbits",
+         "orbital transfers from YOU to SAN"]
+    }
+
+    fn generate(&self, rng: &mut Rng, size: Option<usize>) -> Option<String> {
+        Some(self.generate_impl(rng, size.unwrap_or(100)))
+    }
+}
+
+impl day::Puzzle for Day06 {
+    type Input = Vec<(String, String)>;
+
+    fn parse_input(&self, input: &mut dyn io::Read) -> error::Result<Vec<(String, String)>> {
+        self.parse(input)
+    }
+
+    fn part1(&self, orbits: &Vec<(String, String)>) -> day::Answer {
+        self.part1_impl(orbits).into()
     }
 
-    fn part2(&self, input: &dyn Fn() -> Box<dyn io::Read>) {
-        println!("{:?}", self.part2_impl(&mut *input()));
+    fn part2(&self, orbits: &Vec<(String, String)>) -> day::Answer {
+        self.part2_impl(orbits).into()
     }
 }
 
 impl Day06 {
+    /// `size` bodies orbiting COM or each other, with YOU and SAN among them,
+    /// in random order.
+    pub fn generate_impl(self: &Self, rng: &mut Rng, size: usize) -> String {
+        let len = if size < 10000 { 3 } else { 4 };
+        let mut names = collections::HashSet::new();
+        let mut bodies = vec![String::from("COM")];
+        while bodies.len() < size.max(2) - 1 {
+            let name = rng.name(len);
+            if !["COM", "YOU", "SAN"].contains(&name.as_str()) && names.insert(name.clone()) {
+                bodies.push(name);
+            }
+        }
+        let mut orbits = (1..bodies.len())
+            .map(|i| format!("{}){}", bodies[rng.below(i)], bodies[i]))
+            .collect::<Vec<_>>();
+        orbits.push(format!("{})YOU", rng.pick(&bodies)));
+        orbits.push(format!("{})SAN", rng.pick(&bodies)));
+        rng.shuffle(&mut orbits);
+        orbits.join("\n") + "\n"
+    }
+
+    /// The orbits, one `center)orbiter` per line.
+    pub fn parse(self: &Self, input: &mut dyn io::Read) -> error::Result<Vec<(String, String)>> {
+        let lines = parse::lines(input)?;
+        Ok(lines.iter().map(|l| match l.text.split_once(')') {
+            Some((k, v)) => Ok((k.to_owned(), v.to_owned())),
+            None => Err(l.expected("an orbit like COM)B")),
+        }).collect::<Result<_, _>>()?)
+    }
+
     fn count(&self, ts: &mut topological_sort::TopologicalSort<String>, gen: usize) -> usize {
         let v = (*ts).pop_all();
         gen * v.len() + if v.len() == 0 { 0 } else { self.count(ts, gen + 1) }
     }
 
-    fn part1_impl(self: &Self, input: &mut dyn io::Read) -> BoxResult<usize> {
-        let reader = io::BufReader::new(input);
+    pub fn part1_impl(self: &Self, orbits: &[(String, String)]) -> error::Result<usize> {
         let mut ts = topological_sort::TopologicalSort::<String>::new();
-        reader.lines().map(|r| r.unwrap()).for_each(|l| {
-            let mut split = l.as_str().split(")");
-            let k = split.next().unwrap();
-            let v = split.next().unwrap();
+        orbits.iter().for_each(|(k, v)| {
             ts.add_dependency(k.to_owned(), v.to_owned());
         });
         Ok(self.count(&mut ts, 0))
@@ -72,14 +117,11 @@ impl Day06 {
         } else { None }
     }
 
-    fn part2_impl(self: &Self, input: &mut dyn io::Read) -> BoxResult<usize> {
-        let reader = io::BufReader::new(input);
+    pub fn part2_impl(self: &Self, orbits: &[(String, String)]) -> error::Result<usize> {
         let mut ts = topological_sort::TopologicalSort::<String>::new();
         let mut map= collections::HashMap::new();
-        reader.lines().map(|r| r.unwrap()).for_each(|l| {
-            let mut split = l.as_str().split(")");
-            let k = split.next().unwrap();
-            let v = split.next().unwrap();
+        orbits.iter().for_each(|(k, v)| {
+            let k = k.as_str();
             ts.add_dependency(k.to_owned(), v.to_owned());
             let mut orbiters = map.get_mut(k);
             if orbiters == None {
@@ -93,16 +135,17 @@ impl Day06 {
                 return Ok(n - 2);
             }
         }
-        bail!("no way")
+        Err(Error::no_solution("no way from YOU to SAN"))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::generate;
 
     fn test1(s: &str, v: usize) {
-        assert_eq!(Day06 {}.part1_impl(&mut s.as_bytes()).unwrap(), v);
+        assert_eq!(Day06 {}.part1_impl(&Day06 {}.parse(&mut s.as_bytes()).unwrap()).unwrap(), v);
     }
 
     #[test]
@@ -121,7 +164,7 @@ K)L", 42);
     }
 
     fn test2(s: &str, v: usize) {
-        assert_eq!(Day06 {}.part2_impl(&mut s.as_bytes()).unwrap(), v);
+        assert_eq!(Day06 {}.part2_impl(&Day06 {}.parse(&mut s.as_bytes()).unwrap()).unwrap(), v);
     }
 
     #[test]
@@ -140,4 +183,27 @@ K)L
 K)YOU
 I)SAN", 4);
     }
+
+    #[test]
+    fn properties() {
+        let day = Day06 {};
+        generate::check(20, |rng| {
+            let orbits = day.parse(&mut day.generate_impl(rng, 50).as_bytes()).unwrap();
+            let centers = orbits.iter().map(|(c, o)| (o.as_str(), c.as_str()))
+                .collect::<collections::HashMap<_, _>>();
+            let path = |body: &str| {
+                let (mut path, mut center) = (vec![], centers.get(body));
+                while let Some(&c) = center {
+                    path.push(c.to_string());
+                    center = centers.get(c);
+                }
+                path
+            };
+            assert_eq!(day.part1_impl(&orbits).unwrap(),
+                       orbits.iter().map(|(_, o)| path(o).len()).sum::<usize>());
+            let (you, san) = (path("YOU"), path("SAN"));
+            let common = you.iter().filter(|b| san.contains(b)).count();
+            assert_eq!(day.part2_impl(&orbits).unwrap(), you.len() + san.len() - 2 * common);
+        });
+    }
 }
\ No newline at end of file
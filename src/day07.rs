@@ -1,25 +1,38 @@
 use permute;
-use simple_error::bail;
-use std::error;
 use std::io;
-use std::io::BufRead;
 use std::sync::mpsc;
-use std::thread;
 use crate::day;
-
-pub type BoxResult<T> = Result<T, Box<dyn error::Error>>;
+use crate::error::{self, Error, Fault};
+use crate::parse;
 
 pub struct Day07 {}
 
+inventory::submit! { day::Registration(&Day07 {}) }
+
 impl day::Day for Day07 {
     fn tag(&self) -> &str { "07" }
 
-    fn part1(&self, input: &dyn Fn() -> Box<dyn io::Read>) {
-        println!("{:?}", self.part1_impl(&mut *input(), 0));
+    fn title(&self) -> &str { "Amplification Circuit" }
+
+    fn parts(&self) -> [&str; 2] {
+        ["highest signal sent to the thrusters",
+         "highest signal with the amplifiers in a feedback loop"]
     }
+}
+
+impl day::Puzzle for Day07 {
+    type Input = Vec<i64>;
 
-    fn part2(&self, input: &dyn Fn() -> Box<dyn io::Read>) {
-        println!("{:?}", self.part2_impl(&mut *input(), 0));
+    fn parse_input(&self, input: &mut dyn io::Read) -> error::Result<Vec<i64>> {
+        self.parse(input)
+    }
+
+    fn part1(&self, p: &Vec<i64>) -> day::Answer {
+        self.part1_impl(p, 0).into()
+    }
+
+    fn part2(&self, p: &Vec<i64>) -> day::Answer {
+        self.part2_impl(p, 0).into()
     }
 }
 
@@ -38,11 +51,13 @@ impl Intcode {
         v
     }
 
-    fn run(&mut self, sender: mpsc::Sender<i64>, receiver: mpsc::Receiver<i64>) -> BoxResult<i64> {
+    fn run(&mut self, sender: mpsc::Sender<i64>, receiver: mpsc::Receiver<i64>) -> error::Result<i64> {
         let mut ip = 0;
         let mut o = None;
+        let cancel = day::Cancel::current();
         while self.op(self.p[ip]) != 99 {
-//            eprintln!("{}: {} {} {} {}", ip, self.p[ip], self.p[ip + 1], self.p[ip + 2], self.p[ip + 3]);
+            cancel.check()?;
+            log::trace!("{}: {} {} {} {}", ip, self.p[ip], self.p[ip + 1], self.p[ip + 2], self.p[ip + 3]);
             match self.op(self.p[ip]) {
                 1 => {
                     let a = self.val(ip, 1);
@@ -60,17 +75,17 @@ impl Intcode {
                 },
                 3 => {
                     let a = self.p[ip + 1];
-//                    eprintln!(">recv");
-                    self.p[a as usize] = receiver.recv()?;
-//                    eprintln!("<recv {}", self.p[a as usize]);
+                    log::trace!(">recv");
+                    self.p[a as usize] = day::recv(&receiver)?;
+                    log::trace!("<recv {}", self.p[a as usize]);
                     ip += 2;
                 },
                 4 => {
                     let a = self.val(ip, 1);
                     o = Some(a);
-//                    eprintln!(">send {}", a);
+                    log::trace!(">send {}", a);
                     sender.send(a)?;
-//                    eprintln!("<send");
+                    log::trace!("<send");
                     ip += 2;
                 },
                 5 => {
@@ -97,11 +112,10 @@ impl Intcode {
                     self.p[c] = if a == b { 1 } else { 0 };
                     ip += 4;
                 },
-                _ => bail!("unknown opcode {}: {}", ip, self.op(self.p[ip])),
+                op => return Err(Error::intcode(ip, Fault::UnknownOpcode(op))),
             };
         }
-        if o.is_none() { bail!("no output"); }
-        Ok(o.unwrap())
+        o.ok_or_else(|| Error::intcode(ip, Fault::NoOutput))
     }
 
     #[allow(dead_code)]
@@ -161,18 +175,19 @@ impl Intcode {
 }
 
 impl Day07 {
-    fn part1_impl(self: &Self, input: &mut dyn io::Read, i: i64)
-        -> BoxResult<i64> {
-        let reader = io::BufReader::new(input);
-        let p = reader.split(b',')
-            .map(|v| String::from_utf8(v.unwrap()).unwrap())
-            .map(|s| s.trim_end().parse::<i64>().unwrap())
-            .collect::<Vec<_>>();
+    /// The Intcode program.
+    pub fn parse(self: &Self, input: &mut dyn io::Read) -> error::Result<Vec<i64>> {
+        parse::numbers(input, "an integer")
+    }
+
+    pub fn part1_impl(self: &Self, p: &[i64], i: i64)
+        -> error::Result<i64> {
         let max = permute::permutations_of(&(0..=4).collect::<Vec<i64>>())
             .map(|phases| {
                 let phases = phases.collect::<Vec<_>>();
                 let (first_sender, first_receiver)
                     = mpsc::channel::<i64>();
+                let mut amps = vec![];
                 let (_, receiver) = phases.into_iter().fold(
                     (first_sender.clone(), first_receiver),
                     |(sender, receiver), &phase| {
@@ -180,31 +195,30 @@ impl Day07 {
                         let (next_sender, next_receiver)
                             = mpsc::channel::<i64>();
                         let next_sender_clone = next_sender.clone();
-                        thread::spawn(move || {
-                            Intcode::new(&p).run(next_sender, receiver).unwrap();
-                        });
-                        sender.send(phase).unwrap();
+                        amps.push(day::spawn(move || {
+                            Intcode::new(&p).run(next_sender, receiver)
+                        }));
+                        // An amplifier that stopped says why when it is joined.
+                        let _ = sender.send(phase);
                         (next_sender_clone, next_receiver)
                     },
                 );
-                first_sender.send(i).unwrap();
-                receiver.recv().unwrap()
-            }).max();
+                let _ = first_sender.send(i);
+                let output = day::recv(&receiver);
+                amps.into_iter().try_for_each(|amp| day::join(amp).map(|_| ()))?;
+                output
+            }).collect::<error::Result<Vec<_>>>()?.into_iter().max();
         Ok(max.unwrap())
     }
 
-    fn part2_impl(self: &Self, input: &mut dyn io::Read, i: i64)
-        -> BoxResult<i64> {
-        let reader = io::BufReader::new(input);
-        let p = reader.split(b',')
-            .map(|v| String::from_utf8(v.unwrap()).unwrap())
-            .map(|s| s.trim_end().parse::<i64>().unwrap())
-            .collect::<Vec<_>>();
+    pub fn part2_impl(self: &Self, p: &[i64], i: i64)
+        -> error::Result<i64> {
         let max = permute::permutations_of(&(5..=9).collect::<Vec<i64>>())
             .map(|phases| {
                 let phases = phases.collect::<Vec<_>>();
                 let (first_sender, first_receiver)
                     = mpsc::channel::<i64>();
+                let mut amps = vec![];
                 let (_, receiver) = phases.into_iter().fold(
                     (first_sender.clone(), first_receiver),
                     |(sender, receiver), &phase| {
@@ -212,23 +226,26 @@ impl Day07 {
                         let (next_sender, next_receiver)
                             = mpsc::channel::<i64>();
                         let next_sender_clone = next_sender.clone();
-                        thread::spawn(move || {
-                            Intcode::new(&p).run(next_sender, receiver).unwrap();
-                        });
-                        sender.send(phase).unwrap();
+                        amps.push(day::spawn(move || {
+                            Intcode::new(&p).run(next_sender, receiver)
+                        }));
+                        // An amplifier that stopped says why when it is joined.
+                        let _ = sender.send(phase);
                         (next_sender_clone, next_receiver)
                     },
                 );
-                first_sender.send(i).unwrap();
-                receiver
+                let _ = first_sender.send(i);
+                let output = receiver
                     .iter()
                     .map(|output| {
-                        first_sender.send(output).unwrap();
+                        // The first amplifier has halted by the last output.
+                        let _ = first_sender.send(output);
                         output
                     })
-                    .last()
-                    .unwrap()
-            }).max();
+                    .last();
+                amps.into_iter().try_for_each(|amp| day::join(amp).map(|_| ()))?;
+                output.ok_or_else(|| Error::intcode(0, Fault::NoOutput))
+            }).collect::<error::Result<Vec<_>>>()?.into_iter().max();
         Ok(max.unwrap())
     }
 }
@@ -238,7 +255,7 @@ mod tests {
     use super::*;
 
     fn test1(s: &str, v: i64) {
-        assert_eq!(Day07 {}.part1_impl(&mut s.as_bytes(), 0).unwrap(), v);
+        assert_eq!(Day07 {}.part1_impl(&Day07 {}.parse(&mut s.as_bytes()).unwrap(), 0).unwrap(), v);
     }
 
     #[test]
@@ -251,7 +268,7 @@ mod tests {
     }
 
     fn test2(s: &str, v: i64) {
-        assert_eq!(Day07 {}.part2_impl(&mut s.as_bytes(), 0).unwrap(), v);
+        assert_eq!(Day07 {}.part2_impl(&Day07 {}.parse(&mut s.as_bytes()).unwrap(), 0).unwrap(), v);
     }
 
     #[test]
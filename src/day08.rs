@@ -1,32 +1,67 @@
-use std::error;
 use std::io;
-use std::io::Read;
-use crate::day;
-
-pub type BoxResult<T> = Result<T, Box<dyn error::Error>>;
+use crate::day::{self, Day};
+use crate::error;
+use crate::parse;
 
 pub struct Day08 {}
 
+inventory::submit! { day::Registration(&Day08 {}) }
+
 impl day::Day for Day08 {
     fn tag(&self) -> &str { "08" }
 
-    fn part1(&self, input: &dyn Fn() -> Box<dyn io::Read>) {
-        println!("{:?}", self.part1_impl(&mut *input()));
+    fn title(&self) -> &str { "Space Image Format" }
+
+    fn parts(&self) -> [&str; 2] {
+        ["ones times twos on the layer with the fewest zeros",
+         "decoded image"]
     }
 
-    fn part2(&self, input: &dyn Fn() -> Box<dyn io::Read>) {
-        println!("{:?}", self.part2_impl(&mut *input()));
+    fn params(&self) -> &[day::Param] {
+        &[day::Param { name: "width", default: 25, min: 1, max: i64::MAX,
+                      help: "image width in pixels" },
+         day::Param { name: "height", default: 6, min: 1, max: i64::MAX,
+                      help: "image height in pixels" }]
+    }
+}
+
+impl day::Puzzle for Day08 {
+    type Input = Vec<u8>;
+
+    fn parse_input(&self, input: &mut dyn io::Read) -> error::Result<Vec<u8>> {
+        self.parse(input)
+    }
+
+    fn part1(&self, pixels: &Vec<u8>) -> day::Answer {
+        let (width, height) = (self.param("width") as usize, self.param("height") as usize);
+        self.part1_impl(pixels, width, height).into()
+    }
+
+    fn part2(&self, pixels: &Vec<u8>) -> day::Answer {
+        let (width, height) = (self.param("width") as usize, self.param("height") as usize);
+        self.part2_impl(pixels, width, height).into()
     }
 }
 
 impl Day08 {
+    /// The image's pixel digits, layer after layer.
+    pub fn parse(self: &Self, input: &mut dyn io::Read) -> error::Result<Vec<u8>> {
+        let mut pixels = vec![];
+        for l in parse::lines(input)? {
+            l.only("0123456789", "a digit")?;
+            pixels.extend(l.text.bytes());
+        }
+        Ok(pixels)
+    }
+
     fn count(&self, v: &Vec<u8>, b: u8) -> usize {
         v.iter().filter(|&d| *d == b).count()
     }
 
-    fn part1_impl(self: &Self, input: &mut dyn io::Read) -> BoxResult<usize> {
-        let pixels = input.bytes().map(|b| b.unwrap()).collect::<Vec<_>>();
-        let layers = pixels.chunks_exact(25 * 6).map(|slice| slice.to_vec()).collect::<Vec<_>>();
+    pub fn part1_impl(self: &Self, pixels: &[u8], width: usize, height: usize)
+        -> error::Result<usize> {
+        let layers = pixels.chunks_exact(width * height).map(|slice| slice.to_vec())
+            .collect::<Vec<_>>();
         let result = layers.iter().map(|layer: &Vec<_>| self.count(layer, b'0'))
             .zip(layers.iter()).min_by_key(|&(count, _)| count)
             .map(|(_, layer)| self.count(layer, b'1') * self.count(layer, b'2'));
@@ -37,16 +72,18 @@ impl Day08 {
         match b { b'0' => b' ', b'1' => b'.', _ => b'X', }
     }
 
-    fn part2_impl(self: &Self, input: &mut dyn io::Read) -> BoxResult<Vec<String>> {
-        let pixels = input.bytes().map(|b| b.unwrap()).collect::<Vec<_>>();
-        let mut layers = pixels.chunks_exact(25 * 6).map(|slice| slice.to_vec()).collect::<Vec<_>>();
+    pub fn part2_impl(self: &Self, pixels: &[u8], width: usize, height: usize)
+        -> error::Result<Vec<String>> {
+        let mut layers = pixels.chunks_exact(width * height).map(|slice| slice.to_vec())
+            .collect::<Vec<_>>();
         let init = layers.remove(0);
         let image = layers.iter().fold(init, |result, layer| {
            result.iter().zip(layer.iter()).map(|(&a, &b)|
                if a != b'2' { a } else { b })
         }.collect());
-        let result = image.iter().map(|&b| self.transform(b)).collect::<Vec<_>>().chunks(25).map(|line|
-            String::from_utf8(line.iter().cloned().collect()).unwrap()).collect::<Vec<_>>();
+        let result = image.iter().map(|&b| self.transform(b)).collect::<Vec<_>>()
+            .chunks(width).map(|line|
+                String::from_utf8(line.iter().cloned().collect()).unwrap()).collect::<Vec<_>>();
         Ok(result)
     }
 }
\ No newline at end of file
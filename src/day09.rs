@@ -1,12 +1,8 @@
-use simple_error::bail;
-use std::error;
 use std::io;
-use std::io::BufRead;
 use std::sync::mpsc;
-use std::thread;
 use crate::day;
-
-pub type BoxResult<T> = Result<T, Box<dyn error::Error>>;
+use crate::error::{self, Error, Fault};
+use crate::parse;
 
 struct Intcode {
     p: Vec<i64>,
@@ -24,103 +20,104 @@ impl Intcode {
     }
 
     fn put(&mut self, a: usize, v: i64) {
-//        eprintln!("put @{} {}", a, v);
+        log::trace!("put @{} {}", a, v);
         if a >= self.p.len() { self.p.resize(a + 1, 0); }
         self.p[a] = v;
     }
 
-    fn addr(&mut self, ip: usize, i: usize) -> usize {
+    fn addr(&mut self, ip: usize, i: usize) -> error::Result<usize> {
         let a = self.get(ip + i);
         let v = match self.get(ip) / vec![100, 1000, 10000][i - 1] % 10 {
             0 => a as usize,
             2 => (a  + self.base) as usize,
-            _ => 0, // XXX
+            mode => return Err(Error::intcode(ip, Fault::BadMode(mode))),
         };
-        v
+        Ok(v)
     }
 
-    fn val(&mut self, ip: usize, i: usize) -> i64 {
+    fn val(&mut self, ip: usize, i: usize) -> error::Result<i64> {
         let a = self.get(ip + i);
         let v = match self.get(ip) / vec![100, 1000, 10000][i - 1] % 10 {
             1 => a,
             _ => {
-                let addr = self.addr(ip, i);
+                let addr = self.addr(ip, i)?;
                 self.get(addr)
             },
         };
-//        eprintln!("{} {} {} {} {} {}", ip, self.get(ip), i, a, self.base, v);
-        v
+        log::trace!("{} {} {} {} {} {}", ip, self.get(ip), i, a, self.base, v);
+        Ok(v)
     }
 
-    fn run(&mut self, sender: mpsc::Sender<i64>, receiver: mpsc::Receiver<i64>) -> BoxResult<i64> {
+    fn run(&mut self, sender: mpsc::Sender<i64>, receiver: mpsc::Receiver<i64>) -> error::Result<i64> {
         let mut ip = 0;
         let mut o = None;
+        let cancel = day::Cancel::current();
         while { let op = self.get(ip); self.op(op) != 99 } {
-//            eprintln!("{}: {} {} {} {}", ip, self.p[ip], self.p[ip + 1], self.p[ip + 2], self.p[ip + 3]);
+            cancel.check()?;
+            log::trace!("{}: {} {} {} {}", ip, self.p[ip], self.p[ip + 1], self.p[ip + 2], self.p[ip + 3]);
             match self.op(self.p[ip]) {
                 1 => {
-                    let a = self.val(ip, 1);
-                    let b = self.val( ip, 2);
-                    let c = self.addr(ip, 3);
+                    let a = self.val(ip, 1)?;
+                    let b = self.val(ip, 2)?;
+                    let c = self.addr(ip, 3)?;
                     self.put(c, a + b);
                     ip += 4;
                 },
                 2 => {
-                    let a = self.val(ip, 1);
-                    let b = self.val(ip, 2);
-                    let c = self.addr(ip, 3);
+                    let a = self.val(ip, 1)?;
+                    let b = self.val(ip, 2)?;
+                    let c = self.addr(ip, 3)?;
                     self.put(c, a * b);
                     ip += 4;
                 },
                 3 => {
-                    let a = self.addr(ip, 1);
-//                    eprintln!(">recv {}", a);
-                    self.put(a as usize, receiver.recv()?);
-//                    eprintln!("<recv {}", self.get(a as usize));
+                    let a = self.addr(ip, 1)?;
+                    log::trace!(">recv {}", a);
+                    self.put(a as usize, day::recv(&receiver)?);
+                    log::trace!("<recv {}", self.get(a));
                     ip += 2;
                 },
                 4 => {
-                    let a = self.val(ip, 1);
+                    let a = self.val(ip, 1)?;
                     o = Some(a);
-//                    eprintln!(">send {}", a);
+                    log::trace!(">send {}", a);
                     sender.send(a)?;
-//                    eprintln!("<send");
+                    log::trace!("<send");
                     ip += 2;
                 },
                 5 => {
-                    let a = self.val(ip, 1);
-                    let b = self.val(ip, 2) as usize;
+                    let a = self.val(ip, 1)?;
+                    let b = self.val(ip, 2)? as usize;
                     ip = if a != 0 { b } else { ip + 3 };
                 },
                 6 => {
-                    let a = self.val(ip, 1);
-                    let b = self.val(ip, 2) as usize;
+                    let a = self.val(ip, 1)?;
+                    let b = self.val(ip, 2)? as usize;
                     ip = if a == 0 { b } else { ip + 3 };
                 },
                 7 => {
-                    let a = self.val(ip, 1);
-                    let b = self.val(ip, 2);
-                    let c = self.addr(ip, 3);
+                    let a = self.val(ip, 1)?;
+                    let b = self.val(ip, 2)?;
+                    let c = self.addr(ip, 3)?;
                     self.put(c, if a < b { 1 } else { 0 });
                     ip += 4;
                 },
                 8 => {
-                    let a = self.val(ip, 1);
-                    let b = self.val(ip, 2);
-                    let c = self.addr(ip,3);
+                    let a = self.val(ip, 1)?;
+                    let b = self.val(ip, 2)?;
+                    let c = self.addr(ip, 3)?;
                     self.put(c, if a == b { 1 } else { 0 });
                     ip += 4;
                 },
                 9 => {
-                    self.base += self.val(ip, 1);
-//                    eprintln!("<base {}", self.base);
+                    self.base += self.val(ip, 1)?;
+                    log::trace!("<base {}", self.base);
                     ip += 2;
                 }
-                _ => bail!("unknown opcode {}: {}", ip, self.op(self.p[ip])),
+                op => return Err(Error::intcode(ip, Fault::UnknownOpcode(op))),
             };
         }
-        if o.is_none() { bail!("no output"); }
-        Ok(o.unwrap())
+        o.ok_or_else(|| Error::intcode(ip, Fault::NoOutput))
     }
 
     #[allow(dead_code)]
@@ -190,34 +187,55 @@ impl Intcode {
 
 pub struct Day09 {}
 
+inventory::submit! { day::Registration(&Day09 {}) }
+
 impl day::Day for Day09 {
     fn tag(&self) -> &str { "09" }
 
-    fn part1(&self, input: &dyn Fn() -> Box<dyn io::Read>) {
-        println!("{:?}", self.part1_impl(&mut *input(), 1));
+    fn title(&self) -> &str { "Sensor Boost" }
+
+    fn parts(&self) -> [&str; 2] {
+        ["BOOST keycode in test mode",
+         "coordinates of the distress signal"]
+    }
+}
+
+impl day::Puzzle for Day09 {
+    type Input = Vec<i64>;
+
+    fn parse_input(&self, input: &mut dyn io::Read) -> error::Result<Vec<i64>> {
+        self.parse(input)
     }
 
-    fn part2(&self, input: &dyn Fn() -> Box<dyn io::Read>) {
-        println!("{:?}", self.part1_impl(&mut *input(), 2));
+    fn part1(&self, p: &Vec<i64>) -> day::Answer {
+        self.part1_impl(p, 1).into()
+    }
+
+    fn part2(&self, p: &Vec<i64>) -> day::Answer {
+        self.part1_impl(p, 2).into()
     }
 }
 
 impl Day09 {
-    fn part1_impl(self: &Self, input: &mut dyn io::Read, i: i64) -> BoxResult<String> {
-        let reader = io::BufReader::new(input);
-        let p = reader.split(b',')
-            .map(|v| String::from_utf8(v.unwrap()).unwrap())
-            .map(|s| s.trim_end().parse::<i64>().unwrap())
-            .collect::<Vec<_>>();
+    /// The Intcode program.
+    pub fn parse(self: &Self, input: &mut dyn io::Read) -> error::Result<Vec<i64>> {
+        parse::numbers(input, "an integer")
+    }
+
+    pub fn part1_impl(self: &Self, p: &[i64], i: i64) -> error::Result<String> {
+        let p = p.to_vec();
         let (input_sender, input_receiver) = mpsc::channel::<i64>();
         let (output_sender, output_receiver) = mpsc::channel::<i64>();
-        thread::spawn(move || {
+        let cpu = day::spawn(move || {
             let mut ic = Intcode::new(&p);
 //            ic.disassemble();
-            ic.run(output_sender, input_receiver).unwrap();
+            ic.run(output_sender, input_receiver)
         });
-        input_sender.send(i)?;
-        Ok(output_receiver.iter().map(|i| i.to_string()).collect::<Vec<_>>().join(","))
+        // A program may halt without reading its input, which is no error.
+        let _ = input_sender.send(i);
+        let output = output_receiver.iter().map(|i| i.to_string()).collect::<Vec<_>>().join(",");
+        day::join(cpu)?;
+        Ok(output)
     }
 }
 
@@ -226,7 +244,7 @@ mod tests {
     use super::*;
 
     fn test1(s: &str, o: &str) {
-        assert_eq!(Day09 {}.part1_impl(&mut s.as_bytes(), 1).unwrap(), o);
+        assert_eq!(Day09 {}.part1_impl(&Day09 {}.parse(&mut s.as_bytes()).unwrap(), 1).unwrap(), o);
     }
 
     #[test]
@@ -235,4 +253,19 @@ mod tests {
         test1("1102,34915192,34915192,7,4,7,99,0", "1219070632396864");
         test1("104,1125899906842624,99", "1125899906842624")
     }
+
+    #[test]
+    fn faults() {
+        let fault = |p: &[i64]| {
+            let (sender, _output) = mpsc::channel::<i64>();
+            let (_input, receiver) = mpsc::channel::<i64>();
+            match Intcode::new(p).run(sender, receiver) {
+                Err(Error::Intcode(context, fault)) => (context.ip, fault),
+                r => panic!("expected an Intcode fault, got {:?}", r),
+            }
+        };
+        assert_eq!(fault(&[1101, 1, 1, 5, 98]), (Some(4), Fault::UnknownOpcode(98)));
+        assert_eq!(fault(&[301, 0, 0, 0]), (Some(0), Fault::BadMode(3)));
+        assert_eq!(fault(&[99]), (Some(0), Fault::NoOutput));
+    }
 }
\ No newline at end of file
@@ -1,33 +1,72 @@
 use num::abs;
 use num_integer::gcd;
 use num_rational::Ratio;
-use std::error;
 use std::io;
-use std::io::BufRead;
-use crate::day;
-
-pub type BoxResult<T> = Result<T, Box<dyn error::Error>>;
+use crate::day::{self, Day};
+use crate::error::{self, Error};
+use crate::generate::Rng;
+use crate::parse;
 
 pub struct Day10 {}
 
+inventory::submit! { day::Registration(&Day10 {}) }
+
 impl day::Day for Day10 {
     fn tag(&self) -> &str { "10" }
 
-    fn part1(&self, input: &dyn Fn() -> Box<dyn io::Read>) {
-        println!("{:?}", self.part1_impl(&mut *input()));
+    fn title(&self) -> &str { "Monitoring Station" }
+
+    fn parts(&self) -> [&str; 2] {
+        ["asteroids detected from the best location",
+         "100 * x + y of the 200th vaporized asteroid"]
     }
 
-    fn part2(&self, input: &dyn Fn() -> Box<dyn io::Read>) {
-        println!("{:?}", self.part2_impl(&mut *input(), 200));
+    fn params(&self) -> &[day::Param] {
+        &[day::Param { name: "nth", default: 200, min: 1, max: i64::MAX,
+                      help: "vaporized asteroid to find in part 2" }]
+    }
+
+    fn version(&self) -> u32 { 2 }
+
+    fn generate(&self, rng: &mut Rng, size: Option<usize>) -> Option<String> {
+        Some(self.generate_impl(rng, size.unwrap_or(26)))
+    }
+}
+
+impl day::Puzzle for Day10 {
+    type Input = Vec<(usize, usize)>;
+
+    fn parse_input(&self, input: &mut dyn io::Read) -> error::Result<Vec<(usize, usize)>> {
+        self.parse(input)
+    }
+
+    fn part1(&self, asteroids: &Vec<(usize, usize)>) -> day::Answer {
+        self.part1_impl(asteroids).into()
+    }
+
+    fn part2(&self, asteroids: &Vec<(usize, usize)>) -> day::Answer {
+        self.part2_impl(asteroids, self.param("nth") as usize).into()
     }
 }
 
 impl Day10 {
-    fn find_best<'a>(&self, asteroids: &'a Vec<(usize, usize)>)
+    /// A square field `size` wide with asteroids on two fifths of it.  It is at
+    /// least 26 wide, so that part 2 does not get to its last few asteroids.
+    pub fn generate_impl(self: &Self, rng: &mut Rng, size: usize) -> String {
+        let side = size.max(26);
+        let mut cells = vec![false; side * side];
+        cells.iter_mut().take(side * side * 2 / 5).for_each(|c| *c = true);
+        rng.shuffle(&mut cells);
+        cells.chunks(side)
+            .map(|row| row.iter().map(|&a| if a { '#' } else { '.' }).collect::<String>() + "\n")
+            .collect()
+    }
+
+    fn find_best<'a>(&self, asteroids: &'a [(usize, usize)])
         -> (&'a (usize, usize), usize) {
         asteroids.iter().map(|p| {
             let others = asteroids.iter().filter(|&a| p != a).collect::<Vec<_>>();
-//            eprintln!("p {:?} {:?}", p, others);
+            log::trace!("p {:?} {:?}", p, others);
             (p,
              others.iter().map(|&(x, y)| {
                  let x = *x as i64;
@@ -46,38 +85,34 @@ impl Day10 {
                          (x + sx, y + sy,
                           obstructed || asteroids.contains(
                               &(x as usize, y as usize))));
-//                eprintln!("{}", obstructed);
+                log::trace!("{}", obstructed);
                  if !obstructed { 1 } else { 0 }
              }).sum())
         }).max_by_key(|&(_, s)| s).unwrap()
     }
 
-    fn part1_impl(self: &Self, input: &mut dyn io::Read) -> BoxResult<usize> {
-        let reader = io::BufReader::new(input);
-        let asteroids = reader.split(b'\n')
-            .filter(|l| !l.as_ref().unwrap().is_empty())
+    /// The asteroid positions, from a map with `#` for an asteroid, or `X` for
+    /// the one the puzzle examples put the station on.
+    pub fn parse(self: &Self, input: &mut dyn io::Read) -> error::Result<Vec<(usize, usize)>> {
+        let lines = parse::lines(input)?;
+        for l in &lines { l.only("#.X", "an asteroid (# or X) or empty space (.)")?; }
+        Ok(lines.into_iter()
+            .filter(|l| !l.text.is_empty())
             .enumerate().flat_map(|(y, r)| {
-                let m = r.unwrap().into_iter().map(|b| b == b'#');
+                let m = r.text.into_bytes().into_iter().map(|b| b == b'#' || b == b'X');
                 let e = m.enumerate();
                 e.filter_map(move|(x, b)|
                     if b { Some((x, y)) } else { None })
             })
-            .collect::<Vec<_>>();
-        Ok(self.find_best(&asteroids).1)
+            .collect::<Vec<_>>())
     }
 
-    fn part2_impl(self: &Self, input: &mut dyn io::Read, n: usize) -> BoxResult<usize> {
-        let reader = io::BufReader::new(input);
-        let asteroids = reader.split(b'\n')
-            .filter(|l| !l.as_ref().unwrap().is_empty())
-            .enumerate().flat_map(|(y, r)| {
-            let m = r.unwrap().into_iter().map(|b| b == b'#');
-            let e = m.enumerate();
-            e.filter_map(move|(x, b)|
-                if b { Some((x, y)) } else { None })
-        })
-            .collect::<Vec<_>>();
-        let &p = self.find_best(&asteroids).0;
+    pub fn part1_impl(self: &Self, asteroids: &[(usize, usize)]) -> error::Result<usize> {
+        Ok(self.find_best(asteroids).1)
+    }
+
+    pub fn part2_impl(self: &Self, asteroids: &[(usize, usize)], n: usize) -> error::Result<usize> {
+        let &p = self.find_best(asteroids).0;
         let (px, py) = p;
         let mut others = asteroids.iter().filter(|&a| p != *a).map(|&a| {
             let  (x, y) = a;
@@ -94,6 +129,9 @@ impl Day10 {
         }).collect::<Vec<_>>();
         others.sort_by(|&(aq, ar, ad, _), &(bq, br, bd, _)|
             aq.cmp(&bq).then(ar.cmp(&br)).then(ad.cmp(&bd)));
+        if n == 0 || n > others.len() {
+            return Err(Error::no_solution(&format!("only {} asteroids to vaporize", others.len())));
+        }
         let mut lq = -1;
         let mut lr = Ratio::new(1, 1);
         let mut i = 0;
@@ -109,6 +147,8 @@ impl Day10 {
                 lr = *r;
             }
             i = (i + 1) % others.len();
+            // A new turn of the laser may start on the ray that ended the last one.
+            if i == 0 { lq = -1; }
         }
         Ok(x * 100 + y)
     }
@@ -117,9 +157,11 @@ impl Day10 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::generate;
+    use std::collections::HashSet;
 
     fn test1(s: &str, o: usize) {
-        assert_eq!(Day10 {}.part1_impl(&mut s.as_bytes()).unwrap(), o);
+        assert_eq!(Day10 {}.part1_impl(&Day10 {}.parse(&mut s.as_bytes()).unwrap()).unwrap(), o);
     }
 
     #[test]
@@ -187,7 +229,7 @@ mod tests {
     }
 
     fn test2(s: &str, n: usize, o: usize) {
-        assert_eq!(Day10 {}.part2_impl(&mut s.as_bytes(), n).unwrap(), o);
+        assert_eq!(Day10 {}.part2_impl(&Day10 {}.parse(&mut s.as_bytes()).unwrap(), n).unwrap(), o);
     }
 
     #[test]
@@ -220,4 +262,23 @@ mod tests {
 #.#.#.#####.####.###
 ###.##.####.##.#..##", 200, 802)
     }
+
+    #[test]
+    fn properties() {
+        let day = Day10 {};
+        generate::check(20, |rng| {
+            let map = (0..8).map(|_| (0..8).map(|_| if rng.one_in(3) { '#' } else { '.' })
+                .collect::<String>() + "\n").collect::<String>();
+            let asteroids = day.parse(&mut map.as_bytes()).unwrap();
+            if asteroids.is_empty() { return; }
+            // The directions to the other asteroids, in lowest terms.
+            let visible = asteroids.iter().map(|&(x, y)| asteroids.iter()
+                .filter(|&&a| a != (x, y))
+                .map(|&(ax, ay)| {
+                    let (dx, dy) = (ax as i64 - x as i64, ay as i64 - y as i64);
+                    (dx / gcd(dx, dy), dy / gcd(dx, dy))
+                }).collect::<HashSet<_>>().len()).max().unwrap();
+            assert_eq!(day.part1_impl(&asteroids).unwrap(), visible, "\n{}", map);
+        });
+    }
 }
\ No newline at end of file
@@ -1,14 +1,10 @@
-use simple_error::bail;
 use evmap;
-use std::error;
 use std::io;
-use std::io::BufRead;
 use std::sync;
 use std::sync::mpsc;
-use std::thread;
 use crate::day;
-
-pub type BoxResult<T> = Result<T, Box<dyn error::Error>>;
+use crate::error::{self, Error, Fault};
+use crate::parse;
 
 struct Intcode {
     p: Vec<i64>,
@@ -26,106 +22,107 @@ impl Intcode {
     }
 
     fn put(&mut self, a: usize, v: i64) {
-//        eprintln!("put @{} {}", a, v);
+        log::trace!("put @{} {}", a, v);
         if a >= self.p.len() { self.p.resize(a + 1, 0); }
         self.p[a] = v;
     }
 
-    fn addr(&mut self, ip: usize, i: usize) -> usize {
+    fn addr(&mut self, ip: usize, i: usize) -> error::Result<usize> {
         let a = self.get(ip + i);
         let v = match self.get(ip) / vec![100, 1000, 10000][i - 1] % 10 {
             0 => a as usize,
             2 => (a  + self.base) as usize,
-            _ => 0, // XXX
+            mode => return Err(Error::intcode(ip, Fault::BadMode(mode))),
         };
-        v
+        Ok(v)
     }
 
-    fn val(&mut self, ip: usize, i: usize) -> i64 {
+    fn val(&mut self, ip: usize, i: usize) -> error::Result<i64> {
         let a = self.get(ip + i);
         let v = match self.get(ip) / vec![100, 1000, 10000][i - 1] % 10 {
             1 => a,
             _ => {
-                let addr = self.addr(ip, i);
+                let addr = self.addr(ip, i)?;
                 self.get(addr)
             },
         };
-//        eprintln!("{} {} {} {} {} {}", ip, self.get(ip), i, a, self.base, v);
-        v
+        log::trace!("{} {} {} {} {} {}", ip, self.get(ip), i, a, self.base, v);
+        Ok(v)
     }
 
     fn run(&mut self, sender: mpsc::Sender<i64>, receiver: mpsc::Receiver<i64>,
-        request: mpsc::Sender<()>, ack: mpsc::Receiver<()>) -> BoxResult<i64> {
+        request: mpsc::Sender<()>, ack: mpsc::Receiver<()>) -> error::Result<i64> {
         let mut ip = 0;
         let mut o = None;
+        let cancel = day::Cancel::current();
         while { let op = self.get(ip); self.op(op) != 99 } {
-//            eprintln!("{}: {} {} {} {}", ip, self.p[ip], self.p[ip + 1], self.p[ip + 2], self.p[ip + 3]);
+            cancel.check()?;
+            log::trace!("{}: {} {} {} {}", ip, self.p[ip], self.p[ip + 1], self.p[ip + 2], self.p[ip + 3]);
             match self.op(self.p[ip]) {
                 1 => {
-                    let a = self.val(ip, 1);
-                    let b = self.val( ip, 2);
-                    let c = self.addr(ip, 3);
+                    let a = self.val(ip, 1)?;
+                    let b = self.val(ip, 2)?;
+                    let c = self.addr(ip, 3)?;
                     self.put(c, a + b);
                     ip += 4;
                 },
                 2 => {
-                    let a = self.val(ip, 1);
-                    let b = self.val(ip, 2);
-                    let c = self.addr(ip, 3);
+                    let a = self.val(ip, 1)?;
+                    let b = self.val(ip, 2)?;
+                    let c = self.addr(ip, 3)?;
                     self.put(c, a * b);
                     ip += 4;
                 },
                 3 => {
-                    let a = self.addr(ip, 1);
-//                    eprintln!(">recv {}", a);
+                    let a = self.addr(ip, 1)?;
+                    log::trace!(">recv {}", a);
                     request.send(())?;
-                    self.put(a as usize, receiver.recv()?);
-//                    eprintln!("<recv {}", self.get(a as usize));
+                    self.put(a as usize, day::recv(&receiver)?);
+                    log::trace!("<recv {}", self.get(a));
                     ip += 2;
                 },
                 4 => {
-                    let a = self.val(ip, 1);
+                    let a = self.val(ip, 1)?;
                     o = Some(a);
-//                    eprintln!(">send {}", a);
+                    log::trace!(">send {}", a);
                     sender.send(a)?;
-//                    eprintln!("<send");
-                    ack.recv()?;
+                    log::trace!("<send");
+                    day::recv(&ack)?;
                     ip += 2;
                 },
                 5 => {
-                    let a = self.val(ip, 1);
-                    let b = self.val(ip, 2) as usize;
+                    let a = self.val(ip, 1)?;
+                    let b = self.val(ip, 2)? as usize;
                     ip = if a != 0 { b } else { ip + 3 };
                 },
                 6 => {
-                    let a = self.val(ip, 1);
-                    let b = self.val(ip, 2) as usize;
+                    let a = self.val(ip, 1)?;
+                    let b = self.val(ip, 2)? as usize;
                     ip = if a == 0 { b } else { ip + 3 };
                 },
                 7 => {
-                    let a = self.val(ip, 1);
-                    let b = self.val(ip, 2);
-                    let c = self.addr(ip, 3);
+                    let a = self.val(ip, 1)?;
+                    let b = self.val(ip, 2)?;
+                    let c = self.addr(ip, 3)?;
                     self.put(c, if a < b { 1 } else { 0 });
                     ip += 4;
                 },
                 8 => {
-                    let a = self.val(ip, 1);
-                    let b = self.val(ip, 2);
-                    let c = self.addr(ip,3);
+                    let a = self.val(ip, 1)?;
+                    let b = self.val(ip, 2)?;
+                    let c = self.addr(ip, 3)?;
                     self.put(c, if a == b { 1 } else { 0 });
                     ip += 4;
                 },
                 9 => {
-                    self.base += self.val(ip, 1);
-//                    eprintln!("<base {}", self.base);
+                    self.base += self.val(ip, 1)?;
+                    log::trace!("<base {}", self.base);
                     ip += 2;
                 }
-                _ => bail!("unknown opcode {}: {}", ip, self.op(self.p[ip])),
+                op => return Err(Error::intcode(ip, Fault::UnknownOpcode(op))),
             };
         }
-        if o.is_none() { bail!("no output"); }
-        Ok(o.unwrap())
+        o.ok_or_else(|| Error::intcode(ip, Fault::NoOutput))
     }
 
     #[allow(dead_code)]
@@ -195,30 +192,45 @@ impl Intcode {
 
 pub struct Day11 {}
 
+inventory::submit! { day::Registration(&Day11 {}) }
+
 impl day::Day for Day11 {
     fn tag(&self) -> &str { "11" }
 
-    fn part1(&self, input: &dyn Fn() -> Box<dyn io::Read>) {
-        let reader = io::BufReader::new(input());
-        let p = reader.split(b',')
-            .map(|v| String::from_utf8(v.unwrap()).unwrap())
-            .map(|s| s.trim_end().parse::<i64>().unwrap())
-            .collect::<Vec<_>>();
-        println!("{:?}", self.part1_impl(p));
+    fn title(&self) -> &str { "Space Police" }
+
+    fn parts(&self) -> [&str; 2] {
+        ["panels painted at least once",
+         "registration identifier painted on the hull"]
     }
+}
+
+impl day::Puzzle for Day11 {
+    type Input = Vec<i64>;
 
-    fn part2(&self, input: &dyn Fn() -> Box<dyn io::Read>) {
-        let reader = io::BufReader::new(input());
-        let p = reader.split(b',')
-            .map(|v| String::from_utf8(v.unwrap()).unwrap())
-            .map(|s| s.trim_end().parse::<i64>().unwrap())
-            .collect::<Vec<_>>();
-        println!("{:?}", self.part2_impl(p));
+    fn parse_input(&self, input: &mut dyn io::Read) -> error::Result<Vec<i64>> {
+        self.parse(input)
+    }
+
+    fn part1(&self, p: &Vec<i64>) -> day::Answer {
+        self.part1_impl(p).into()
+    }
+
+    fn part2(&self, p: &Vec<i64>) -> day::Answer {
+        self.part2_impl(p)
+            .map(|s| s.trim_start_matches('\n').lines().map(String::from).collect::<Vec<_>>())
+            .into()
     }
 }
 
 impl Day11 {
-    fn part1_impl(self: &Self, p: Vec<i64>) -> BoxResult<i64> {
+    /// The Intcode program.
+    pub fn parse(self: &Self, input: &mut dyn io::Read) -> error::Result<Vec<i64>> {
+        parse::numbers(input, "an integer")
+    }
+
+    pub fn part1_impl(self: &Self, p: &[i64]) -> error::Result<i64> {
+        let p = p.to_vec();
         let (input_sender, input_receiver) = mpsc::channel::<i64>();
         let (output_sender, output_receiver) = mpsc::channel::<i64>();
         let (request_sender, request_receiver) = mpsc::channel::<()>();
@@ -231,22 +243,22 @@ impl Day11 {
         let mut dir = (0, -1);
         let area_r = sync::Arc::new(sync::RwLock::new(0i64));
         let area_w = area_r.clone();
-        let cpu = thread::spawn(move || {
+        let cpu = day::spawn(move || {
             let mut ic = Intcode::new(&p);
 //            ic.disassemble();
-            ic.run(output_sender, input_receiver, request_sender, ack_receiver).unwrap();
+            ic.run(output_sender, input_receiver, request_sender, ack_receiver)
         });
-        thread::spawn(move || {
+        day::spawn(move || {
             while request_receiver.recv().is_ok() {
                 let pos = *pos_r.read().unwrap();
                 let input = grid_r
                     .get_and(&pos, |rs| { *rs.first().unwrap_or(&0) })
                     .unwrap_or(0);
-//                eprintln!("input for {:?}: {}", pos, input);
+                log::trace!("input for {:?}: {}", pos, input);
                 input_sender.send(input).unwrap();
             }
         });
-        thread::spawn(move || {
+        day::spawn(move || -> error::Result<()> {
             loop {
                 let output = output_receiver.recv();
                 if output.is_err() { break; }
@@ -254,28 +266,30 @@ impl Day11 {
                 let pos = *pos_r_2.read().unwrap();
                 let mut area = area_w.write().unwrap();
                 if !grid_r_clone.contains_key(&pos) { *area += 1; }
-//                eprintln!("output for {:?} {} area {}", pos, output, *area);
+                log::trace!("output for {:?} {} area {}", pos, output, *area);
                 grid_w.update(pos, output);
                 grid_w.refresh();
-                ack_sender.send(()).unwrap();
-                let output = output_receiver.recv().unwrap();
+                ack_sender.send(())?;
+                let output = output_receiver.recv()?;
                 match output {
                     0 => dir = (dir.1, -dir.0),
                     1 => dir = (-dir.1, dir.0),
-                    _ => { eprintln!("invalid direction") },
+                    _ => { log::debug!("invalid direction {}", output) },
                 }
                 let mut pos = pos_w.write().unwrap();
                 *pos = ((*pos).0 + dir.0, (*pos).1 + dir.1);
-//                eprintln!("turn {} -> {:?}", output, *pos);
-                ack_sender.send(()).unwrap();
+                log::trace!("turn {} -> {:?}", output, *pos);
+                ack_sender.send(())?;
             }
+            Ok(())
         });
-        cpu.join().unwrap();
+        day::join(cpu)?;
         let area = *area_r.read().unwrap();
         Ok(area)
     }
 
-    fn part2_impl(self: &Self, p: Vec<i64>) -> BoxResult<String> {
+    pub fn part2_impl(self: &Self, p: &[i64]) -> error::Result<String> {
+        let p = p.to_vec();
         let (input_sender, input_receiver) = mpsc::channel::<i64>();
         let (output_sender, output_receiver) = mpsc::channel::<i64>();
         let (request_sender, request_receiver) = mpsc::channel::<()>();
@@ -288,18 +302,18 @@ impl Day11 {
         let pos_r_2 = pos_r.clone();
         let pos_w = pos_r.clone();
         let mut dir = (0, -1);
-        thread::spawn(move || {
+        let cpu = day::spawn(move || {
             let mut ic = Intcode::new(&p);
 //            ic.disassemble();
-            ic.run(output_sender, input_receiver, request_sender, ack_receiver).unwrap();
+            ic.run(output_sender, input_receiver, request_sender, ack_receiver)
         });
-        thread::spawn(move || {
+        day::spawn(move || {
             while request_receiver.recv().is_ok() {
                 let pos = *pos_r.read().unwrap();
                 let input = grid_r
                     .get_and(&pos, |rs| { *rs.first().unwrap_or(&0) })
                     .unwrap_or(0);
-//                    eprintln!("input for {:?}: {}", pos, input);
+                    log::trace!("input for {:?}: {}", pos, input);
                 input_sender.send(input).unwrap();
             }
             for y in 0..6 {
@@ -312,26 +326,26 @@ impl Day11 {
                 }
             }
         });
-        let painter = thread::spawn(move || {
+        let painter = day::spawn(move || -> error::Result<String> {
             loop {
                 let output = output_receiver.recv();
                 if output.is_err() { break; }
                 let output = output.unwrap();
                 let pos = *pos_r_2.read().unwrap();
-//                eprintln!("output for {:?} {}", pos, output);
+                log::trace!("output for {:?} {}", pos, output);
                 grid_w.update(pos, output);
                 grid_w.refresh();
-                ack_sender.send(()).unwrap();
-                let output = output_receiver.recv().unwrap();
+                ack_sender.send(())?;
+                let output = output_receiver.recv()?;
                 match output {
                     0 => dir = (dir.1, -dir.0),
                     1 => dir = (-dir.1, dir.0),
-                    _ => { eprintln!("invalid direction") },
+                    _ => { log::debug!("invalid direction {}", output) },
                 }
                 let mut pos = pos_w.write().unwrap();
                 *pos = ((*pos).0 + dir.0, (*pos).1 + dir.1);
-//                    eprintln!("turn {} -> {:?}", output, *pos);
-                ack_sender.send(()).unwrap();
+                    log::trace!("turn {} -> {:?}", output, *pos);
+                ack_sender.send(())?;
             }
             let mut output = String::from("");
             for y in 0..6 {
@@ -344,10 +358,12 @@ impl Day11 {
                 }
                 output = format!("{}\n{}", output, s);
             }
-            output
+            Ok(output)
         });
-        let output = painter.join().unwrap();
-        eprintln!("{}", output);
+        let output = day::join(painter);
+        day::join(cpu)?;
+        let output = output?;
+        log::debug!("{}", output);
         Ok(output)
     }
 }
@@ -359,7 +375,7 @@ mod tests {
     fn test1(v: Vec<i64>, o: i64) {
         let mut p: Vec<i64> = v.iter().flat_map(|i| vec![104, *i]).collect();
         p.push(99);
-        assert_eq!(Day11 {}.part1_impl(p).unwrap(), o);
+        assert_eq!(Day11 {}.part1_impl(&p).unwrap(), o);
     }
 
     #[test]
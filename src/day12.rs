@@ -2,12 +2,11 @@ use num::{abs, signum};
 use num_integer::lcm;
 use regex::Regex;
 use std::collections::HashSet;
-use std::error;
 use std::io;
-use std::io::BufRead;
-use crate::day;
-
-pub type BoxResult<T> = Result<T, Box<dyn error::Error>>;
+use crate::day::{self, Day};
+use crate::error;
+use crate::generate::Rng;
+use crate::parse;
 
 #[derive(PartialEq, Eq, Hash, Clone)]
 struct Space {
@@ -42,32 +41,69 @@ impl Moon {
 
 pub struct Day12 {}
 
+inventory::submit! { day::Registration(&Day12 {}) }
+
 impl day::Day for Day12 {
     fn tag(&self) -> &str { "12" }
 
-    fn part1(&self, input: &dyn Fn() -> Box<dyn io::Read>) {
-        println!("{:?}", self.part1_impl(&mut *input(), 1000));
+    fn title(&self) -> &str { "The N-Body Problem" }
+
+    fn parts(&self) -> [&str; 2] {
+        ["total energy after the steps",
+         "steps until the moons repeat a previous state"]
+    }
+
+    fn params(&self) -> &[day::Param] {
+        &[day::Param { name: "steps", default: 1000, min: 0, max: i64::MAX,
+                      help: "steps to simulate in part 1" }]
+    }
+
+    fn generate(&self, rng: &mut Rng, size: Option<usize>) -> Option<String> {
+        Some(self.generate_impl(rng, size.unwrap_or(4)))
+    }
+}
+
+impl day::Puzzle for Day12 {
+    type Input = Vec<(i64, i64, i64)>;
+
+    fn parse_input(&self, input: &mut dyn io::Read) -> error::Result<Vec<(i64, i64, i64)>> {
+        self.parse(input)
+    }
+
+    fn part1(&self, positions: &Vec<(i64, i64, i64)>) -> day::Answer {
+        let steps = self.param("steps") as usize;
+        self.part1_impl(positions, steps).into()
     }
 
-    fn part2(&self, input: &dyn Fn() -> Box<dyn io::Read>) {
-        println!("{:?}", self.part2_impl(&mut *input()));
+    fn part2(&self, positions: &Vec<(i64, i64, i64)>) -> day::Answer {
+        self.part2_impl(positions).into()
     }
 }
 
 impl Day12 {
-    fn part1_impl(self: &Self, input: &mut dyn io::Read, n: usize) -> BoxResult<i64> {
-        let reader = io::BufReader::new(input);
+    /// `size` moons with coordinates between -20 and 20.
+    pub fn generate_impl(self: &Self, rng: &mut Rng, size: usize) -> String {
+        (0..size).map(|_| format!("<x={}, y={}, z={}>\n",
+                                  rng.range(-20, 20), rng.range(-20, 20), rng.range(-20, 20)))
+            .collect()
+    }
+
+    /// The moon positions, one `<x=.., y=.., z=..>` per line.
+    pub fn parse(self: &Self, input: &mut dyn io::Read) -> error::Result<Vec<(i64, i64, i64)>> {
         lazy_static! {
             static ref RE: Regex = Regex::new("<x=(.+), y=(.+), z=(.+)>").unwrap();
         }
-        let mut space = Space::new(reader.lines().map(|l| {
-            let l = l.unwrap();
-            let cap = RE.captures(&l).unwrap();
-            Moon::new(
-                cap[1].parse().unwrap(),
-                cap[2].parse().unwrap(),
-                cap[3].parse().unwrap(), )
-        }).collect());
+        let lines = parse::lines(input)?;
+        Ok(lines.iter().map(|l| {
+            let cap = RE.captures(&l.text)
+                .ok_or_else(|| l.expected("a position like <x=1, y=2, z=3>"))?;
+            Ok((l.capture(&cap, 1, "a number")?, l.capture(&cap, 2, "a number")?,
+                l.capture(&cap, 3, "a number")?))
+        }).collect::<Result<_, parse::ParseError>>()?)
+    }
+
+    pub fn part1_impl(self: &Self, positions: &[(i64, i64, i64)], n: usize) -> error::Result<i64> {
+        let mut space = Space::new(positions.iter().map(|&(x, y, z)| Moon::new(x, y, z)).collect());
         let apply_gravity = |space: &mut Space| {
             let moons = &mut space.moons;
             for i in 0..moons.len() {
@@ -94,19 +130,8 @@ impl Day12 {
         Ok(space.moons.iter().map(|m| m.e()).sum())
     }
 
-    fn part2_impl(self: &Self, input: &mut dyn io::Read) -> BoxResult<i64> {
-        let reader = io::BufReader::new(input);
-        lazy_static! {
-            static ref RE: Regex = Regex::new("<x=(.+), y=(.+), z=(.+)>").unwrap();
-        }
-        let mut space = Space::new(reader.lines().map(|l| {
-            let l = l.unwrap();
-            let cap = RE.captures(&l).unwrap();
-            Moon::new(
-                cap[1].parse().unwrap(),
-                cap[2].parse().unwrap(),
-                cap[3].parse().unwrap(), )
-        }).collect());
+    pub fn part2_impl(self: &Self, positions: &[(i64, i64, i64)]) -> error::Result<i64> {
+        let mut space = Space::new(positions.iter().map(|&(x, y, z)| Moon::new(x, y, z)).collect());
         let apply_x_gravity = |space: &mut Space| {
             let moons = &mut space.moons;
             for i in 0..moons.len() {
@@ -152,9 +177,11 @@ impl Day12 {
                 moon.z += moon.vz;
             }
         };
+        let cancel = day::Cancel::current();
         let mut xp = 0i64;
         let mut seen: HashSet<Space> = HashSet::new();
         for _ in 0i64.. {
+            cancel.check()?;
             apply_x_gravity(&mut space);
             apply_x_velocity(&mut space);
             if !seen.insert(space.clone()) { break; }
@@ -163,6 +190,7 @@ impl Day12 {
         let mut yp = 0i64;
         let mut seen: HashSet<Space> = HashSet::new();
         for _ in 0i64.. {
+            cancel.check()?;
             apply_y_gravity(&mut space);
             apply_y_velocity(&mut space);
             if !seen.insert(space.clone()) { break; }
@@ -171,6 +199,7 @@ impl Day12 {
         let mut zp = 0i64;
         let mut seen: HashSet<Space> = HashSet::new();
         for _ in 0i64.. {
+            cancel.check()?;
             apply_z_gravity(&mut space);
             apply_z_velocity(&mut space);
             if !seen.insert(space.clone()) { break; }
@@ -186,7 +215,7 @@ mod tests {
 
     fn test1(s: &str, n: usize, v: i64) {
         assert_eq!(
-            Day12 {}.part1_impl(&mut s.as_bytes(), n).unwrap(), v);
+            Day12 {}.part1_impl(&Day12 {}.parse(&mut s.as_bytes()).unwrap(), n).unwrap(), v);
     }
 
 
@@ -204,7 +233,7 @@ mod tests {
 
     fn test2(s: &str, v: i64) {
         assert_eq!(
-            Day12 {}.part2_impl(&mut s.as_bytes()).unwrap(), v);
+            Day12 {}.part2_impl(&Day12 {}.parse(&mut s.as_bytes()).unwrap()).unwrap(), v);
     }
 
 
@@ -219,4 +248,13 @@ mod tests {
 <x=2, y=-7, z=3>
 <x=9, y=-8, z=-3>", 4686774924);
     }
+
+    #[test]
+    fn bad_input() {
+        let e = Day12 {}.parse(&mut "<x=-1, y=0, z=2>\n<x=2, y=?, z=-7>\n".as_bytes()).unwrap_err();
+        match e {
+            error::Error::Parse(_, e) => assert_eq!((e.line, e.column, e.text.as_str()), (2, 9, "?")),
+            e => panic!("expected a parse error, got {:?}", e),
+        }
+    }
 }
\ No newline at end of file
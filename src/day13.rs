@@ -1,14 +1,10 @@
-use simple_error::bail;
 use evmap;
 use num::signum;
-use std::error;
 use std::io;
-use std::io::BufRead;
 use std::sync::mpsc;
-use std::thread;
 use crate::day;
-
-pub type BoxResult<T> = Result<T, Box<dyn error::Error>>;
+use crate::error::{self, Error, Fault};
+use crate::parse;
 
 struct Intcode {
     p: Vec<i64>,
@@ -26,106 +22,107 @@ impl Intcode {
     }
 
     fn put(&mut self, a: usize, v: i64) {
-//        eprintln!("put @{} {}", a, v);
+        log::trace!("put @{} {}", a, v);
         if a >= self.p.len() { self.p.resize(a + 1, 0); }
         self.p[a] = v;
     }
 
-    fn addr(&mut self, ip: usize, i: usize) -> usize {
+    fn addr(&mut self, ip: usize, i: usize) -> error::Result<usize> {
         let a = self.get(ip + i);
         let v = match self.get(ip) / vec![100, 1000, 10000][i - 1] % 10 {
             0 => a as usize,
             2 => (a  + self.base) as usize,
-            _ => 0, // XXX
+            mode => return Err(Error::intcode(ip, Fault::BadMode(mode))),
         };
-        v
+        Ok(v)
     }
 
-    fn val(&mut self, ip: usize, i: usize) -> i64 {
+    fn val(&mut self, ip: usize, i: usize) -> error::Result<i64> {
         let a = self.get(ip + i);
         let v = match self.get(ip) / vec![100, 1000, 10000][i - 1] % 10 {
             1 => a,
             _ => {
-                let addr = self.addr(ip, i);
+                let addr = self.addr(ip, i)?;
                 self.get(addr)
             },
         };
-//        eprintln!("{} {} {} {} {} {}", ip, self.get(ip), i, a, self.base, v);
-        v
+        log::trace!("{} {} {} {} {} {}", ip, self.get(ip), i, a, self.base, v);
+        Ok(v)
     }
 
     fn run(&mut self, sender: mpsc::Sender<i64>, receiver: mpsc::Receiver<i64>,
-        request: mpsc::Sender<()>, ack: mpsc::Receiver<()>) -> BoxResult<i64> {
+        request: mpsc::Sender<()>, ack: mpsc::Receiver<()>) -> error::Result<i64> {
         let mut ip = 0;
         let mut o = None;
+        let cancel = day::Cancel::current();
         while { let op = self.get(ip); self.op(op) != 99 } {
-//            eprintln!("{}: {} {} {} {}", ip, self.p[ip], self.p[ip + 1], self.p[ip + 2], self.p[ip + 3]);
+            cancel.check()?;
+            log::trace!("{}: {} {} {} {}", ip, self.p[ip], self.p[ip + 1], self.p[ip + 2], self.p[ip + 3]);
             match self.op(self.p[ip]) {
                 1 => {
-                    let a = self.val(ip, 1);
-                    let b = self.val( ip, 2);
-                    let c = self.addr(ip, 3);
+                    let a = self.val(ip, 1)?;
+                    let b = self.val(ip, 2)?;
+                    let c = self.addr(ip, 3)?;
                     self.put(c, a + b);
                     ip += 4;
                 },
                 2 => {
-                    let a = self.val(ip, 1);
-                    let b = self.val(ip, 2);
-                    let c = self.addr(ip, 3);
+                    let a = self.val(ip, 1)?;
+                    let b = self.val(ip, 2)?;
+                    let c = self.addr(ip, 3)?;
                     self.put(c, a * b);
                     ip += 4;
                 },
                 3 => {
-                    let a = self.addr(ip, 1);
-//                    eprintln!(">recv {}", a);
+                    let a = self.addr(ip, 1)?;
+                    log::trace!(">recv {}", a);
                     request.send(())?;
-                    self.put(a as usize, receiver.recv()?);
-//                    eprintln!("<recv {}", self.get(a as usize));
+                    self.put(a as usize, day::recv(&receiver)?);
+                    log::trace!("<recv {}", self.get(a));
                     ip += 2;
                 },
                 4 => {
-                    let a = self.val(ip, 1);
+                    let a = self.val(ip, 1)?;
                     o = Some(a);
-//                    eprintln!(">send {}", a);
+                    log::trace!(">send {}", a);
                     sender.send(a)?;
-//                    eprintln!("<send");
-                    ack.recv()?;
+                    log::trace!("<send");
+                    day::recv(&ack)?;
                     ip += 2;
                 },
                 5 => {
-                    let a = self.val(ip, 1);
-                    let b = self.val(ip, 2) as usize;
+                    let a = self.val(ip, 1)?;
+                    let b = self.val(ip, 2)? as usize;
                     ip = if a != 0 { b } else { ip + 3 };
                 },
                 6 => {
-                    let a = self.val(ip, 1);
-                    let b = self.val(ip, 2) as usize;
+                    let a = self.val(ip, 1)?;
+                    let b = self.val(ip, 2)? as usize;
                     ip = if a == 0 { b } else { ip + 3 };
                 },
                 7 => {
-                    let a = self.val(ip, 1);
-                    let b = self.val(ip, 2);
-                    let c = self.addr(ip, 3);
+                    let a = self.val(ip, 1)?;
+                    let b = self.val(ip, 2)?;
+                    let c = self.addr(ip, 3)?;
                     self.put(c, if a < b { 1 } else { 0 });
                     ip += 4;
                 },
                 8 => {
-                    let a = self.val(ip, 1);
-                    let b = self.val(ip, 2);
-                    let c = self.addr(ip,3);
+                    let a = self.val(ip, 1)?;
+                    let b = self.val(ip, 2)?;
+                    let c = self.addr(ip, 3)?;
                     self.put(c, if a == b { 1 } else { 0 });
                     ip += 4;
                 },
                 9 => {
-                    self.base += self.val(ip, 1);
-//                    eprintln!("<base {}", self.base);
+                    self.base += self.val(ip, 1)?;
+                    log::trace!("<base {}", self.base);
                     ip += 2;
                 }
-                _ => bail!("unknown opcode {}: {}", ip, self.op(self.p[ip])),
+                op => return Err(Error::intcode(ip, Fault::UnknownOpcode(op))),
             };
         }
-        if o.is_none() { bail!("no output"); }
-        Ok(o.unwrap())
+        o.ok_or_else(|| Error::intcode(ip, Fault::NoOutput))
     }
 
     #[allow(dead_code)]
@@ -195,46 +192,62 @@ impl Intcode {
 
 pub struct Day13 {}
 
+inventory::submit! { day::Registration(&Day13 {}) }
+
 impl day::Day for Day13 {
     fn tag(&self) -> &str { "13" }
 
-    fn part1(&self, input: &dyn Fn() -> Box<dyn io::Read>) {
-        let reader = io::BufReader::new(input());
-        let p = reader.split(b',')
-            .map(|v| String::from_utf8(v.unwrap()).unwrap())
-            .map(|s| s.trim_end().parse::<i64>().unwrap())
-            .collect::<Vec<_>>();
-        println!("{:?}", self.part1_impl(p));
+    fn title(&self) -> &str { "Care Package" }
+
+    fn parts(&self) -> [&str; 2] {
+        ["block tiles on the screen",
+         "score after the last block is broken"]
     }
+}
+
+impl day::Puzzle for Day13 {
+    type Input = Vec<i64>;
 
-    fn part2(&self, input: &dyn Fn() -> Box<dyn io::Read>) {
-        let reader = io::BufReader::new(input());
-        let p = reader.split(b',')
-            .map(|v| String::from_utf8(v.unwrap()).unwrap())
-            .map(|s| s.trim_end().parse::<i64>().unwrap())
-            .collect::<Vec<_>>();
-        println!("{:?}", self.part2_impl(p));
+    fn parse_input(&self, input: &mut dyn io::Read) -> error::Result<Vec<i64>> {
+        self.parse(input)
+    }
+
+    fn part1(&self, p: &Vec<i64>) -> day::Answer {
+        self.part1_impl(p).into()
+    }
+
+    fn part2(&self, p: &Vec<i64>) -> day::Answer {
+        self.part2_impl(p).into()
     }
 }
 
 impl Day13 {
-    fn part1_impl(self: &Self, p: Vec<i64>) -> BoxResult<usize> {
+    /// The Intcode program.
+    pub fn parse(self: &Self, input: &mut dyn io::Read) -> error::Result<Vec<i64>> {
+        parse::numbers(input, "an integer")
+    }
+
+    pub fn part1_impl(self: &Self, p: &[i64]) -> error::Result<usize> {
+        let p = p.to_vec();
         let (_input_sender, input_receiver) = mpsc::channel::<i64>();
         let (output_sender, output_receiver) = mpsc::channel::<i64>();
         let (request_sender, _request_receiver) = mpsc::channel::<()>();
         let (ack_sender, ack_receiver) = mpsc::channel::<()>();
-        let _cpu = thread::spawn(move || {
+        let cpu = day::spawn(move || {
             let mut ic = Intcode::new(&p);
-            ic.run(output_sender, input_receiver, request_sender, ack_receiver).unwrap();
+            ic.run(output_sender, input_receiver, request_sender, ack_receiver)
         });
-        Ok(output_receiver.iter().enumerate().filter(|(i, x)| {
+        let blocks = output_receiver.iter().enumerate().filter(|(i, x)| {
             ack_sender.send(()).unwrap();
             i % 3 == 2 && *x == 2
         })
-            .count())
+            .count();
+        day::join(cpu)?;
+        Ok(blocks)
     }
 
-    fn part2_impl(self: &Self, p: Vec<i64>) -> BoxResult<i64> {
+    pub fn part2_impl(self: &Self, p: &[i64]) -> error::Result<i64> {
+        let p = p.to_vec();
         let (input_sender, input_receiver) = mpsc::channel::<i64>();
         let (output_sender, output_receiver) = mpsc::channel::<i64>();
         let (request_sender, request_receiver) = mpsc::channel::<()>();
@@ -242,26 +255,26 @@ impl Day13 {
         let (grid_r, mut grid_w) = evmap::new();
         let (comm_r, mut comm_w) = evmap::new();
 //        let comm_r_2 = comm_r.clone();
-        let _cpu = thread::spawn(move || {
+        let cpu = day::spawn(move || {
             let mut ic = Intcode::new(&p);
             ic.p[0] = 2;
-            ic.run(output_sender, input_receiver, request_sender, ack_receiver).unwrap();
+            ic.run(output_sender, input_receiver, request_sender, ack_receiver)
         });
-        let screen = thread::spawn(move || {
+        let screen = day::spawn(move || -> error::Result<i64> {
             let mut score = 0;
             loop {
                 let x = output_receiver.recv();
                 if x.is_err() { break; }
                 let x =  x.unwrap();
-                ack_sender.send(()).unwrap();
-                let y = output_receiver.recv().unwrap();
-                ack_sender.send(()).unwrap();
+                ack_sender.send(())?;
+                let y = output_receiver.recv()?;
+                ack_sender.send(())?;
                 if x == -1 && y == 0 {
-                    score = output_receiver.recv().unwrap();
+                    score = output_receiver.recv()?;
 //                    comm_w.update("score", score);
 //                    comm_w.refresh();
                 } else {
-                    let tile = output_receiver.recv().unwrap();
+                    let tile = output_receiver.recv()?;
                     match tile {
                         0 => {
                             grid_w.update((x, y), ' ');
@@ -287,14 +300,14 @@ impl Day13 {
                             grid_w.update((x, y), 'o');
                             grid_w.refresh();
                         },
-                        _ => { eprintln!("unknown tile id"); },
+                        _ => { log::debug!("unknown tile id {}", tile); },
                     };
                 }
-                ack_sender.send(()).unwrap();
+                ack_sender.send(())?;
             }
-            score
+            Ok(score)
         });
-        thread::spawn(move || {
+        day::spawn(move || {
             while request_receiver.recv().is_ok() {
                 let mut grid = [[' '; 42]; 20];
                 grid_r.for_each(|&(x, y), tile| {
@@ -310,7 +323,9 @@ impl Day13 {
                 input_sender.send(joystick).unwrap();
             }
         });
-        Ok(screen.join().unwrap())
+        let score = day::join(screen);
+        day::join(cpu)?;
+        score
 //        Ok(comm_r_2.get_and("score", |score| score[0]).unwrap())
     }
 }
\ No newline at end of file
@@ -1,143 +1,184 @@
 use regex::Regex;
-use std::collections::HashMap;
-use std::error;
+use std::collections::{HashMap, HashSet};
 use std::io;
-use std::io::BufRead;
-use crate::day;
+use crate::day::{self, Day};
+use crate::error::{self, Error};
+use crate::generate::Rng;
+use crate::parse;
 
-pub type BoxResult<T> = Result<T, Box<dyn error::Error>>;
+/// The reactions by their output chemical, with the output amount and the inputs.
+pub type Reactions = HashMap<String, (i64, Vec<(i64, String)>)>;
 
 pub struct Day14 {}
 
+inventory::submit! { day::Registration(&Day14 {}) }
+
 impl day::Day for Day14 {
     fn tag(&self) -> &str { "14" }
 
-    fn part1(&self, input: &dyn Fn() -> Box<dyn io::Read>) {
-        println!("{:?}", self.part1_impl(&mut *input()));
+    fn title(&self) -> &str { "Space Stoichiometry" }
+
+    fn parts(&self) -> [&str; 2] {
+        ["ORE required for one FUEL",
+         "FUEL produced from the available ORE"]
+    }
+
+    fn params(&self) -> &[day::Param] {
+        &[day::Param { name: "ore", default: 1000000000000, min: 0, max: i64::MAX,
+                      help: "ORE available in part 2" }]
+    }
+
+    fn version(&self) -> u32 { 2 }
+
+    fn generate(&self, rng: &mut Rng, size: Option<usize>) -> Option<String> {
+        Some(self.generate_impl(rng, size.unwrap_or(20)))
+    }
+}
+
+impl day::Puzzle for Day14 {
+    type Input = Reactions;
+
+    fn parse_input(&self, input: &mut dyn io::Read) -> error::Result<Reactions> {
+        self.parse(input)
     }
 
-    fn part2(&self, input: &dyn Fn() -> Box<dyn io::Read>) {
-        println!("{:?}", self.part2_impl(&mut *input()));
+    fn part1(&self, reactions: &Reactions) -> day::Answer {
+        self.part1_impl(reactions).into()
+    }
+
+    fn part2(&self, reactions: &Reactions) -> day::Answer {
+        let ore = self.param("ore");
+        self.part2_impl(reactions, ore).into()
     }
 }
 
 impl Day14 {
-    fn part1_impl(self: &Self, input: &mut dyn io::Read) -> BoxResult<i64> {
-        let reader = io::BufReader::new(input);
-        lazy_static! {
-            static ref REACTION: Regex = Regex::new("(.+) => (.+) (.+)").unwrap();
-        }
-        let mut reactions = HashMap::new();
-        reader.lines().for_each(|l| {
-            let l = l.unwrap();
-            let cap = REACTION.captures(&l).unwrap();
-            let inputs: Vec<(i64, String)> = cap[1].split(", ").map(|s| {
-                let s: Vec<_> = s.split(" ").collect();
-                (s[0].to_string().parse().unwrap(), s[1].to_string())
-            }).collect();
-            reactions.insert(cap[3].to_string(),
-                     (cap[2].to_string().parse::<i64>().unwrap(), inputs));
-        });
-        let (output, need) = ("FUEL", 1);
-        let mut stock = HashMap::new();
-        let mut ore = 0;
-        fn extract(
-            output: &str,
-            need: i64,
-            reactions: &HashMap<String, (i64, Vec<(i64, String)>)>,
-            stock: &mut HashMap<String, i64>,
-            ore: &mut i64) {
-            let mut supply = stock.get(output).map(|v| *v).unwrap_or(0);
-            if supply < need {
-                let (output_cnt, inputs) = reactions.get(output).unwrap();
-                let factor = (need - supply - 1) / output_cnt + 1;
-                for (input_cnt, input) in inputs {
-                    let amount = input_cnt * factor;
-                    if input == "ORE" {
-                        *ore += amount;
-                    } else {
-                        extract(input, amount, reactions, stock, ore);
-                    }
-                }
-                supply += output_cnt * factor;
+    /// Reactions for `size` chemicals made from ORE and the chemicals before
+    /// them, and one for FUEL.  A chemical that would take too much ORE is
+    /// made from ORE only, to keep the amounts in range.
+    pub fn generate_impl(self: &Self, rng: &mut Rng, size: usize) -> String {
+        let mut names = HashSet::new();
+        let mut chemicals = vec![(String::from("ORE"), 1.0)];
+        let mut reactions = vec![];
+        while chemicals.len() <= size + 1 {
+            let fuel = chemicals.len() == size + 1;
+            let len = rng.range(1, 5) as usize;
+            let name = if fuel { String::from("FUEL") } else { rng.name(len) };
+            if name == "ORE" || name == "FUEL" && !fuel || !names.insert(name.clone()) { continue; }
+            let output = if fuel { 1 } else { rng.range(1, 10) };
+            let mut inputs = vec![];
+            for _ in 0..rng.range(1, if fuel { 5 } else { 3 }) {
+                let i = rng.below(chemicals.len());
+                if inputs.iter().all(|&(j, _)| j != i) { inputs.push((i, rng.range(1, 9))); }
+            }
+            let mut cost = inputs.iter().map(|&(i, n)| chemicals[i].1 * n as f64).sum::<f64>();
+            if cost / output as f64 > 1e5 {
+                inputs = vec![(0, rng.range(1, 9))];
+                cost = inputs[0].1 as f64;
             }
-            stock.insert(output.to_string(), supply - need);
-        };
-        extract(output, need, &reactions, &mut stock, &mut ore);
-        Ok(ore)
+            reactions.push(format!("{} => {} {}", inputs.iter()
+                .map(|&(i, n)| format!("{} {}", n, chemicals[i].0)).collect::<Vec<_>>()
+                .join(", "), output, name));
+            chemicals.push((name, cost / output as f64 + 1.0));
+        }
+        rng.shuffle(&mut reactions);
+        reactions.join("\n") + "\n"
     }
 
-    fn part2_impl(self: &Self, input: &mut dyn io::Read) -> BoxResult<i64> {
-        let reader = io::BufReader::new(input);
+    pub fn parse(self: &Self, input: &mut dyn io::Read) -> error::Result<Reactions> {
         lazy_static! {
             static ref REACTION: Regex = Regex::new("(.+) => (.+) (.+)").unwrap();
         }
+        let lines = parse::lines(input)?;
         let mut reactions = HashMap::new();
-        reader.lines().for_each(|l| {
-            let l = l.unwrap();
-            let cap = REACTION.captures(&l).unwrap();
-            let inputs: Vec<(i64, String)> = cap[1].split(", ").map(|s| {
-                let s: Vec<_> = s.split(" ").collect();
-                (s[0].to_string().parse().unwrap(), s[1].to_string())
-            }).collect();
-            reactions.insert(cap[3].to_string(),
-                             (cap[2].to_string().parse::<i64>().unwrap(), inputs));
-        });
-        let (output, need) = ("FUEL", 1);
-        let mut stock = HashMap::new();
-        let mut ore = 0;
-        fn extract(
-            output: &str,
-            need: i64,
-            reactions: &HashMap<String, (i64, Vec<(i64, String)>)>,
-            stock: &mut HashMap<String, i64>,
-            ore: &mut i64) -> bool {
-            let mut supply = stock.get(output).map(|v| *v).unwrap_or(0);
-            if supply < need {
-                let (output_cnt, inputs) = reactions.get(output).unwrap();
-                let factor = (need - supply - 1) / output_cnt + 1;
-                for (input_cnt, input) in inputs {
-                    let amount = input_cnt * factor;
-                    if input == "ORE" {
-                        *ore += amount;
-                    } else {
-                        extract(input, amount, reactions, stock, ore);
-                    }
-                }
-                supply += output_cnt * factor;
-            }
-            stock.insert(output.to_string(), supply - need);
-            true
-        };
-        let mut fuel = 0;
-        loop {
-            extract(output, need, &reactions, &mut stock, &mut ore);
-            if ore >= 1000000000000 { break; }
-            fuel += 1;
-            if stock.iter().all(|(_, v)| *v == 0) { break; }
+        let mut used = vec![];
+        for l in &lines {
+            let cap = REACTION.captures(&l.text)
+                .ok_or_else(|| l.expected("a reaction like 7 A, 1 E => 1 FUEL"))?;
+            let inputs = cap.get(1).unwrap().as_str().split(", ").map(|s|
+                match s.split_once(' ') {
+                    Some((amount, chemical)) => {
+                        used.push((l, chemical));
+                        Ok((l.field(amount, "an amount")?, chemical.to_string()))
+                    },
+                    None => Err(l.error(s, "an amount and a chemical")),
+                }).collect::<Result<Vec<(i64, String)>, _>>()?;
+            reactions.insert(cap[3].to_string(), (l.capture(&cap, 2, "an amount")?, inputs));
         }
-        if ore < 1000000000000 {
-            let cycles = 1000000000000 / ore;
-            fuel *= cycles;
-            ore *= cycles;
-            ore -= 1000000000000;
-            loop {
-                extract(output, need, &reactions, &mut stock, &mut ore);
-                if ore >= 0 { break; }
-                fuel += 1;
-            }
+        // Every chemical but ORE has to come from somewhere.
+        let missing = used.into_iter().find(|&(_, c)| c != "ORE" && !reactions.contains_key(c));
+        if let Some((l, chemical)) = missing {
+            return Err(l.error(chemical, "a chemical that a reaction produces").into());
+        }
+        if !reactions.contains_key("FUEL") {
+            return Err(Error::input("no reaction produces FUEL"));
+        }
+        Ok(reactions)
+    }
+
+    pub fn part1_impl(self: &Self, reactions: &Reactions) -> error::Result<i64> {
+        Ok(ore_for(reactions, 1))
+    }
+
+    /// The most FUEL the available ORE makes, found by doubling the amount
+    /// until it takes too much ORE and then halving the range.
+    pub fn part2_impl(self: &Self, reactions: &Reactions, available: i64)
+        -> error::Result<i64> {
+        let cancel = day::Cancel::current();
+        let (mut fuel, mut over) = (0, 1);
+        while ore_for(reactions, over) <= available {
+            cancel.check()?;
+            fuel = over;
+            over *= 2;
+        }
+        while over - fuel > 1 {
+            cancel.check()?;
+            let mid = fuel + (over - fuel) / 2;
+            if ore_for(reactions, mid) <= available { fuel = mid; } else { over = mid; }
         }
         Ok(fuel)
     }
 }
 
+/// The ORE needed to make `fuel` FUEL, making each chemical in as few
+/// reactions as it takes and keeping what is left over for later.
+fn ore_for(reactions: &Reactions, fuel: i64) -> i64 {
+    let mut stock = HashMap::new();
+    let mut ore = 0;
+    fn extract(
+        output: &str,
+        need: i64,
+        reactions: &Reactions,
+        stock: &mut HashMap<String, i64>,
+        ore: &mut i64) {
+        let mut supply = stock.get(output).map(|v| *v).unwrap_or(0);
+        if supply < need {
+            let (output_cnt, inputs) = reactions.get(output).unwrap();
+            let factor = (need - supply - 1) / output_cnt + 1;
+            for (input_cnt, input) in inputs {
+                let amount = input_cnt * factor;
+                if input == "ORE" {
+                    *ore += amount;
+                } else {
+                    extract(input, amount, reactions, stock, ore);
+                }
+            }
+            supply += output_cnt * factor;
+        }
+        stock.insert(output.to_string(), supply - need);
+    }
+    extract("FUEL", fuel, reactions, &mut stock, &mut ore);
+    ore
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::generate;
 
     fn test1(s: &str, v: i64) {
         assert_eq!(
-            Day14 {}.part1_impl(&mut s.as_bytes()).unwrap(), v);
+            Day14 {}.part1_impl(&Day14 {}.parse(&mut s.as_bytes()).unwrap()).unwrap(), v);
     }
 
 
@@ -194,11 +235,16 @@ mod tests {
 121 ORE => 7 VRPVC
 7 XCVML => 6 RJRHP
 5 BHXH, 4 VRPVC => 5 LTCX", 2210736);
+        let e = Day14 {}.parse(&mut "10 ORE => 10 A\n7 A, 1 B => 1 FUEL\n".as_bytes()).err();
+        assert_eq!(e.unwrap().to_string(),
+                   "line 2, column 8: expected a chemical that a reaction produces, found \"B\"");
+        assert!(Day14 {}.parse(&mut "10 ORE => 10 A\n".as_bytes()).is_err());
     }
 
     fn test2(s: &str, v: i64) {
         assert_eq!(
-            Day14 {}.part2_impl(&mut s.as_bytes()).unwrap(), v);
+            Day14 {}.part2_impl(&Day14 {}.parse(&mut s.as_bytes()).unwrap(), 1000000000000)
+                .unwrap(), v);
     }
 
 
@@ -243,4 +289,41 @@ mod tests {
 7 XCVML => 6 RJRHP
 5 BHXH, 4 VRPVC => 5 LTCX", 460664);
     }
+
+    #[test]
+    fn properties() {
+        let day = Day14 {};
+        generate::check(20, |rng| {
+            let reactions = day.parse(&mut day.generate_impl(rng, 10).as_bytes()).unwrap();
+            let ore = day.part1_impl(&reactions).unwrap();
+            assert_eq!(fuel_made(&reactions, ore - 1), 0);
+            for &available in &[ore, ore * rng.range(1, 1000) + rng.range(0, ore - 1)] {
+                assert_eq!(day.part2_impl(&reactions, available).unwrap(),
+                           fuel_made(&reactions, available));
+            }
+        });
+    }
+
+    /// The FUEL the ORE makes one FUEL at a time, each from what is in stock
+    /// and as many reactions as the rest takes.
+    fn fuel_made(reactions: &Reactions, mut ore: i64) -> i64 {
+        let mut stock = HashMap::new();
+        let mut fuel = 0;
+        loop {
+            let mut needs = vec![(String::from("FUEL"), 1)];
+            let mut used = 0;
+            while let Some((chemical, need)) = needs.pop() {
+                if chemical == "ORE" { used += need; continue; }
+                let have = stock.entry(chemical.clone()).or_insert(0);
+                if *have >= need { *have -= need; continue; }
+                let (made, inputs) = &reactions[&chemical];
+                let runs = (need - *have + made - 1) / made;
+                *have += runs * made - need;
+                needs.extend(inputs.iter().map(|(n, input)| (input.clone(), n * runs)));
+            }
+            if used > ore { return fuel; }
+            ore -= used;
+            fuel += 1;
+        }
+    }
 }
\ No newline at end of file
@@ -1,13 +1,9 @@
-use simple_error::bail;
 use evmap;
-use std::error;
 use std::io;
-use std::io::BufRead;
 use std::sync::mpsc;
-use std::thread;
 use crate::day;
-
-pub type BoxResult<T> = Result<T, Box<dyn error::Error>>;
+use crate::error::{self, Error, Fault};
+use crate::parse;
 
 struct Intcode {
     p: Vec<i64>,
@@ -25,106 +21,107 @@ impl Intcode {
     }
 
     fn put(&mut self, a: usize, v: i64) {
-//        eprintln!("put @{} {}", a, v);
+        log::trace!("put @{} {}", a, v);
         if a >= self.p.len() { self.p.resize(a + 1, 0); }
         self.p[a] = v;
     }
 
-    fn addr(&mut self, ip: usize, i: usize) -> usize {
+    fn addr(&mut self, ip: usize, i: usize) -> error::Result<usize> {
         let a = self.get(ip + i);
         let v = match self.get(ip) / vec![100, 1000, 10000][i - 1] % 10 {
             0 => a as usize,
             2 => (a  + self.base) as usize,
-            _ => 0, // XXX
+            mode => return Err(Error::intcode(ip, Fault::BadMode(mode))),
         };
-        v
+        Ok(v)
     }
 
-    fn val(&mut self, ip: usize, i: usize) -> i64 {
+    fn val(&mut self, ip: usize, i: usize) -> error::Result<i64> {
         let a = self.get(ip + i);
         let v = match self.get(ip) / vec![100, 1000, 10000][i - 1] % 10 {
             1 => a,
             _ => {
-                let addr = self.addr(ip, i);
+                let addr = self.addr(ip, i)?;
                 self.get(addr)
             },
         };
-//        eprintln!("{} {} {} {} {} {}", ip, self.get(ip), i, a, self.base, v);
-        v
+        log::trace!("{} {} {} {} {} {}", ip, self.get(ip), i, a, self.base, v);
+        Ok(v)
     }
 
     fn run(&mut self, sender: mpsc::Sender<i64>, receiver: mpsc::Receiver<i64>,
-        request: mpsc::Sender<()>, ack: mpsc::Receiver<()>) -> BoxResult<i64> {
+        request: mpsc::Sender<()>, ack: mpsc::Receiver<()>) -> error::Result<i64> {
         let mut ip = 0;
         let mut o = None;
+        let cancel = day::Cancel::current();
         while { let op = self.get(ip); self.op(op) != 99 } {
-//            eprintln!("{}: {} {} {} {}", ip, self.p[ip], self.p[ip + 1], self.p[ip + 2], self.p[ip + 3]);
+            cancel.check()?;
+            log::trace!("{}: {} {} {} {}", ip, self.p[ip], self.p[ip + 1], self.p[ip + 2], self.p[ip + 3]);
             match self.op(self.p[ip]) {
                 1 => {
-                    let a = self.val(ip, 1);
-                    let b = self.val( ip, 2);
-                    let c = self.addr(ip, 3);
+                    let a = self.val(ip, 1)?;
+                    let b = self.val(ip, 2)?;
+                    let c = self.addr(ip, 3)?;
                     self.put(c, a + b);
                     ip += 4;
                 },
                 2 => {
-                    let a = self.val(ip, 1);
-                    let b = self.val(ip, 2);
-                    let c = self.addr(ip, 3);
+                    let a = self.val(ip, 1)?;
+                    let b = self.val(ip, 2)?;
+                    let c = self.addr(ip, 3)?;
                     self.put(c, a * b);
                     ip += 4;
                 },
                 3 => {
-                    let a = self.addr(ip, 1);
-//                    eprintln!(">recv {}", a);
+                    let a = self.addr(ip, 1)?;
+                    log::trace!(">recv {}", a);
                     request.send(())?;
-                    self.put(a as usize, receiver.recv()?);
-//                    eprintln!("<recv {}", self.get(a as usize));
+                    self.put(a as usize, day::recv(&receiver)?);
+                    log::trace!("<recv {}", self.get(a));
                     ip += 2;
                 },
                 4 => {
-                    let a = self.val(ip, 1);
+                    let a = self.val(ip, 1)?;
                     o = Some(a);
-//                    eprintln!(">send {}", a);
+                    log::trace!(">send {}", a);
                     sender.send(a)?;
-//                    eprintln!("<send");
-                    ack.recv()?;
+                    log::trace!("<send");
+                    day::recv(&ack)?;
                     ip += 2;
                 },
                 5 => {
-                    let a = self.val(ip, 1);
-                    let b = self.val(ip, 2) as usize;
+                    let a = self.val(ip, 1)?;
+                    let b = self.val(ip, 2)? as usize;
                     ip = if a != 0 { b } else { ip + 3 };
                 },
                 6 => {
-                    let a = self.val(ip, 1);
-                    let b = self.val(ip, 2) as usize;
+                    let a = self.val(ip, 1)?;
+                    let b = self.val(ip, 2)? as usize;
                     ip = if a == 0 { b } else { ip + 3 };
                 },
                 7 => {
-                    let a = self.val(ip, 1);
-                    let b = self.val(ip, 2);
-                    let c = self.addr(ip, 3);
+                    let a = self.val(ip, 1)?;
+                    let b = self.val(ip, 2)?;
+                    let c = self.addr(ip, 3)?;
                     self.put(c, if a < b { 1 } else { 0 });
                     ip += 4;
                 },
                 8 => {
-                    let a = self.val(ip, 1);
-                    let b = self.val(ip, 2);
-                    let c = self.addr(ip,3);
+                    let a = self.val(ip, 1)?;
+                    let b = self.val(ip, 2)?;
+                    let c = self.addr(ip, 3)?;
                     self.put(c, if a == b { 1 } else { 0 });
                     ip += 4;
                 },
                 9 => {
-                    self.base += self.val(ip, 1);
-//                    eprintln!("<base {}", self.base);
+                    self.base += self.val(ip, 1)?;
+                    log::trace!("<base {}", self.base);
                     ip += 2;
                 }
-                _ => bail!("unknown opcode {}: {}", ip, self.op(self.p[ip])),
+                op => return Err(Error::intcode(ip, Fault::UnknownOpcode(op))),
             };
         }
-        if o.is_none() { bail!("no output"); }
-        Ok(o.unwrap())
+        o.ok_or_else(|| Error::intcode(ip, Fault::NoOutput))
     }
 
     #[allow(dead_code)]
@@ -194,38 +191,50 @@ impl Intcode {
 
 pub struct Day15 {}
 
+inventory::submit! { day::Registration(&Day15 {}) }
+
 impl day::Day for Day15 {
     fn tag(&self) -> &str { "15" }
 
-    fn part1(&self, input: &dyn Fn() -> Box<dyn io::Read>) {
-        let reader = io::BufReader::new(input());
-        let p = reader.split(b',')
-            .map(|v| String::from_utf8(v.unwrap()).unwrap())
-            .map(|s| s.trim_end().parse::<i64>().unwrap())
-            .collect::<Vec<_>>();
-        println!("{:?}", self.part1_impl(p));
+    fn title(&self) -> &str { "Oxygen System" }
+
+    fn parts(&self) -> [&str; 2] {
+        ["fewest movements to the oxygen system",
+         "minutes until the area is filled with oxygen"]
     }
+}
+
+impl day::Puzzle for Day15 {
+    type Input = Vec<i64>;
 
-    fn part2(&self, input: &dyn Fn() -> Box<dyn io::Read>) {
-        let reader = io::BufReader::new(input());
-        let p = reader.split(b',')
-            .map(|v| String::from_utf8(v.unwrap()).unwrap())
-            .map(|s| s.trim_end().parse::<i64>().unwrap())
-            .collect::<Vec<_>>();
-        println!("{:?}", self.part2_impl(p));
+    fn parse_input(&self, input: &mut dyn io::Read) -> error::Result<Vec<i64>> {
+        self.parse(input)
+    }
+
+    fn part1(&self, p: &Vec<i64>) -> day::Answer {
+        self.part1_impl(p).into()
+    }
+
+    fn part2(&self, p: &Vec<i64>) -> day::Answer {
+        self.part2_impl(p).into()
     }
 }
 
 impl Day15 {
-    fn part1_impl(self: &Self, p: Vec<i64>) -> BoxResult<usize> {
+    /// The Intcode program.
+    pub fn parse(self: &Self, input: &mut dyn io::Read) -> error::Result<Vec<i64>> {
+        parse::numbers(input, "an integer")
+    }
+
+    pub fn part1_impl(self: &Self, p: &[i64]) -> error::Result<usize> {
+        let p = p.to_vec();
         let (input_sender, input_receiver) = mpsc::channel::<i64>();
         let (output_sender, output_receiver) = mpsc::channel::<i64>();
         let (request_sender, request_receiver) = mpsc::channel::<()>();
         let (ack_sender, ack_receiver) = mpsc::channel::<()>();
-        let _cpu = thread::spawn(move || {
+        let cpu = day::spawn(move || {
             let mut ic = Intcode::new(&p);
             ic.run(output_sender, input_receiver, request_sender, ack_receiver)
-                .unwrap_or(0);
         });
         let (grid_r, mut grid_w) = evmap::new();
         let dirs: Vec<(i64, i64)> = vec![(0, -1), (0, 1), (-1, 0), (1, 0)];
@@ -240,69 +249,73 @@ impl Day15 {
         grid_w.refresh();
         let mut track = vec![];
 
-        let mut peek = |(px, py), (dx, dy), track: &mut Vec<_>| {
-            request_receiver.recv().unwrap();
-            input_sender.send(to_move((dx, dy))).unwrap();
-            let v = output_receiver.recv().unwrap();
-            ack_sender.send(()).unwrap();
+        let mut peek = |(px, py), (dx, dy), track: &mut Vec<_>| -> error::Result<i64> {
+            day::recv(&request_receiver)?;
+            input_sender.send(to_move((dx, dy)))?;
+            let v = day::recv(&output_receiver)?;
+            ack_sender.send(())?;
             if v != 0 {
-                request_receiver.recv().unwrap();
-                input_sender.send(to_move((-dx, -dy))).unwrap();
-                output_receiver.recv().unwrap();
-                ack_sender.send(()).unwrap();
+                day::recv(&request_receiver)?;
+                input_sender.send(to_move((-dx, -dy)))?;
+                day::recv(&output_receiver)?;
+                ack_sender.send(())?;
             };
             track.push((dx, dy).clone());
             grid_w.update((px + dx, py + dy), (v, track.clone()));
             grid_w.refresh();
             track.pop();
             grid_w.refresh();
-//            eprintln!("peek ({}, {}): {}", px + dx, py + dy, v);
-            v
+            log::trace!("peek ({}, {}): {}", px + dx, py + dy, v);
+            Ok(v)
         };
 
-        let step = |(dx, dy): (i64, i64), forward, track: &mut Vec<_>| {
+        let step = |(dx, dy): (i64, i64), forward, track: &mut Vec<_>| -> error::Result<()> {
             let (dx, dy) = if forward { (dx, dy) } else { (-dx, -dy) };
-            request_receiver.recv().unwrap();
-            input_sender.send(to_move((dx, dy))).unwrap();
-            output_receiver.recv().unwrap();
-            ack_sender.send(()).unwrap();
+            day::recv(&request_receiver)?;
+            input_sender.send(to_move((dx, dy)))?;
+            day::recv(&output_receiver)?;
+            ack_sender.send(())?;
             if forward { track.push((dx, dy).clone()); } else { track.pop(); };
+            Ok(())
         };
 
         let mut i = 0;
         let mut found = false;
         while !found {
             i += 1;
-//            eprintln!("{}: {:?}", i, pos);
+            log::trace!("{}: {:?}", i, pos);
 
-            let r = pos.iter().fold((vec![], false), |(mut pos, found), &(px, py)| {
+            let r = pos.iter().try_fold((vec![], false), |(mut pos, found), &(px, py)| {
                 let (_, t) = grid_r.get_and(&(px, py), |x| x[0].clone()).unwrap();
 //                eprintln!("({}, {}): {} {:?}", px, py, v, t);
-                for m in &t { step(*m, true, &mut track); }
+                for m in &t { step(*m, true, &mut track)?; }
                 let moves = dirs.iter().filter(|&(dx, dy)|
                     grid_r.get_and(&(px + *dx, py + *dy), |_| ()) == None);
-                let moves: Vec<_> = moves
-                    .map(|&(dx, dy)| ((px + dx, py + dy), peek((px, py), (dx, dy), &mut track)))
-                    .filter(|&(_, x)| x != 0).collect();
+                let moves = moves
+                    .map(|&(dx, dy)|
+                        Ok(((px + dx, py + dy), peek((px, py), (dx, dy), &mut track)?)))
+                    .collect::<error::Result<Vec<_>>>()?;
+                let moves: Vec<_> = moves.into_iter().filter(|&(_, x)| x != 0).collect();
                 for (p, _) in &moves { pos.push(*p); }
-                for m in t.iter().rev() { step(*m, false, &mut track) };
-                (pos, found || moves.iter().any(|(_, x)| *x == 2))
+                for m in t.iter().rev() { step(*m, false, &mut track)? };
+                Ok((pos, found || moves.iter().any(|(_, x)| *x == 2)))
             });
+            let r = match r { Ok(r) => r, Err(e) => return day::or_join(Err(e), cpu) };
             pos = r.0;
             found = r.1;
         }
         Ok(i)
     }
 
-    fn part2_impl(self: &Self, p: Vec<i64>) -> BoxResult<i64> {
+    pub fn part2_impl(self: &Self, p: &[i64]) -> error::Result<i64> {
+        let p = p.to_vec();
         let (input_sender, input_receiver) = mpsc::channel::<i64>();
         let (output_sender, output_receiver) = mpsc::channel::<i64>();
         let (request_sender, request_receiver) = mpsc::channel::<()>();
         let (ack_sender, ack_receiver) = mpsc::channel::<()>();
-        let _cpu = thread::spawn(move || {
+        let cpu = day::spawn(move || {
             let mut ic = Intcode::new(&p);
             ic.run(output_sender, input_receiver, request_sender, ack_receiver)
-                .unwrap_or(0);
         });
         let (grid_r, mut grid_w) = evmap::new();
         let dirs: Vec<(i64, i64)> = vec![(0, -1), (0, 1), (-1, 0), (1, 0)];
@@ -317,50 +330,54 @@ impl Day15 {
         grid_w.refresh();
         let mut track = vec![];
 
-        let mut peek = |(px, py), (dx, dy), track: &mut Vec<_>| {
-            request_receiver.recv().unwrap();
-            input_sender.send(to_move((dx, dy))).unwrap();
-            let v = output_receiver.recv().unwrap();
-            ack_sender.send(()).unwrap();
+        let mut peek = |(px, py), (dx, dy), track: &mut Vec<_>| -> error::Result<i64> {
+            day::recv(&request_receiver)?;
+            input_sender.send(to_move((dx, dy)))?;
+            let v = day::recv(&output_receiver)?;
+            ack_sender.send(())?;
             if v != 0 {
-                request_receiver.recv().unwrap();
-                input_sender.send(to_move((-dx, -dy))).unwrap();
-                output_receiver.recv().unwrap();
-                ack_sender.send(()).unwrap();
+                day::recv(&request_receiver)?;
+                input_sender.send(to_move((-dx, -dy)))?;
+                day::recv(&output_receiver)?;
+                ack_sender.send(())?;
             };
             track.push((dx, dy).clone());
             grid_w.update((px + dx, py + dy), (v, track.clone()));
             grid_w.refresh();
             track.pop();
             grid_w.refresh();
-//            eprintln!("peek ({}, {}): {}", px + dx, py + dy, v);
-            v
+            log::trace!("peek ({}, {}): {}", px + dx, py + dy, v);
+            Ok(v)
         };
 
-        let step = |(dx, dy): (i64, i64), forward, track: &mut Vec<_>| {
+        let step = |(dx, dy): (i64, i64), forward, track: &mut Vec<_>| -> error::Result<()> {
             let (dx, dy) = if forward { (dx, dy) } else { (-dx, -dy) };
-            request_receiver.recv().unwrap();
-            input_sender.send(to_move((dx, dy))).unwrap();
-            output_receiver.recv().unwrap();
-            ack_sender.send(()).unwrap();
+            day::recv(&request_receiver)?;
+            input_sender.send(to_move((dx, dy)))?;
+            day::recv(&output_receiver)?;
+            ack_sender.send(())?;
             if forward { track.push((dx, dy).clone()); } else { track.pop(); };
+            Ok(())
         };
 
         let mut oxygen = None;
         while !pos.is_empty() {
-            let (p, o) = pos.iter().fold((vec![], None), |(mut pos, _oxygen), &(px, py)| {
+            let r = pos.iter().try_fold((vec![], None), |(mut pos, _oxygen), &(px, py)| {
                 let (_, t) = grid_r.get_and(&(px, py), |x| x[0].clone()).unwrap();
-                for m in &t { step(*m, true, &mut track); }
+                for m in &t { step(*m, true, &mut track)?; }
                 let moves = dirs.iter().filter(|&(dx, dy)|
                     grid_r.get_and(&(px + *dx, py + *dy), |_| ()) == None);
-                let moves: Vec<_> = moves
-                    .map(|&(dx, dy)| ((px + dx, py + dy), peek((px, py), (dx, dy), &mut track)))
-                    .filter(|&(_, x)| x != 0).collect();
+                let moves = moves
+                    .map(|&(dx, dy)|
+                        Ok(((px + dx, py + dy), peek((px, py), (dx, dy), &mut track)?)))
+                    .collect::<error::Result<Vec<_>>>()?;
+                let moves: Vec<_> = moves.into_iter().filter(|&(_, x)| x != 0).collect();
                 for (p, _) in &moves { pos.push(*p); }
-                for m in t.iter().rev() { step(*m, false, &mut track) };
-                (pos,
-                 moves.iter().filter(|(_, x)| *x == 2).next().map(|(p, _)| *p))
+                for m in t.iter().rev() { step(*m, false, &mut track)? };
+                Ok((pos,
+                    moves.iter().filter(|(_, x)| *x == 2).next().map(|(p, _)| *p)))
             });
+            let (p, o) = match r { Ok(r) => r, Err(e) => return day::or_join(Err(e), cpu) };
             pos = p;
             if o != None { oxygen = o; }
         }
@@ -368,7 +385,7 @@ impl Day15 {
         let mut t = 0;
         pos = vec![oxygen.unwrap()];
         while !pos.is_empty() {
-//            eprintln!("{}: {:?}", t, pos);
+            log::trace!("{}: {:?}", t, pos);
             pos = pos.iter().fold(vec![], |mut pos, &(px, py)| {
                 for (dx, dy) in dirs.iter().filter(|&(dx, dy)|
                     grid_r.get_and(&(px + *dx, py + *dy), |x| x[0].clone()).unwrap().0 == 1) {
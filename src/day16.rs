@@ -1,34 +1,65 @@
 use num::abs;
-use std::error;
 use std::io;
-use std::io::BufRead;
-use crate::day;
-
-pub type BoxResult<T> = Result<T, Box<dyn error::Error>>;
+use crate::day::{self, Day};
+use crate::error::{self, Error};
+use crate::parse;
 
 pub struct Day16 {}
 
+inventory::submit! { day::Registration(&Day16 {}) }
+
 fn round_up(x: usize, p: usize) -> usize { (x + p - 1) / p * p }
 
 impl day::Day for Day16 {
     fn tag(&self) -> &str { "16" }
 
-    fn part1(&self, input: &dyn Fn() -> Box<dyn io::Read>) {
-        let mut reader = io::BufReader::new(input());
-        let mut line= String::new();
-        reader.read_line(&mut line);
-        println!("{:?}", self.part1_impl(line.as_str(), 100));
+    fn title(&self) -> &str { "Flawed Frequency Transmission" }
+
+    fn parts(&self) -> [&str; 2] {
+        ["first eight digits after the phases",
+         "eight digit message embedded in the real signal"]
+    }
+
+    fn params(&self) -> &[day::Param] {
+        &[day::Param { name: "phases", default: 100, min: 0, max: i64::MAX,
+                      help: "FFT phases to run" }]
+    }
+
+    fn version(&self) -> u32 { 2 }
+}
+
+impl day::Puzzle for Day16 {
+    type Input = String;
+
+    fn parse_input(&self, input: &mut dyn io::Read) -> error::Result<String> {
+        self.parse(input)
+    }
+
+    fn part1(&self, signal: &String) -> day::Answer {
+        let phases = self.param("phases") as usize;
+        self.part1_impl(signal, phases).into()
     }
 
-    fn part2(&self, input: &dyn Fn() -> Box<dyn io::Read>) {
-        let mut reader = io::BufReader::new(input());
-        let mut line= String::new();
-        reader.read_line(&mut line);
-        println!("{:?}", self.part2_impl(line.as_str(), 100));
+    fn part2(&self, signal: &String) -> day::Answer {
+        let phases = self.param("phases") as usize;
+        self.part2_impl(signal, phases).into()
     }
 }
 
 impl Day16 {
+    /// The signal's digits.
+    pub fn parse(self: &Self, input: &mut dyn io::Read) -> error::Result<String> {
+        let expected = "a signal of digits";
+        match parse::lines(input)?.into_iter().next() {
+            Some(l) if l.text.is_empty() => Err(l.expected(expected).into()),
+            Some(l) => { l.only("0123456789", "a digit")?; Ok(l.text) },
+            None => Err(parse::ParseError {
+                file: day::input_name(), line: 1, column: 1, text: String::new(),
+                expected: expected.to_string(),
+            }.into()),
+        }
+    }
+
     fn phase(&self, n: usize) -> Vec<i32> {
         let mut v: Vec<i32> = vec![0, 1, 0, -1].iter().flat_map(|&d| vec![d; n + 1])
             .collect();
@@ -37,8 +68,9 @@ impl Day16 {
         v
     }
 
-    fn part1_impl(self: &Self, s: &str, n: usize) -> BoxResult<String> {
-        let mut s = (0..n).fold(s.to_string(), |s, _| {
+    /// The whole signal after `n` phases, computed digit by digit.
+    pub fn fft(self: &Self, s: &str, n: usize) -> String {
+        (0..n).fold(s.to_string(), |s, _| {
             let it = (0..s.len()).map(|i| {
                 let phase = self.phase(i);
                 let a: i32 = s.split("").into_iter().filter(|&s| !s.is_empty())
@@ -48,22 +80,39 @@ impl Day16 {
                 (abs(a) % 10).to_string()
             });
             it.collect::<Vec<_>>().join("")
-        });
+        })
+    }
+
+    pub fn part1_impl(self: &Self, s: &str, n: usize) -> error::Result<String> {
+        let mut s = self.fft(s, n);
         s.truncate(8);
         Ok(s)
     }
 
-    fn part2_impl(self: &Self, s: &str, n: usize) -> BoxResult<String> {
-        let repeat = 10000;
+    pub fn part2_impl(self: &Self, s: &str, n: usize) -> error::Result<String> {
+        let offset = s.get(..7).and_then(|o| o.parse::<usize>().ok())
+            .ok_or_else(|| Error::input("a signal shorter than its 7 digit offset"))?;
+        self.tail(s, 10000, offset, n)
+    }
+
+    /// Eight digits at the offset into the signal repeated, after `n` phases.
+    /// Only works for an offset in the second half, where every digit is the
+    /// sum of those after it, and with eight digits after it.
+    pub fn tail(self: &Self, s: &str, repeat: usize, offset: usize, n: usize)
+        -> error::Result<String> {
         let phases = n;
         let period = s.len();
         let len = period * repeat;
-        let offset = s[..7].parse::<usize>().unwrap();
         let input = s.chars().map(|c| (c as u8 - b'0') as usize)
             .collect::<Vec<_>>();
 
         let start = offset;
-        assert!(start + start >= len);
+        if start + start < len {
+            return Err(Error::no_solution(&format!("offset {} in the first half", offset)));
+        }
+        if start + 8 > len {
+            return Err(Error::no_solution(&format!("offset {} past the end", offset)));
+        }
         let mut output = (start..len).rev().scan(0, |s, offset| {
             let sum = *s + input[offset % period];
             let digit = sum % 10;
@@ -75,7 +124,6 @@ impl Day16 {
         let output = (2..=phases).fold(output, |input, _| {
             let period = input.len();
             let start = offset;
-            assert!(start + start >= len);
             let mut output = (start..len).rev().scan(0, |s, offset| {
                 let sum = *s + input[(offset - start) % period];
                 let digit = sum % 10;
@@ -94,6 +142,7 @@ impl Day16 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::generate;
 
     fn test1(s: &str, n: usize, v: &str) {
         assert_eq!(Day16 {}.part1_impl(s, n).unwrap().as_str(), v);
@@ -117,5 +166,22 @@ mod tests {
         test2("03036732577212944063491565474664", 100, "84462026");
         test2("02935109699940807407585447034323", 100, "78725270");
         test2("03081770884921959731165446850517", 100, "53553731");
+        assert!(Day16 {}.part2_impl("12345678", 1).is_err());
+        assert!(Day16 {}.part2_impl("123456", 1).is_err());
+        assert!(Day16 {}.tail("12345678", 2, 1, 1).is_err());
+        assert!(Day16 {}.parse(&mut "".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn properties() {
+        let day = Day16 {};
+        generate::check(20, |rng| {
+            let s = (0..rng.range(8, 30)).map(|_| rng.range(0, 9).to_string()).collect::<String>();
+            let (repeat, phases) = (rng.range(2, 4) as usize, rng.range(1, 5) as usize);
+            let len = s.len() * repeat;
+            let offset = rng.range(len.div_ceil(2) as i64, (len - 8) as i64) as usize;
+            assert_eq!(day.tail(&s, repeat, offset, phases).unwrap(),
+                       &day.fft(&s.repeat(repeat), phases)[offset..offset + 8], "{}", s);
+        });
     }
 }
\ No newline at end of file
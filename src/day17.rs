@@ -1,14 +1,9 @@
-use simple_error::bail;
-use evmap;
-use std::error;
+use std::collections::HashMap;
 use std::io;
-use std::io::BufRead;
 use std::sync::mpsc;
-use std::thread;
 use crate::day;
-use evmap::{ReadHandle, WriteHandle};
-
-pub type BoxResult<T> = Result<T, Box<dyn error::Error>>;
+use crate::error::{self, Error, Fault};
+use crate::parse;
 
 struct Intcode {
     p: Vec<i64>,
@@ -26,107 +21,108 @@ impl Intcode {
     }
 
     fn put(&mut self, a: usize, v: i64) {
-//        eprintln!("put @{} {}", a, v);
+        log::trace!("put @{} {}", a, v);
         if a >= self.p.len() { self.p.resize(a + 1, 0); }
         self.p[a] = v;
     }
 
-    fn addr(&mut self, ip: usize, i: usize) -> usize {
+    fn addr(&mut self, ip: usize, i: usize) -> error::Result<usize> {
         let a = self.get(ip + i);
         let v = match self.get(ip) / vec![100, 1000, 10000][i - 1] % 10 {
             0 => a as usize,
             2 => (a  + self.base) as usize,
-            _ => 0, // XXX
+            mode => return Err(Error::intcode(ip, Fault::BadMode(mode))),
         };
-        v
+        Ok(v)
     }
 
-    fn val(&mut self, ip: usize, i: usize) -> i64 {
+    fn val(&mut self, ip: usize, i: usize) -> error::Result<i64> {
         let a = self.get(ip + i);
         let v = match self.get(ip) / vec![100, 1000, 10000][i - 1] % 10 {
             1 => a,
             _ => {
-                let addr = self.addr(ip, i);
+                let addr = self.addr(ip, i)?;
                 self.get(addr)
             },
         };
-//        eprintln!("{} {} {} {} {} {}", ip, self.get(ip), i, a, self.base, v);
-        v
+        log::trace!("{} {} {} {} {} {}", ip, self.get(ip), i, a, self.base, v);
+        Ok(v)
     }
 
     fn run(&mut self, sender: mpsc::Sender<i64>, receiver: mpsc::Receiver<i64>,
-        request: mpsc::Sender<()>, ack: mpsc::Receiver<()>) -> BoxResult<i64> {
+        request: mpsc::Sender<()>, ack: mpsc::Receiver<()>) -> error::Result<i64> {
         let mut ip = 0;
         let mut o = None;
+        let cancel = day::Cancel::current();
         while { let op = self.get(ip); self.op(op) != 99 } {
-//            eprintln!("{}: {} {} {} {}", ip, self.p[ip], self.p[ip + 1], self.p[ip + 2], self.p[ip + 3]);
+            cancel.check()?;
+            log::trace!("{}: {} {} {} {}", ip, self.p[ip], self.p[ip + 1], self.p[ip + 2], self.p[ip + 3]);
             match self.op(self.p[ip]) {
                 1 => {
-                    let a = self.val(ip, 1);
-                    let b = self.val( ip, 2);
-                    let c = self.addr(ip, 3);
+                    let a = self.val(ip, 1)?;
+                    let b = self.val(ip, 2)?;
+                    let c = self.addr(ip, 3)?;
                     self.put(c, a + b);
                     ip += 4;
                 },
                 2 => {
-                    let a = self.val(ip, 1);
-                    let b = self.val(ip, 2);
-                    let c = self.addr(ip, 3);
+                    let a = self.val(ip, 1)?;
+                    let b = self.val(ip, 2)?;
+                    let c = self.addr(ip, 3)?;
                     self.put(c, a * b);
                     ip += 4;
                 },
                 3 => {
-                    let a = self.addr(ip, 1);
-//                    eprintln!(">recv {}", a);
+                    let a = self.addr(ip, 1)?;
+                    log::trace!(">recv {}", a);
                     request.send(())?;
-                    self.put(a as usize, receiver.recv()?);
-//                    eprintln!("<recv {}", self.get(a as usize));
+                    self.put(a as usize, day::recv(&receiver)?);
+                    log::trace!("<recv {}", self.get(a));
                     ip += 2;
                 },
                 4 => {
-                    let a = self.val(ip, 1);
+                    let a = self.val(ip, 1)?;
                     o = Some(a);
-//                    eprintln!(">send {}", a);
+                    log::trace!(">send {}", a);
                     sender.send(a)?;
-//                    eprintln!("<send");
-                    ack.recv()?;
-//                    eprintln!("<<send");
+                    log::trace!("<send");
+                    day::recv(&ack)?;
+                    log::trace!("<<send");
                     ip += 2;
                 },
                 5 => {
-                    let a = self.val(ip, 1);
-                    let b = self.val(ip, 2) as usize;
+                    let a = self.val(ip, 1)?;
+                    let b = self.val(ip, 2)? as usize;
                     ip = if a != 0 { b } else { ip + 3 };
                 },
                 6 => {
-                    let a = self.val(ip, 1);
-                    let b = self.val(ip, 2) as usize;
+                    let a = self.val(ip, 1)?;
+                    let b = self.val(ip, 2)? as usize;
                     ip = if a == 0 { b } else { ip + 3 };
                 },
                 7 => {
-                    let a = self.val(ip, 1);
-                    let b = self.val(ip, 2);
-                    let c = self.addr(ip, 3);
+                    let a = self.val(ip, 1)?;
+                    let b = self.val(ip, 2)?;
+                    let c = self.addr(ip, 3)?;
                     self.put(c, if a < b { 1 } else { 0 });
                     ip += 4;
                 },
                 8 => {
-                    let a = self.val(ip, 1);
-                    let b = self.val(ip, 2);
-                    let c = self.addr(ip,3);
+                    let a = self.val(ip, 1)?;
+                    let b = self.val(ip, 2)?;
+                    let c = self.addr(ip, 3)?;
                     self.put(c, if a == b { 1 } else { 0 });
                     ip += 4;
                 },
                 9 => {
-                    self.base += self.val(ip, 1);
-//                    eprintln!("<base {}", self.base);
+                    self.base += self.val(ip, 1)?;
+                    log::trace!("<base {}", self.base);
                     ip += 2;
                 }
-                _ => bail!("unknown opcode {}: {}", ip, self.op(self.p[ip])),
+                op => return Err(Error::intcode(ip, Fault::UnknownOpcode(op))),
             };
         }
-        if o.is_none() { bail!("no output"); }
-        Ok(o.unwrap())
+        o.ok_or_else(|| Error::intcode(ip, Fault::NoOutput))
     }
 
     #[allow(dead_code)]
@@ -196,118 +192,141 @@ impl Intcode {
 
 pub struct Day17 {}
 
+inventory::submit! { day::Registration(&Day17 {}) }
+
 impl day::Day for Day17 {
     fn tag(&self) -> &str { "17" }
 
-    fn part1(&self, input: &dyn Fn() -> Box<dyn io::Read>) {
-        let reader = io::BufReader::new(input());
-        let p = reader.split(b',')
-            .map(|v| String::from_utf8(v.unwrap()).unwrap())
-            .map(|s| s.trim_end().parse::<i64>().unwrap())
-            .collect::<Vec<_>>();
-        let (grid_r, mut grid_w) = evmap::new();
-        let mut o = (0, 0);
-        let mut dir = (0, 0);
-        println!("{:?}", self.part1_impl(p, &grid_r, &mut grid_w, &mut o, &mut dir));
+    fn title(&self) -> &str { "Set and Forget" }
+
+    fn parts(&self) -> [&str; 2] {
+        ["sum of the alignment parameters",
+         "dust collected by the vacuum robot"]
     }
+}
 
-    fn part2(&self, input: &dyn Fn() -> Box<dyn io::Read>) {
-        let reader = io::BufReader::new(input());
-        let p = reader.split(b',')
-            .map(|v| String::from_utf8(v.unwrap()).unwrap())
-            .map(|s| s.trim_end().parse::<i64>().unwrap())
-            .collect::<Vec<_>>();
-        let mut p2 = p.clone();
-        p2[0] = 2;
-        let mut o = (0, 0);
-        let mut dir = (0, 0);
-        let (grid_r, mut grid_w) = evmap::new();
-        self.part1_impl(p, &grid_r, &mut grid_w, &mut o, &mut dir).unwrap();
-        println!("{:?}", self.part2_impl(p2, &grid_r, &mut grid_w, o, dir));
+/// What the camera shows: the scaffold, and where the robot is and faces.
+pub struct View {
+    p: Vec<i64>,
+    grid: HashMap<(i32, i32), char>,
+    origin: (i32, i32),
+    dir: (i32, i32),
+}
+
+impl day::Puzzle for Day17 {
+    type Input = View;
+
+    fn parse_input(&self, input: &mut dyn io::Read) -> error::Result<View> {
+        let p = self.parse(input)?;
+        let picture = self.camera(&p)?;
+        Ok(self.view(p, &picture))
+    }
+
+    fn part1(&self, view: &View) -> day::Answer {
+        self.part1_impl(view).into()
+    }
+
+    fn part2(&self, view: &View) -> day::Answer {
+        self.part2_impl(view).into()
     }
 }
 
 impl Day17 {
-    fn part1_impl(self: &Self, p: Vec<i64>,
-                  grid_r: &ReadHandle<(i32, i32), char>,
-                  grid_w: &mut WriteHandle<(i32, i32), char>,
-                  origin: &mut (i32, i32), dir: &mut (i32, i32))
-        -> BoxResult<i32> {
+    /// The Intcode program.
+    pub fn parse(self: &Self, input: &mut dyn io::Read) -> error::Result<Vec<i64>> {
+        parse::numbers(input, "an integer")
+    }
+
+    pub fn part1_impl(self: &Self, view: &View) -> i32 {
+        let is_scaffold = |x, y| view.grid.get(&(x, y)) == Some(&'#');
+        view.grid.keys()
+            .filter(|&&(x, y)| x > 0 && y > 0 && is_scaffold(x, y) && is_scaffold(x, y - 1)
+                    && is_scaffold(x - 1, y - 1) && is_scaffold(x + 1, y - 1))
+            .map(|(x, y)| x * (y - 1))
+            .sum()
+    }
+
+    pub fn part2_impl(self: &Self, view: &View) -> error::Result<i64> {
+        let mut p2 = view.p.clone();
+        p2[0] = 2;
+        self.walk(&p2, view)
+    }
+
+    /// The picture the camera program takes.
+    fn camera(self: &Self, p: &[i64]) -> error::Result<String> {
+        let p = p.to_vec();
         let (_input_sender, input_receiver) = mpsc::channel::<i64>();
         let (output_sender, output_receiver) = mpsc::channel::<i64>();
         let (request_sender, _request_receiver) = mpsc::channel::<()>();
         let (ack_sender, ack_receiver) = mpsc::channel::<()>();
-        let _cpu = thread::spawn(move || {
+        let cpu = day::spawn(move || {
             let mut ic = Intcode::new(&p);
             ic.run(output_sender, input_receiver, request_sender, ack_receiver)
-                .unwrap_or(0);
         });
 
         let mut s = String::new();
-        let (mut x, mut y) = (0, 0);
-        let mut t = 0;
-        while match output_receiver.recv() {
-            Ok(b) => {
-                ack_sender.send(()).unwrap();
-                let c = b as u8 as char;
-                s.push(c);
-                grid_w.update((x, y).clone(), c);
-                grid_w.refresh();
+        while let Ok(b) = output_receiver.recv() {
+            ack_sender.send(())?;
+            s.push(b as u8 as char);
+        }
+        log::debug!("{}", s);
+        day::join(cpu)?;
+
+        Ok(s)
+    }
+
+    pub fn view(self: &Self, p: Vec<i64>, picture: &str) -> View {
+        let mut grid = HashMap::new();
+        let mut origin = (0, 0);
+        let mut dir = (0, 0);
+        for (y, line) in picture.lines().enumerate() {
+            for (x, c) in line.chars().enumerate() {
+                let (x, y) = (x as i32, y as i32);
+                grid.insert((x, y), c);
                 match c {
-                    '#' => {
-                        if y > 0 && x > 0
-                            && grid_r.get_and(&(x, y - 1), |c| c[0]) == Some('#')
-                            && grid_r.get_and(&(x - 1, y - 1), |c| c[0]) == Some('#')
-                            && grid_r.get_and(&(x + 1, y - 1), |c| c[0]) == Some('#') {
-                            t += x * (y - 1);
-                        };
-                        x += 1;
-                    },
-                    '.' => { x += 1; },
-                    '^' => { *origin = (x, y); *dir = (0, -1); x += 1; }
-                    'v' => { *origin = (x, y); *dir = (0, 1); x += 1; }
-                    '<' => { *origin = (x, y); *dir = (-1, 0); x += 1; }
-                    '>' => { *origin = (x, y); *dir = (1, 0); x += 1; }
-                    '\n' => { x = 0; y += 1; },
+                    '^' => { origin = (x, y); dir = (0, -1); }
+                    'v' => { origin = (x, y); dir = (0, 1); }
+                    '<' => { origin = (x, y); dir = (-1, 0); }
+                    '>' => { origin = (x, y); dir = (1, 0); }
                     _ => (),
                 }
-                true
-            },
-            Err(_) => false,
-        } {};
-        eprintln!("{}", s);
-
-        Ok(t)
+            }
+        }
+        View { p, grid, origin, dir }
     }
 
-    fn part2_impl(self: &Self, p: Vec<i64>,
-                  grid_r: &ReadHandle<(i32, i32), char>,
-                  grid_w: &mut WriteHandle<(i32, i32), char>,
-                  origin: (i32, i32), dir: (i32, i32))
-        -> BoxResult<i64> {
+    fn walk(self: &Self, p: &[i64], view: &View) -> error::Result<i64> {
+        let p = p.to_vec();
         let (input_sender, input_receiver) = mpsc::channel::<i64>();
         let (output_sender, output_receiver) = mpsc::channel::<i64>();
         let (request_sender, request_receiver) = mpsc::channel::<()>();
         let (ack_sender, ack_receiver) = mpsc::channel::<()>();
-        let _cpu = thread::spawn(move || {
+        let cpu = day::spawn(move || {
             let mut ic = Intcode::new(&p);
             ic.run(output_sender, input_receiver, request_sender, ack_receiver)
-                .unwrap_or(0);
         });
+        match self.steer(view, &input_sender, &output_receiver, &request_receiver, &ack_sender) {
+            Ok(n) => day::join(cpu).map(|_| n),
+            n => day::or_join(n, cpu),
+        }
+    }
+
+    /// Feeds the movement routines to the vacuum robot and collects the dust it reports.
+    fn steer(self: &Self, view: &View, input_sender: &mpsc::Sender<i64>,
+        output_receiver: &mpsc::Receiver<i64>, request_receiver: &mpsc::Receiver<()>,
+        ack_sender: &mpsc::Sender<()>) -> error::Result<i64> {
         let mut s = String::new();
         for _i in 0..2047 {
-            match output_receiver.recv().unwrap() as u8 as char {
-                '\n' => { eprintln!("{}", s); s = String::new(); },
+            match day::recv(output_receiver)? as u8 as char {
+                '\n' => { log::debug!("{}", s); s = String::new(); },
                 c => { s.push(c); }
             };
-            ack_sender.send(()).unwrap();
+            ack_sender.send(())?;
         }
 
-//        eprintln!("{:?} {:?}", origin, dir);
+        log::trace!("{:?} {:?}", view.origin, view.dir);
 
-        let is_ok = |(x, y), (dx, dy)| {
-            grid_r.get_and(&(x + dx, y + dy), |c| c[0]) == Some('#')
-        };
+        let is_ok = |(x, y), (dx, dy)| view.grid.get(&(x + dx, y + dy)) == Some(&'#');
 
         let run = |(x, y), (dx, dy)| {
             let len = (1..).position(|i| !is_ok((x, y), (dx * i, dy * i)))
@@ -317,9 +336,9 @@ impl Day17 {
         };
 
         let mut n = 0;
-        let mut pos = origin;
+        let mut pos = view.origin;
         let mut last = None;
-        let mut face = dir;
+        let mut face = view.dir;
         loop {
             let (_m, next, next_face) =
                 if let Some((next, dist, next_face)) = run(pos, face) {
@@ -333,7 +352,7 @@ impl Day17 {
                     (format!("L,L,{}", dist), next, next_face)
                 };
             if Some(next) == last { break; }
-//            eprintln!("{:?}", _m);
+            log::trace!("{:?}", _m);
             face = next_face;
             last = Some(pos);
             pos = next;
@@ -346,17 +365,17 @@ R,12,L,6,L,6,L,8
 y
 ";
         for c in instructions.chars() {
-            request_receiver.recv().unwrap();
-            input_sender.send(c as i64).unwrap();
+            day::recv(request_receiver)?;
+            input_sender.send(c as i64)?;
             match c {
                 '\n' => {
                     loop {
                         let mut done = false;
-                        match output_receiver.recv().unwrap() as u8 as char {
-                            '\n' => { eprintln!("{}", s); s = String::new(); done = true; },
+                        match day::recv(output_receiver)? as u8 as char {
+                            '\n' => { log::debug!("{}", s); s = String::new(); done = true; },
                             c => { s.push(c); }
                         };
-                        ack_sender.send(()).unwrap();
+                        ack_sender.send(())?;
                         if done { break; }
                     };
                 },
@@ -368,7 +387,7 @@ y
         let (mut x, mut y) = (0, 0);
         while match output_receiver.recv() {
             Ok(b) => {
-                ack_sender.send(()).unwrap();
+                ack_sender.send(())?;
                 let c = b as u8 as char;
                 s.push(c);
                 match c {
@@ -381,13 +400,13 @@ y
                     '<' => { x += 1; }
                     '>' => { x += 1; }
                     '\n' => {
-                        eprintln!("{}", s);
+                        log::debug!("{}", s);
                         s = String::new();
                         x = 0;
                         y += 1;
                     },
                     _ => {
-                        eprintln!("whoa: {}", b);
+                        log::warn!("unexpected output {}", b);
                         n += b;
                         x += 1;
                     },
@@ -403,10 +422,11 @@ y
 
 #[cfg(test)]
 mod tests {
-//    use super::*;
+    use super::*;
 
-    fn test1(s: &str, v: usize) {
-//        assert_eq!(Day17 {}.part1_impl(s), v);
+    fn test1(s: &str, v: i32) {
+        let view = Day17 {}.view(vec![], s.trim_start());
+        assert_eq!(Day17 {}.part1_impl(&view), v);
     }
 
     #[test]
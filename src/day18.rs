@@ -1,40 +1,142 @@
 use evmap;
 use itertools::Itertools;
 use itertools::FoldWhile::{Continue, Done};
-use std::error;
 use std::io;
-use std::io::BufRead;
 use crate::day;
-use std::collections::HashSet;
-
-pub type BoxResult<T> = Result<T, Box<dyn error::Error>>;
+use crate::error;
+use crate::generate::Rng;
+use crate::parse;
+use std::collections::{HashMap, HashSet, VecDeque};
 
 pub struct Day18 {}
 
+inventory::submit! { day::Registration(&Day18 {}) }
+
 impl day::Day for Day18 {
     fn tag(&self) -> &str { "18" }
 
-    fn part1(&self, input: &dyn Fn() -> Box<dyn io::Read>) {
-        println!("{:?}", self.part1_impl(&mut *input()));
+    fn title(&self) -> &str { "Many-Worlds Interpretation" }
+
+    fn parts(&self) -> [&str; 2] {
+        ["fewest steps to collect all keys",
+         "fewest steps to collect all keys with four robots"]
     }
 
-    fn part2(&self, input: &dyn Fn() -> Box<dyn io::Read>) {
-        println!("{:?}", self.part2_impl(&mut *input()));
+    fn generate(&self, rng: &mut Rng, size: Option<usize>) -> Option<String> {
+        Some(self.generate_impl(rng, size.unwrap_or(6)))
+    }
+}
+
+impl day::Puzzle for Day18 {
+    type Input = Vec<String>;
+
+    fn parse_input(&self, input: &mut dyn io::Read) -> error::Result<Vec<String>> {
+        self.parse(input)
+    }
+
+    fn part1(&self, map: &Vec<String>) -> day::Answer {
+        self.part1_impl(map).into()
+    }
+
+    fn part2(&self, map: &Vec<String>) -> day::Answer {
+        self.part2_impl(map).into()
     }
 }
 
 impl Day18 {
-    fn part1_impl(self: &Self, input: &mut dyn io::Read) -> BoxResult<usize> {
-        let reader = io::BufReader::new(input);
+    /// A maze with `size` keys (at most 26) around an open square with the
+    /// entrance in its middle, so that part 2 can split it.  Doors are only
+    /// put where the keys behind them can be collected in some order.
+    pub fn generate_impl(self: &Self, rng: &mut Rng, size: usize) -> String {
+        let n = size.min(26);
+        let cells = (n + 3).max(7) | 1;
+        let side = 2 * cells + 1;
+        let mut grid = vec![vec!['#'; side]; side];
+        let mut stack = vec![(cells / 2, cells / 2)];
+        let mut visited = HashSet::new();
+        visited.insert(stack[0]);
+        grid[2 * stack[0].1 + 1][2 * stack[0].0 + 1] = '.';
+        while let Some(&(x, y)) = stack.last() {
+            let next = [(x + 1, y), (x, y + 1), (x.wrapping_sub(1), y), (x, y.wrapping_sub(1))];
+            let mut next = next.iter().copied()
+                .filter(|&(x, y)| x < cells && y < cells && !visited.contains(&(x, y)))
+                .collect::<Vec<_>>();
+            if next.is_empty() {
+                stack.pop();
+                continue;
+            }
+            let (nx, ny) = next.swap_remove(rng.below(next.len()));
+            grid[y + ny + 1][x + nx + 1] = '.';
+            grid[2 * ny + 1][2 * nx + 1] = '.';
+            visited.insert((nx, ny));
+            stack.push((nx, ny));
+        }
+        let center = cells / 2 * 2 + 1;
+        let plaza = |x: usize, y: usize| x.abs_diff(center) <= 2 && y.abs_diff(center) <= 2;
+        for row in grid.iter_mut().skip(center - 2).take(5) {
+            row.iter_mut().skip(center - 2).take(5).for_each(|c| *c = '.');
+        }
+        grid[center][center] = '@';
+        let mut parent = HashMap::new();
+        let mut queue = VecDeque::from(vec![(center, center)]);
+        while let Some((x, y)) = queue.pop_front() {
+            for (nx, ny) in [(x + 1, y), (x, y + 1), (x - 1, y), (x, y - 1)] {
+                let open = grid[ny][nx] != '#' && (nx, ny) != (center, center);
+                if open && !parent.contains_key(&(nx, ny)) {
+                    parent.insert((nx, ny), (x, y));
+                    queue.push_back((nx, ny));
+                }
+            }
+        }
+        let mut spots = (0..cells * cells).map(|i| (2 * (i % cells) + 1, 2 * (i / cells) + 1))
+            .filter(|&(x, y)| !plaza(x, y)).collect::<Vec<_>>();
+        rng.shuffle(&mut spots);
+        let keys = spots[..n].to_vec();
+        let path = |mut p: (usize, usize)| {
+            let mut path = vec![];
+            while let Some(&q) = parent.get(&p) {
+                path.push(p);
+                p = q;
+            }
+            path
+        };
+        let paths = keys.iter().map(|&k| path(k)).collect::<Vec<_>>();
+        let mut letters = (b'a'..b'a' + n as u8).map(|b| b as char).collect::<Vec<_>>();
+        rng.shuffle(&mut letters);
+        for (&(x, y), &letter) in keys.iter().zip(&letters) { grid[y][x] = letter; }
+        for (m, letter) in letters.iter().enumerate().take(n.saturating_sub(1)) {
+            if rng.one_in(3) { continue; }
+            let j = rng.range(m as i64 + 1, n as i64 - 1) as usize;
+            let candidates = paths[j].iter().filter(|&&(x, y)| grid[y][x] == '.' && !plaza(x, y)
+                && paths.iter().enumerate().all(|(i, path)| i > m || !path.contains(&(x, y))))
+                .collect::<Vec<_>>();
+            if candidates.is_empty() { continue; }
+            let &(x, y) = *rng.pick(&candidates);
+            grid[y][x] = letter.to_ascii_uppercase();
+        }
+        grid.iter().map(|row| row.iter().collect::<String>() + "\n").collect()
+    }
+
+    /// The map, one row per line.
+    pub fn parse(self: &Self, input: &mut dyn io::Read) -> error::Result<Vec<String>> {
+        let lines = parse::lines(input)?;
+        for l in &lines {
+            l.only("#.@abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ",
+                   "a wall, a passage, an entrance, a key or a door")?;
+        }
+        Ok(lines.into_iter().map(|l| l.text).collect())
+    }
+
+    pub fn part1_impl(self: &Self, map: &[String]) -> error::Result<usize> {
         let (grid_r, mut grid_w) = evmap::new();
         let mut origin = (0, 0);
         let (mut mx, mut my) = (0, 0);
         let mut keys = HashSet::new();
-        reader.lines().enumerate().for_each(|(y, l)|
-            l.unwrap().chars().enumerate().for_each(|(x, c)| {
+        map.iter().enumerate().for_each(|(y, l)|
+            l.chars().enumerate().for_each(|(x, c)| {
                 if x > mx { mx = x };
                 if y > my { my = y };
-//                eprintln!("{} {} {:?}", x, y, c);
+                log::trace!("{} {} {:?}", x, y, c);
                 let mut c = c;
                 match c {
                     '@' => { origin = (x, y); c = '.'; },
@@ -70,22 +172,24 @@ impl Day18 {
         }
 
         let mut seen = HashSet::new();
-//        eprintln!("{}", to_string(&keys));
+        log::trace!("{}", to_string(&keys));
+        let cancel = day::Cancel::current();
         let r = (1..).fold_while(
             (1, vec![(origin, None as Option<(usize, usize)>, keys)]),
             |(_, states), i| {
-//                eprintln!("i {}", i);
+                if cancel.is_cancelled() { return Done((i, vec![])); }
+                log::trace!("i {}", i);
             let new_states: Vec<_> = states.into_iter()
                 .flat_map(|state| {
                     let (pos, last, locked_keys) = state;
                     let seen_key = (pos, to_string(&locked_keys));
                     if seen.contains(&seen_key) { vec![] } else {
                         seen.insert(seen_key);
-//                    eprintln!("i {} pos {:?} last {:?} locked_keys {:?}", i, pos, last, locked_keys);
+                    log::trace!("i {} pos {:?} last {:?} locked_keys {:?}", i, pos, last, locked_keys);
                         let candidates = next(pos);
-//                    eprintln!("candidates {:?}", candidates);
+                    log::trace!("candidates {:?}", candidates);
                         if candidates.len() == 1 && Some(candidates[0]) == last {
-//                            eprintln!("plugging {:?}", pos);
+                            log::trace!("plugging {:?}", pos);
                             grid_w.update(pos, '#');
                             grid_w.refresh();
                         }
@@ -119,12 +223,12 @@ impl Day18 {
                             !seen.contains(&(*p, seen_key.clone())))
                             .map(|(p, last, locked_keys, _)| (p, last, locked_keys))
                             .collect();
-//                    eprintln!("candidates2 {:?}", candidates2);
+                    log::trace!("candidates2 {:?}", candidates2);
                         candidates2
                     }
                 })
                 .collect();
-//                eprintln!("new_states {:?}", new_states);
+                log::trace!("new_states {:?}", new_states);
                 let r = (i, new_states.clone());
                 if new_states.into_iter().find(|(_, _, locked_keys)| locked_keys.is_empty()) != None {
                     Done(r)
@@ -132,22 +236,22 @@ impl Day18 {
                     Continue(r)
                 }
             });
+        cancel.check()?;
         let r = r.into_inner().0;
-        eprintln!("{:?}", r);
+        log::debug!("{:?}", r);
         Ok(r)
     }
 
-    fn part2_impl(self: &Self, input: &mut dyn io::Read) -> BoxResult<usize> {
-        let reader = io::BufReader::new(input);
+    pub fn part2_impl(self: &Self, map: &[String]) -> error::Result<usize> {
         let (grid_r, mut grid_w) = evmap::new();
         let mut origin = (0, 0);
         let (mut mx, mut my) = (0, 0);
         let mut keys = HashSet::new();
-        reader.lines().enumerate().for_each(|(y, l)|
-            l.unwrap().chars().enumerate().for_each(|(x, c)| {
+        map.iter().enumerate().for_each(|(y, l)|
+            l.chars().enumerate().for_each(|(x, c)| {
                 if x > mx { mx = x };
                 if y > my { my = y };
-//                eprintln!("{} {} {:?}", x, y, c);
+                log::trace!("{} {} {:?}", x, y, c);
                 let mut c = c;
                 match c {
                     '@' => {
@@ -197,13 +301,15 @@ impl Day18 {
         }
 
         let mut seen = HashSet::new();
-//        eprintln!("{}", to_string(&keys));
+        log::trace!("{}", to_string(&keys));
 
         let robots: Vec<_> = origins.into_iter().map(|o| (o, None)).collect();
+        let cancel = day::Cancel::current();
         let r = (1..).fold_while(
             (1, vec![(robots, keys)]),
             |(_, states), i| {
-//                eprintln!("i {}", i);
+                if cancel.is_cancelled() { return Done((i, vec![])); }
+                log::trace!("i {}", i);
                 let new_states: Vec<_> = states.into_iter()
                     .flat_map(|state| {
                         let (robots, locked_keys) = state;
@@ -213,11 +319,11 @@ impl Day18 {
                             let seen_key = (pos, to_string(&locked_keys));
                             if seen.contains(&seen_key) { vec![] } else {
                                 seen.insert(seen_key);
-//                                eprintln!("i {} pos {:?} last {:?} locked_keys {:?}", i, pos, last, locked_keys);
+                                log::trace!("i {} pos {:?} last {:?} locked_keys {:?}", i, pos, last, locked_keys);
                                 let candidates = next(pos);
-//                                eprintln!("candidates {:?}", candidates);
+                                log::trace!("candidates {:?}", candidates);
                                 if candidates.len() == 1 && Some(candidates[0]) == last {
-//                                    eprintln!("plugging {:?}", pos);
+                                    log::trace!("plugging {:?}", pos);
                                     grid_w.update(pos, '#');
                                     grid_w.refresh();
                                 }
@@ -255,13 +361,13 @@ impl Day18 {
                                         (robots, locked_keys.clone())
                                     })
                                     .collect();
-//                                eprintln!("candidates2 {:?}", candidates2);
+                                log::trace!("candidates2 {:?}", candidates2);
                                 candidates2
                             }
                         }).collect::<Vec<_>>()
                     })
                     .collect();
-//                eprintln!("new_states {:?}", new_states);
+                log::trace!("new_states {:?}", new_states);
                 let r = (i, new_states.clone());
                 if new_states.is_empty() || new_states.into_iter().find(|(_, locked_keys)| locked_keys.is_empty()) != None {
                     Done(r)
@@ -269,8 +375,9 @@ impl Day18 {
                     Continue(r)
                 }
             });
+        cancel.check()?;
         let r = r.into_inner().0;
-        eprintln!("{:?}", r);
+        log::debug!("{:?}", r);
         Ok(r)
     }
 }
@@ -278,9 +385,12 @@ impl Day18 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::cmp::Reverse;
+    use std::collections::BinaryHeap;
+    use crate::generate;
 
     fn test1(s: &str, n: usize) {
-        assert_eq!(Day18 {}.part1_impl(&mut s.as_bytes()).unwrap(), n);
+        assert_eq!(Day18 {}.part1_impl(&Day18 {}.parse(&mut s.as_bytes()).unwrap()).unwrap(), n);
     }
 
     #[test]
@@ -316,7 +426,7 @@ mod tests {
     }
 
     fn test2(s: &str, n: usize) {
-        assert_eq!(Day18 {}.part2_impl(&mut s.as_bytes()).unwrap(), n);
+        assert_eq!(Day18 {}.part2_impl(&Day18 {}.parse(&mut s.as_bytes()).unwrap()).unwrap(), n);
     }
 
     #[test]
@@ -352,4 +462,68 @@ mod tests {
 #o#m..#i#jk.#
 #############", 72);
     }
+
+    #[test]
+    fn properties() {
+        let day = Day18 {};
+        generate::check(10, |rng| {
+            let map = day.parse(&mut day.generate_impl(rng, 4).as_bytes()).unwrap();
+            let mut grid = map.iter().map(|l| l.as_bytes().to_vec()).collect::<Vec<_>>();
+            let y = grid.iter().position(|l| l.contains(&b'@')).unwrap();
+            let x = grid[y].iter().position(|&c| c == b'@').unwrap();
+            assert_eq!(day.part1_impl(&map).ok(), fewest_steps(&grid, vec![(x, y)]));
+            for &(x, y) in &[(x, y), (x + 1, y), (x - 1, y), (x, y + 1), (x, y - 1)] {
+                grid[y][x] = b'#';
+            }
+            let robots = vec![(x - 1, y - 1), (x + 1, y - 1), (x - 1, y + 1), (x + 1, y + 1)];
+            assert_eq!(day.part2_impl(&map).ok(), fewest_steps(&grid, robots));
+        });
+    }
+
+    /// The fewest steps for the robots to collect every key, by Dijkstra's
+    /// algorithm over where the robots are and which keys they hold, moving one
+    /// robot at a time to a key it can walk to.
+    fn fewest_steps(grid: &[Vec<u8>], robots: Vec<(usize, usize)>) -> Option<usize> {
+        let all = grid.iter().flatten().filter(|c| c.is_ascii_lowercase())
+            .fold(0u32, |keys, &c| keys | 1 << (c - b'a'));
+        let reachable = |from: (usize, usize), keys: u32| {
+            let mut distances = HashMap::new();
+            distances.insert(from, 0);
+            let mut queue = VecDeque::from(vec![from]);
+            let mut found = vec![];
+            while let Some((x, y)) = queue.pop_front() {
+                let d = distances[&(x, y)];
+                for &(x, y) in &[(x + 1, y), (x - 1, y), (x, y + 1), (x, y - 1)] {
+                    let c = grid[y][x];
+                    let locked = c.is_ascii_uppercase() && keys & 1 << (c - b'A') == 0;
+                    if c == b'#' || locked || distances.contains_key(&(x, y)) { continue; }
+                    distances.insert((x, y), d + 1);
+                    if c.is_ascii_lowercase() && keys & 1 << (c - b'a') == 0 {
+                        found.push(((x, y), d + 1, 1 << (c - b'a')));
+                    } else {
+                        queue.push_back((x, y));
+                    }
+                }
+            }
+            found
+        };
+        let mut best = HashMap::new();
+        let mut queue = BinaryHeap::new();
+        queue.push(Reverse((0, robots, 0u32)));
+        while let Some(Reverse((steps, robots, keys))) = queue.pop() {
+            if keys == all { return Some(steps); }
+            if matches!(best.get(&(robots.clone(), keys)), Some(&b) if b < steps) { continue; }
+            for (i, &robot) in robots.iter().enumerate() {
+                for (key, d, bit) in reachable(robot, keys) {
+                    let mut robots = robots.clone();
+                    robots[i] = key;
+                    if steps + d < *best.get(&(robots.clone(), keys | bit)).unwrap_or(&usize::MAX) {
+                        best.insert((robots.clone(), keys | bit), steps + d);
+                        queue.push(Reverse((steps + d, robots, keys | bit)));
+                    }
+                }
+            }
+        }
+        None
+    }
 }
\ No newline at end of file
@@ -1,15 +1,9 @@
-use simple_error::bail;
-use closure::closure;
 use evmap;
-use evmap::{ReadHandle, WriteHandle};
-use std::error;
 use std::io;
-use std::io::BufRead;
 use std::sync::mpsc;
-use std::thread;
-use crate::day;
-
-pub type BoxResult<T> = Result<T, Box<dyn error::Error>>;
+use crate::day::{self, Day};
+use crate::error::{self, Error, Fault};
+use crate::parse;
 
 struct Intcode {
     p: Vec<i64>,
@@ -22,113 +16,114 @@ impl Intcode {
     fn op(&self, c: i64) -> i64 { c % 100 }
 
     fn get(&mut self, a: usize) -> i64 {
-//        eprintln!("get {}", a);
+        log::trace!("get {}", a);
         if a >= self.p.len() { self.p.resize(a + 1, 0); }
         self.p[a]
     }
 
     fn put(&mut self, a: usize, v: i64) {
-//        eprintln!("put @{} {}", a, v);
+        log::trace!("put @{} {}", a, v);
         if a >= self.p.len() { self.p.resize(a + 1, 0); }
         self.p[a] = v;
     }
 
-    fn addr(&mut self, ip: usize, i: usize) -> usize {
+    fn addr(&mut self, ip: usize, i: usize) -> error::Result<usize> {
         let a = self.get(ip + i);
         let v = match self.get(ip) / vec![100, 1000, 10000][i - 1] % 10 {
             0 => a as usize,
             2 => (a  + self.base) as usize,
-            _ => 0, // XXX
+            mode => return Err(Error::intcode(ip, Fault::BadMode(mode))),
         };
-        v
+        Ok(v)
     }
 
-    fn val(&mut self, ip: usize, i: usize) -> i64 {
+    fn val(&mut self, ip: usize, i: usize) -> error::Result<i64> {
         let a = self.get(ip + i);
         let v = match self.get(ip) / vec![100, 1000, 10000][i - 1] % 10 {
             1 => a,
             _ => {
-                let addr = self.addr(ip, i);
+                let addr = self.addr(ip, i)?;
                 self.get(addr)
             },
         };
-//        eprintln!("{} {} {} {} {} {}", ip, self.get(ip), i, a, self.base, v);
-        v
+        log::trace!("{} {} {} {} {} {}", ip, self.get(ip), i, a, self.base, v);
+        Ok(v)
     }
 
     fn run(&mut self, sender: &mpsc::Sender<i64>, receiver: &mpsc::Receiver<i64>,
-        request: &mpsc::Sender<()>, ack: &mpsc::Receiver<()>) -> BoxResult<i64> {
+        request: &mpsc::Sender<()>, ack: &mpsc::Receiver<()>) -> error::Result<i64> {
         let mut ip = 0;
         let mut o = None;
+        let cancel = day::Cancel::current();
         while { let op = self.get(ip); self.op(op) != 99 } {
-//            eprintln!("{}: {} {} {} {}", ip, self.p[ip], self.p[ip + 1], self.p[ip + 2], self.p[ip + 3]);
+            cancel.check()?;
+            log::trace!("{}: {} {} {} {}", ip, self.p[ip], self.p[ip + 1], self.p[ip + 2], self.p[ip + 3]);
             match self.op(self.p[ip]) {
                 1 => {
-                    let a = self.val(ip, 1);
-                    let b = self.val( ip, 2);
-                    let c = self.addr(ip, 3);
+                    let a = self.val(ip, 1)?;
+                    let b = self.val(ip, 2)?;
+                    let c = self.addr(ip, 3)?;
                     self.put(c, a + b);
                     ip += 4;
                 },
                 2 => {
-                    let a = self.val(ip, 1);
-                    let b = self.val(ip, 2);
-                    let c = self.addr(ip, 3);
+                    let a = self.val(ip, 1)?;
+                    let b = self.val(ip, 2)?;
+                    let c = self.addr(ip, 3)?;
                     self.put(c, a * b);
                     ip += 4;
                 },
                 3 => {
-                    let a = self.addr(ip, 1);
-//                    eprintln!(">recv {}", a);
+                    let a = self.addr(ip, 1)?;
+                    log::trace!(">recv {}", a);
                     request.send(())?;
-                    self.put(a as usize, receiver.recv()?);
-//                    eprintln!("<recv {}", self.get(a as usize));
+                    self.put(a as usize, day::recv(receiver)?);
+                    log::trace!("<recv {}", self.get(a));
                     ip += 2;
                 },
                 4 => {
-                    let a = self.val(ip, 1);
+                    let a = self.val(ip, 1)?;
                     o = Some(a);
-//                    eprintln!(">send {}", a);
+                    log::trace!(">send {}", a);
                     sender.send(a)?;
-//                    eprintln!("<send");
-                    ack.recv()?;
-//                    eprintln!("<<send");
+                    log::trace!("<send");
+                    day::recv(ack)?;
+                    log::trace!("<<send");
                     ip += 2;
                 },
                 5 => {
-                    let a = self.val(ip, 1);
-                    let b = self.val(ip, 2) as usize;
+                    let a = self.val(ip, 1)?;
+                    let b = self.val(ip, 2)? as usize;
                     ip = if a != 0 { b } else { ip + 3 };
                 },
                 6 => {
-                    let a = self.val(ip, 1);
-                    let b = self.val(ip, 2) as usize;
+                    let a = self.val(ip, 1)?;
+                    let b = self.val(ip, 2)? as usize;
                     ip = if a == 0 { b } else { ip + 3 };
                 },
                 7 => {
-                    let a = self.val(ip, 1);
-                    let b = self.val(ip, 2);
-                    let c = self.addr(ip, 3);
+                    let a = self.val(ip, 1)?;
+                    let b = self.val(ip, 2)?;
+                    let c = self.addr(ip, 3)?;
                     self.put(c, if a < b { 1 } else { 0 });
                     ip += 4;
                 },
                 8 => {
-                    let a = self.val(ip, 1);
-                    let b = self.val(ip, 2);
-                    let c = self.addr(ip,3);
+                    let a = self.val(ip, 1)?;
+                    let b = self.val(ip, 2)?;
+                    let c = self.addr(ip, 3)?;
                     self.put(c, if a == b { 1 } else { 0 });
                     ip += 4;
                 },
                 9 => {
-                    self.base += self.val(ip, 1);
-//                    eprintln!("<base {}", self.base);
+                    self.base += self.val(ip, 1)?;
+                    log::trace!("<base {}", self.base);
                     ip += 2;
                 }
-                _ => bail!("unknown opcode {}: {}", ip, self.op(self.p[ip])),
+                op => return Err(Error::intcode(ip, Fault::UnknownOpcode(op))),
             };
         }
-        if o.is_none() { bail!("no output"); }
-        Ok(o.unwrap())
+        o.ok_or_else(|| Error::intcode(ip, Fault::NoOutput))
     }
 
     #[allow(dead_code)]
@@ -198,100 +193,121 @@ impl Intcode {
 
 pub struct Day19 {}
 
+inventory::submit! { day::Registration(&Day19 {}) }
+
 impl day::Day for Day19 {
     fn tag(&self) -> &str { "19" }
 
-    fn part1(&self, input: &dyn Fn() -> Box<dyn io::Read>) {
-        let reader = io::BufReader::new(input());
-        let p = reader.split(b',')
-            .map(|v| String::from_utf8(v.unwrap()).unwrap())
-            .map(|s| s.trim_end().parse::<i64>().unwrap())
-            .collect::<Vec<_>>();
-        let (grid_r, mut grid_w) = evmap::new();
-        println!("{:?}", self.part1_impl(p, &grid_r, &mut grid_w));
+    fn title(&self) -> &str { "Tractor Beam" }
+
+    fn parts(&self) -> [&str; 2] {
+        ["points affected by the beam in the scanned area",
+         "10000 * x + y of the closest square that fits in the beam"]
     }
 
-    fn part2(&self, input: &dyn Fn() -> Box<dyn io::Read>) {
-        let reader = io::BufReader::new(input());
-        let p = reader.split(b',')
-            .map(|v| String::from_utf8(v.unwrap()).unwrap())
-            .map(|s| s.trim_end().parse::<i64>().unwrap())
-            .collect::<Vec<_>>();
-        let (grid_r, mut grid_w) = evmap::new();
-        println!("{:?}", self.part2_impl(p, &grid_r, &mut grid_w));
+    fn params(&self) -> &[day::Param] {
+        &[day::Param { name: "area", default: 50, min: 0, max: i64::MAX,
+                      help: "side of the area scanned in part 1" },
+         day::Param { name: "square", default: 100, min: 1, max: i64::MAX,
+                      help: "side of the square to fit in part 2" }]
+    }
+}
+
+impl day::Puzzle for Day19 {
+    type Input = Vec<i64>;
+
+    fn parse_input(&self, input: &mut dyn io::Read) -> error::Result<Vec<i64>> {
+        self.parse(input)
+    }
+
+    fn part1(&self, p: &Vec<i64>) -> day::Answer {
+        self.part1_impl(p, self.param("area")).into()
+    }
+
+    fn part2(&self, p: &Vec<i64>) -> day::Answer {
+        self.part2_impl(p, self.param("square")).into()
     }
 }
 
 impl Day19 {
-    fn part1_impl(self: &Self, p: Vec<i64>,
-                  grid_r: &ReadHandle<(i64, i64), char>,
-                  grid_w: &mut WriteHandle<(i64, i64), char>)
-        -> BoxResult<i64> {
+    /// The Intcode program.
+    pub fn parse(self: &Self, input: &mut dyn io::Read) -> error::Result<Vec<i64>> {
+        parse::numbers(input, "an integer")
+    }
+
+    pub fn part1_impl(self: &Self, p: &[i64], area: i64) -> error::Result<i64> {
+        let p = p.to_vec();
         let (input_sender, input_receiver) = mpsc::channel::<i64>();
         let (output_sender, output_receiver) = mpsc::channel::<i64>();
         let (request_sender, request_receiver) = mpsc::channel::<()>();
         let (ack_sender, ack_receiver) = mpsc::channel::<()>();
         let (start_sender, start_receiver) = mpsc::channel::<()>();
 
-        let _cpu = thread::spawn(move || {
+        let cpu = day::spawn(move || {
             while start_receiver.recv().is_ok() {
                 let mut ic = Intcode::new(&p.clone());
-                ic.run(&output_sender, &input_receiver, &request_sender, &ack_receiver)
-                    .unwrap_or(0);
+                ic.run(&output_sender, &input_receiver, &request_sender, &ack_receiver)?;
             }
+            Ok(())
         });
+        let probe = |x, y| {
+            start_sender.send(())?;
+            day::recv(&request_receiver)?;
+            input_sender.send(x)?;
+            day::recv(&request_receiver)?;
+            input_sender.send(y)?;
+            let o = day::recv(&output_receiver)?;
+            ack_sender.send(())?;
+            Ok(o)
+        };
 
-        Ok((0..50).flat_map(|x|
-            (0..50).map(closure!(
-                ref start_sender, ref request_receiver, ref input_sender,
-                ref output_receiver, ref ack_sender
-                |y| {
-                    start_sender.send(()).unwrap();
-                    request_receiver.recv().unwrap();
-                    input_sender.send(x).unwrap();
-                    request_receiver.recv().unwrap();
-                    input_sender.send(y).unwrap();
-                    let o = output_receiver.recv().unwrap();
-                    ack_sender.send(()).unwrap();
-                    o
-                })))
-            .sum())
+        let pulled = (0..area).flat_map(|x| (0..area).map(move |y| (x, y)))
+            .map(|(x, y)| probe(x, y))
+            .sum();
+        day::or_join(pulled, cpu)
     }
 
-    fn part2_impl(self: &Self, p: Vec<i64>,
-                  grid_r: &ReadHandle<(i64, i64), i64>,
-                  grid_w: &mut WriteHandle<(i64, i64), i64>)
-                  -> BoxResult<i64> {
+    pub fn part2_impl(self: &Self, p: &[i64], square: i64) -> error::Result<i64> {
+        let d = square - 1;
+        let p = p.to_vec();
+        let (grid_r, mut grid_w) = evmap::new();
         let (input_sender, input_receiver) = mpsc::channel::<i64>();
         let (output_sender, output_receiver) = mpsc::channel::<i64>();
         let (request_sender, request_receiver) = mpsc::channel::<()>();
         let (ack_sender, ack_receiver) = mpsc::channel::<()>();
         let (start_sender, start_receiver) = mpsc::channel::<()>();
-        let _cpu = thread::spawn(move || {
+        let cpu = day::spawn(move || {
             while start_receiver.recv().is_ok() {
                 let mut ic = Intcode::new(&p.clone());
-                ic.run(&output_sender, &input_receiver, &request_sender, &ack_receiver)
-                    .unwrap_or(0);
+                ic.run(&output_sender, &input_receiver, &request_sender, &ack_receiver)?;
             }
+            Ok(())
         });
+        let probe = |x, y| {
+            start_sender.send(())?;
+            day::recv(&request_receiver)?;
+            input_sender.send(x)?;
+            day::recv(&request_receiver)?;
+            input_sender.send(y)?;
+            let o = day::recv(&output_receiver)?;
+            ack_sender.send(())?;
+            Ok(o)
+        };
 
         let (mut x, mut y) = (2, 3);
         let mut look_for_one = true;
         let mut ox = -1;
         loop {
-            start_sender.send(()).unwrap();
-            request_receiver.recv().unwrap();
-            input_sender.send(x).unwrap();
-            request_receiver.recv().unwrap();
-            input_sender.send(y).unwrap();
-            let o = output_receiver.recv().unwrap();
-            ack_sender.send(()).unwrap();
+            let o = match probe(x, y) {
+                Ok(o) => o,
+                Err(e) => return day::or_join(Err(e), cpu),
+            };
             grid_w.update((x, y), o);
             grid_w.refresh();
-            if x >= 99 && y >= 99 && o == 1  {
-                if grid_r.get_and(&(x - 99, y - 99), |c| { c[0] }) == Some(1)
-                    && grid_r.get_and(&(x - 99, y), |c| { c[0] }) == Some(1)
-                    && grid_r.get_and(&(x, y - 99), |c| { c[0] }) == Some(1) {
+            if x >= d && y >= d && o == 1  {
+                if grid_r.get_and(&(x - d, y - d), |c| { c[0] }) == Some(1)
+                    && grid_r.get_and(&(x - d, y), |c| { c[0] }) == Some(1)
+                    && grid_r.get_and(&(x, y - d), |c| { c[0] }) == Some(1) {
                     break;
                 };
             };
@@ -311,7 +327,7 @@ impl Day19 {
                 look_for_one = false;
             };
         };
-        Ok((x - 99) * 10000 + (y - 99))
+        Ok((x - d) * 10000 + (y - d))
     }
 }
 
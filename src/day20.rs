@@ -1,42 +1,139 @@
 use evmap;
 use itertools::Itertools;
 use itertools::FoldWhile::{Continue, Done};
-use std::collections::{HashMap, HashSet};
-use std::error;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::io;
-use std::io::BufRead;
 use crate::day;
-
-pub type BoxResult<T> = Result<T, Box<dyn error::Error>>;
+use crate::error;
+use crate::generate::Rng;
+use crate::parse;
 
 pub struct Day20 {}
 
+inventory::submit! { day::Registration(&Day20 {}) }
+
 impl day::Day for Day20 {
     fn tag(&self) -> &str { "20" }
 
-    fn part1(&self, input: &dyn Fn() -> Box<dyn io::Read>) {
-        println!("{:?}", self.part1_impl(&mut *input()));
+    fn title(&self) -> &str { "Donut Maze" }
+
+    fn parts(&self) -> [&str; 2] {
+        ["steps from AA to ZZ",
+         "steps from AA to ZZ in the recursive maze"]
     }
 
-    fn part2(&self, input: &dyn Fn() -> Box<dyn io::Read>) {
-        println!("{:?}", self.part2_impl(&mut *input()));
+    fn generate(&self, rng: &mut Rng, size: Option<usize>) -> Option<String> {
+        Some(self.generate_impl(rng, size.unwrap_or(5)))
+    }
+}
+
+impl day::Puzzle for Day20 {
+    type Input = Vec<String>;
+
+    fn parse_input(&self, input: &mut dyn io::Read) -> error::Result<Vec<String>> {
+        self.parse(input)
+    }
+
+    fn part1(&self, map: &Vec<String>) -> day::Answer {
+        self.part1_impl(map).into()
+    }
+
+    fn part2(&self, map: &Vec<String>) -> day::Answer {
+        self.part2_impl(map).into()
     }
 }
 
 impl Day20 {
-    fn part1_impl(self: &Self, input: &mut dyn io::Read) -> BoxResult<usize> {
-        let reader = io::BufReader::new(input);
+    /// A square donut with `size` portals (at least one) between its outer and
+    /// inner edges, and AA and ZZ on the outer edge.  Every portal can be
+    /// reached from AA without going through any other, so both parts have a
+    /// solution.
+    pub fn generate_impl(self: &Self, rng: &mut Rng, size: usize) -> String {
+        let n = size.max(1);
+        let b = 5;
+        let k = (n + 2).div_ceil(4) + 1;
+        let w = 2 * b + 5 + 2 * k;
+        // Portals are kept away from the corners, where the labels would be
+        // read as belonging to another edge.
+        let along = (0..k).map(|i| 5 + b + 2 * i).collect::<Vec<_>>();
+        // The tile, its letters and the tile next to it, by edge and position.
+        let outer = |edge, p| match edge {
+            0 => ((p, 2), (p, 0), (p, 1), (p, 3)),
+            1 => ((p, 1 + w), (p, 2 + w), (p, 3 + w), (p, w)),
+            2 => ((2, p), (0, p), (1, p), (3, p)),
+            _ => ((1 + w, p), (2 + w, p), (3 + w, p), (w, p)),
+        };
+        let inner = |edge, p| match edge {
+            0 => ((p, 1 + b), (p, 2 + b), (p, 3 + b), (p, b)),
+            1 => ((p, 2 + w - b), (p, w - b), (p, 1 + w - b), (p, 3 + w - b)),
+            2 => ((1 + b, p), (2 + b, p), (3 + b, p), (b, p)),
+            _ => ((2 + w - b, p), (w - b, p), (1 + w - b, p), (3 + w - b, p)),
+        };
+        let slots = (0..4).flat_map(|edge| along.iter().map(move |&p| (edge, p)))
+            .collect::<Vec<_>>();
+        let (mut outers, mut inners) = (slots.clone(), slots);
+        rng.shuffle(&mut outers);
+        rng.shuffle(&mut inners);
+        // The size of the donut is told by a portal on the lower inner edge.
+        if let Some(i) = inners.iter().position(|&(edge, _)| edge == 1) { inners.swap(0, i); }
+        let mut names = vec![String::from("AA"), String::from("ZZ")];
+        while names.len() < n + 2 {
+            let name = rng.name(2);
+            if !names.contains(&name) { names.push(name); }
+        }
+        let mut portals = names.iter().zip(&outers).map(|(name, &(e, p))| (name, outer(e, p)))
+            .collect::<Vec<_>>();
+        portals.extend(names[2..].iter().zip(&inners).map(|(name, &(e, p))| (name, inner(e, p))));
+        let ring = |x: usize, y: usize| (2..2 + w).contains(&x) && (2..2 + w).contains(&y)
+            && !((2 + b..2 + w - b).contains(&x) && (2 + b..2 + w - b).contains(&y));
+        loop {
+            let mut grid = vec![vec![' '; w + 4]; w + 4];
+            for (y, row) in grid.iter_mut().enumerate() {
+                for (x, c) in row.iter_mut().enumerate().filter(|&(x, _)| ring(x, y)) {
+                    let edge = [(x + 1, y), (x - 1, y), (x, y + 1), (x, y - 1)].iter()
+                        .any(|&(x, y)| !ring(x, y));
+                    *c = if edge || rng.one_in(4) { '#' } else { '.' };
+                }
+            }
+            for (name, ((tx, ty), (ax, ay), (bx, by), (nx, ny))) in &portals {
+                grid[*ty][*tx] = '.';
+                grid[*ny][*nx] = '.';
+                grid[*ay][*ax] = name.as_bytes()[0] as char;
+                grid[*by][*bx] = name.as_bytes()[1] as char;
+            }
+            let mut seen = HashSet::new();
+            let mut queue = VecDeque::from(vec![portals[0].1 .0]);
+            while let Some((x, y)) = queue.pop_front() {
+                for p in [(x + 1, y), (x - 1, y), (x, y + 1), (x, y - 1)] {
+                    if grid[p.1][p.0] == '.' && seen.insert(p) { queue.push_back(p); }
+                }
+            }
+            if portals.iter().all(|(_, (tile, ..))| seen.contains(tile)) {
+                return grid.iter().map(|row| row.iter().collect::<String>() + "\n").collect();
+            }
+        }
+    }
+
+    /// The map, one row per line.
+    pub fn parse(self: &Self, input: &mut dyn io::Read) -> error::Result<Vec<String>> {
+        let lines = parse::lines(input)?;
+        for l in &lines {
+            l.only("#. ABCDEFGHIJKLMNOPQRSTUVWXYZ", "a wall, a passage, a portal letter or space")?;
+        }
+        Ok(lines.into_iter().map(|l| l.text).collect())
+    }
+
+    pub fn part1_impl(self: &Self, map: &[String]) -> error::Result<usize> {
         let (grid_r, mut grid_w) = evmap::new();
         let (portals_r, mut portals_w) = evmap::new();
         let mut w = 0;
         let mut h = 0;
         let mut b = 0;
-        reader.lines().enumerate().for_each(|(y, l)| {
-            let l = l.unwrap();
+        map.iter().enumerate().for_each(|(y, l)| {
             // width
             if y == 0 { w = l.len() - 4; }
             l.chars().enumerate().for_each(|(x, c)| {
-//                eprintln!("x {} y {} w {} h {} b {}", x, y, w, h, b);
+                log::trace!("x {} y {} w {} h {} b {}", x, y, w, h, b);
                 let uc = c.is_ascii_uppercase();
                 // upper outer portal
                 if y == 1 && uc {
@@ -110,10 +207,10 @@ impl Day20 {
                 grid_w.refresh();
             })
         });
-//        eprintln!("w {} h {} b {}", w, h, b);
+        log::trace!("w {} h {} b {}", w, h, b);
         let mut jump = HashMap::new();
         portals_r.for_each(|_x, y| {
-//            eprintln!("portals {:?} {:?}", _x, y);
+            log::trace!("portals {:?} {:?}", _x, y);
             if y.len() == 2 {
                 jump.insert(y[0], y[1]);
                 jump.insert(y[1], y[0]);
@@ -121,7 +218,7 @@ impl Day20 {
         });
         let entry = portals_r.get_and("AA", |p| p[0]).unwrap();
         let exit = portals_r.get_and("ZZ", |p| p[0]).unwrap();
-//        eprintln!("entry {:?} exit {:?}", entry, exit);
+        log::trace!("entry {:?} exit {:?}", entry, exit);
 
         let dirs = vec![(0, -1), (1, 0), (0, 1), (-1, 0)];
 
@@ -150,7 +247,7 @@ impl Day20 {
                 if c[0] == '.' {
                     let p = (x - 2, y - 2);
                     if next(p, &vec![]).len() == 1 && p != entry && p != exit {
-//                        eprintln!("plugging {:?}", p);
+                        log::trace!("plugging {:?}", p);
                         grid_w.update((*x, *y), '#');
                         plugged = true;
                     }
@@ -170,12 +267,12 @@ impl Day20 {
 
         let mut seen = HashSet::new();
         let r = (1..).fold_while((vec![(entry, vec![])], 0), |(states, i), _| {
-//            eprintln!("i {} states len {}", i, states.len());
-//            eprintln!("states {:?}", states);
+            log::trace!("i {} states len {}", i, states.len());
+            log::trace!("states {:?}", states);
             states.iter().for_each(|&(p, _)| { seen.insert(p); });
             let new_states: Vec<(_, Vec<_>)> = states.iter().flat_map(|(pos, path)| {
                 let candidates: Vec<_> = next(*pos, path).into_iter().filter(|p| !seen.contains(p)).collect();
-//                eprintln!("candidates {:?}", candidates);
+                log::trace!("candidates {:?}", candidates);
                 candidates.into_iter().map(move |p| {
                     let mut path = path.clone();
                     path.push(*pos);
@@ -187,23 +284,21 @@ impl Day20 {
             else { Continue((new_states, i + 1)) }
         });
         let r = r.into_inner().1;
-//        eprintln!("{:?}", r);
+        log::trace!("{:?}", r);
         Ok(r)
     }
 
-    fn part2_impl(self: &Self, input: &mut dyn io::Read) -> BoxResult<usize> {
-        let reader = io::BufReader::new(input);
+    pub fn part2_impl(self: &Self, map: &[String]) -> error::Result<usize> {
         let (grid_r, mut grid_w) = evmap::new();
         let (portals_r, mut portals_w) = evmap::new();
         let mut w = 0;
         let mut h = 0;
         let mut b = 0;
-        reader.lines().enumerate().for_each(|(y, l)| {
-            let l = l.unwrap();
+        map.iter().enumerate().for_each(|(y, l)| {
             // width
             if y == 0 { w = l.len() - 4; }
             l.chars().enumerate().for_each(|(x, c)| {
-//                eprintln!("x {} y {} w {} h {} b {}", x, y, w, h, b);
+                log::trace!("x {} y {} w {} h {} b {}", x, y, w, h, b);
                 let uc = c.is_ascii_uppercase();
                 // upper outer portal
                 if y == 1 && uc {
@@ -277,10 +372,10 @@ impl Day20 {
                 grid_w.refresh();
             })
         });
-//        eprintln!("w {} h {} b {}", w, h, b);
+        log::trace!("w {} h {} b {}", w, h, b);
         let mut jump = HashMap::new();
         portals_r.for_each(|_x, y| {
-//            eprintln!("portals {:?} {:?}", _x, y);
+            log::trace!("portals {:?} {:?}", _x, y);
             if y.len() == 2 {
                 jump.insert(y[0], y[1]);
                 jump.insert(y[1], y[0]);
@@ -288,7 +383,7 @@ impl Day20 {
         });
         let entry = portals_r.get_and("AA", |p| (p[0].0, p[0].1, 0)).unwrap();
         let exit = portals_r.get_and("ZZ", |p| (p[0].0, p[0].1, 0)).unwrap();
-//        eprintln!("entry {:?} exit {:?}", entry, exit);
+        log::trace!("entry {:?} exit {:?}", entry, exit);
 
         let dirs = vec![(0, -1), (1, 0), (0, 1), (-1, 0)];
 
@@ -312,7 +407,7 @@ impl Day20 {
             } else if let Some(&(nx, ny, _)) = jump.get(&(x, y, true)) {
                 if z > 0 { next.push((nx, ny, z - 1)); }
             };
-//            eprintln!("{} {} {} -> {:?}", x, y, z, next);
+            log::trace!("{} {} {} -> {:?}", x, y, z, next);
             next
         };
 
@@ -323,7 +418,7 @@ impl Day20 {
                     let p0 = (x - 2, y - 2, 0);
                     let p1 = (x - 2, y - 2, 1);
                     if next(p1, &vec![]).len() == 1 && p0 != entry && p0 != exit {
-//                        eprintln!("plugging ({}, {})", p0.0, p0.1);
+                        log::trace!("plugging ({}, {})", p0.0, p0.1);
                         grid_w.update((*x, *y), '#');
                         plugged = true;
                     }
@@ -343,8 +438,8 @@ impl Day20 {
 
         let mut seen = HashSet::new();
         let r = (1..).fold_while((vec![(entry, vec![])], 0), |(states, i), _| {
-//            eprintln!("i {} states len {}", i, states.len());
-//            eprintln!("states {:?}", states);
+            log::trace!("i {} states len {}", i, states.len());
+            log::trace!("states {:?}", states);
             states.iter().for_each(|&(p, _)| { seen.insert(p); });
             let new_states: Vec<(_, Vec<_>)> = states.iter().flat_map(|(pos, path)| {
                 let candidates: Vec<_> = next(*pos, path).into_iter().filter(|p| !seen.contains(p)).collect();
@@ -359,7 +454,7 @@ impl Day20 {
             else { Continue((new_states, i + 1)) }
         });
         let r = r.into_inner().1;
-//        eprintln!("{:?}", r);
+        log::trace!("{:?}", r);
         Ok(r)
     }
 }
@@ -367,10 +462,12 @@ impl Day20 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::generate;
 
     fn test1(s: &str, n: usize) {
         assert_eq!(
-            Day20 {}.part1_impl(&mut s.replace(":", "").as_bytes()).unwrap(),
+            Day20 {}.part1_impl(
+                &Day20 {}.parse(&mut s.replace(":", "").as_bytes()).unwrap()).unwrap(),
             n);
     }
 
@@ -435,7 +532,8 @@ YN......#               VT..#....QG:
     }
 
     fn test2(s: &str, n: usize) {
-        assert_eq!(Day20 {}.part2_impl(&mut s.replace(":", "").as_bytes()).unwrap(), n);
+        let map = Day20 {}.parse(&mut s.replace(":", "").as_bytes()).unwrap();
+        assert_eq!(Day20 {}.part2_impl(&map).unwrap(), n);
     }
 
     #[test]
@@ -478,4 +576,59 @@ RE....#.#                           #......RF:
                A O F   N                     :
                A A D   M                     :", 396);
     }
+
+    /// The fewest steps from AA to ZZ by a plain breadth-first search, over the
+    /// levels too when `recursive`, going no deeper than there are portals.
+    fn fewest_steps(map: &[String], recursive: bool) -> Option<usize> {
+        let grid = map.iter().map(|l| l.as_bytes()).collect::<Vec<_>>();
+        let (h, w) = (grid.len() as i32, grid.iter().map(|l| l.len()).max().unwrap_or(0) as i32);
+        let at = |x: i32, y: i32|
+            *grid.get(y as usize).and_then(|l| l.get(x as usize)).unwrap_or(&b' ');
+        // The tiles next to a label, with the label and whether it is on the outer edge.
+        let mut labels = HashMap::new();
+        for (x, y) in (0..w).cartesian_product(0..h) {
+            if at(x, y) != b'.' { continue; }
+            for &(dx, dy) in &[(1, 0), (-1, 0), (0, 1), (0, -1)] {
+                let (near, far) = (at(x + dx, y + dy), at(x + 2 * dx, y + 2 * dy));
+                if !near.is_ascii_uppercase() { continue; }
+                let name = if dx + dy > 0 { [near, far] } else { [far, near] };
+                labels.insert((x, y), (name, x == 2 || y == 2 || x + 3 == w || y + 3 == h));
+            }
+        }
+        let tile = |name: [u8; 2], not: (i32, i32)|
+            labels.iter().find(|&(&t, l)| l.0 == name && t != not).map(|(&t, _)| t);
+        let (start, end) = (tile(*b"AA", (-1, -1))?, tile(*b"ZZ", (-1, -1))?);
+        let depth = labels.len();
+        let mut seen = HashSet::new();
+        seen.insert((start, 0));
+        let mut queue = VecDeque::new();
+        queue.push_back((start, 0, 0));
+        while let Some(((x, y), level, steps)) = queue.pop_front() {
+            if (x, y) == end && level == 0 { return Some(steps); }
+            let mut next = [(x + 1, y), (x - 1, y), (x, y + 1), (x, y - 1)].iter()
+                .filter(|&&(x, y)| at(x, y) == b'.').map(|&t| (t, level)).collect::<Vec<_>>();
+            let portal = labels.get(&(x, y))
+                .and_then(|&(name, outer)| Some((tile(name, (x, y))?, outer)));
+            match portal {
+                Some((t, _)) if !recursive => next.push((t, level)),
+                Some((t, true)) if level > 0 => next.push((t, level - 1)),
+                Some((t, false)) if level < depth => next.push((t, level + 1)),
+                _ => (),
+            }
+            for (t, level) in next {
+                if seen.insert((t, level)) { queue.push_back((t, level, steps + 1)); }
+            }
+        }
+        None
+    }
+
+    #[test]
+    fn properties() {
+        let day = Day20 {};
+        generate::check(10, |rng| {
+            let map = day.parse(&mut day.generate_impl(rng, 3).as_bytes()).unwrap();
+            assert_eq!(day.part1_impl(&map).ok(), fewest_steps(&map, false));
+            assert_eq!(day.part2_impl(&map).ok(), fewest_steps(&map, true));
+        });
+    }
 }
\ No newline at end of file
@@ -1,10 +1,11 @@
 use modinverse::modinverse;
 //use modular::*;
 use regex::Regex;
-use std::error;
 use std::io;
-use std::io::BufRead;
-use crate::day;
+use crate::day::{self, Day};
+use crate::error::{self, Error};
+use crate::generate::Rng;
+use crate::parse;
 
 use std::fmt;
 use std::ops::{Add, Mul, Sub};
@@ -206,55 +207,116 @@ impl fmt::Display for Modulo {
 //    }
 //}
 
-pub type BoxResult<T> = Result<T, Box<dyn error::Error>>;
+/// A shuffle technique.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Technique {
+    NewStack,
+    Cut(i64),
+    Increment(usize),
+}
 
 pub struct Day22 {}
 
+inventory::submit! { day::Registration(&Day22 {}) }
+
 impl day::Day for Day22 {
     fn tag(&self) -> &str { "22" }
 
-    fn part1(&self, input: &dyn Fn() -> Box<dyn io::Read>) {
-        println!("{:?}", self.part1_impl(&mut *input(), 119315717514047usize, 1,75144274331587usize));
-        println!("{:?}", self.part1_impl(&mut *input(), 119315717514047usize, 101741582076661usize,75144274331587usize));
-//        println!("{:?}", self.part1_impl(&mut *input(), 10007, 1, 2019));
+    fn title(&self) -> &str { "Slam Shuffle" }
+
+    fn parts(&self) -> [&str; 2] {
+        ["position of the card after the shuffle",
+         "card at the position after the repeated shuffle"]
+    }
+
+    fn params(&self) -> &[day::Param] {
+        &[day::Param { name: "deck", default: 10007, min: 1, max: i64::MAX,
+                      help: "cards in the deck in part 1" },
+         day::Param { name: "card", default: 2019, min: 0, max: i64::MAX,
+                      help: "card to follow in part 1" },
+         day::Param { name: "big-deck", default: 119315717514047, min: 1, max: i64::MAX,
+                      help: "cards in the deck in part 2" },
+         day::Param { name: "shuffles", default: 101741582076661, min: 0, max: i64::MAX,
+                      help: "times the deck is shuffled in part 2" },
+         day::Param { name: "position", default: 2020, min: 0, max: i64::MAX,
+                      help: "position to look at in part 2" }]
     }
 
-    fn part2(&self, input: &dyn Fn() -> Box<dyn io::Read>) {
-        println!("{:?}", self.part2_impl(&mut *input(), 119315717514047usize, 101741582076661usize, 2020));
-        println!("{:?}", self.part2_impl(&mut *input(), 119315717514047usize, 1, 47689744938338usize));
-        println!("{:?}", self.part2_impl(&mut *input(), 119315717514047usize, 101741582076660usize, 2020));
-//        println!("{:?}", self.part2_impl(&mut *input(), 119315717514047, 101741582076661, 2019));
-//        println!("{:?}", self.part2_impl(&mut *input(), 10007, 1, 1510));
-//        println!("{:?}", self.part2_impl(&mut *input(), 10007, 3, 4113));
+    fn version(&self) -> u32 { 2 }
+
+    fn generate(&self, rng: &mut Rng, size: Option<usize>) -> Option<String> {
+        Some(self.generate_impl(rng, size.unwrap_or(100)))
+    }
+}
+
+impl day::Puzzle for Day22 {
+    type Input = Vec<Technique>;
+
+    fn parse_input(&self, input: &mut dyn io::Read) -> error::Result<Vec<Technique>> {
+        self.parse(input)
+    }
+
+    fn part1(&self, techniques: &Vec<Technique>) -> day::Answer {
+        let (len, n) = (self.param("deck") as usize, self.param("card") as usize);
+        self.part1_impl(techniques, len, 1, n).into()
+    }
+
+    fn part2(&self, techniques: &Vec<Technique>) -> day::Answer {
+        let len = self.param("big-deck") as usize;
+        let (rep, n) = (self.param("shuffles") as usize, self.param("position") as usize);
+        self.part2_impl(techniques, len, rep, n).into()
     }
 }
 
 impl Day22 {
-    fn part1_impl(self: &Self, input: &mut dyn io::Read, len: usize, rep: usize, n: usize) -> BoxResult<usize> {
-        let reader = io::BufReader::new(input);
+    /// A shuffle of `size` techniques for the default decks, whose sizes are
+    /// primes larger than any cut or increment.
+    pub fn generate_impl(self: &Self, rng: &mut Rng, size: usize) -> String {
+        (0..size).map(|_| match rng.below(3) {
+            0 => String::from("deal into new stack\n"),
+            1 => format!("cut {}\n", rng.range(-10006, 10006)),
+            _ => format!("deal with increment {}\n", rng.range(2, 10006)),
+        }).collect()
+    }
+
+    /// The shuffle, one technique per line.
+    pub fn parse(self: &Self, input: &mut dyn io::Read) -> error::Result<Vec<Technique>> {
         lazy_static! {
             static ref CUT: Regex = Regex::new("cut (.+)").unwrap();
             static ref INCR: Regex = Regex::new("deal with increment (.+)").unwrap();
         }
+        let lines = parse::lines(input)?;
+        Ok(lines.iter().map(|l| {
+            if l.text == "deal into new stack" {
+                Ok(Technique::NewStack)
+            } else if let Some(cap) = CUT.captures(&l.text) {
+                Ok(Technique::Cut(l.capture(&cap, 1, "a number of cards")?))
+            } else if let Some(cap) = INCR.captures(&l.text) {
+                Ok(Technique::Increment(l.capture(&cap, 1, "an increment")?))
+            } else {
+                Err(l.expected("a technique"))
+            }
+        }).collect::<Result<_, _>>()?)
+    }
+
+    pub fn part1_impl(self: &Self, techniques: &[Technique], len: usize, rep: usize, n: usize)
+        -> error::Result<usize> {
 //        let mut deck: Vec<_> = (0..len).collect();
 //        let mut pos = n;
         let (mut f, mut o) = (1i64, 0);
         let mut trace = 115059975488624i64;
-        reader.lines().for_each(|l| {
-            let l = l.unwrap();
-            if l == String::from("deal into new stack") {
-//                eprintln!("new");
+        techniques.iter().for_each(|&t| {
+            if t == Technique::NewStack {
+                log::trace!("new");
 //                deck = deck.iter().map(|x| *x).rev().collect();
 //                pos = len - pos - 1;
                 f = (len as i64 - f) % len as i64;
                 o = len - o - 1;
-//                eprintln!("new: {} -> {}", trace, 119315717514047i64 - 1 - trace);
+                log::trace!("new: {} -> {}", trace, 119315717514047i64 - 1 - trace);
                 trace = 119315717514047i64 - 1 - trace;
             } else {
-                if let Some(cap) = CUT.captures(&l) {
-//                    eprintln!("cut {:?}", cap);
-                    let cut0: i64 = cap.get(1).unwrap().as_str().parse().unwrap();
-                    let cut = if cut0 < 0 { (len as i64 + cut0) } else { cut0 } as usize;
+                if let Technique::Cut(cut0) = t {
+                    let cut = cut0.rem_euclid(len as i64) as usize;
 //                    let mut new = vec![0; len];
                     let rest = len - cut;
 //                    (&mut new[..rest]).clone_from_slice(&deck[cut..]);
@@ -266,11 +328,9 @@ impl Day22 {
                     let cut = if cut0 < 0 { (119315717514047i64 + cut0) } else { cut0 } as usize;
                     trace -= cut as i64;
                     if trace < 0 { trace += 119315717514047i64 }
-//                    eprintln!("cut {}: {} -> {}", cut0, otrace, trace);
-                } else if let Some(cap) = INCR.captures(&l) {
-//                    eprintln!("incr {:?}", cap);
+                    log::trace!("cut {}: {} -> {}", cut0, otrace, trace);
+                } else if let Technique::Increment(inc) = t {
 //                    let mut new = vec![0; len];
-                    let inc: usize = cap.get(1).unwrap().as_str().parse().unwrap();
 //                    for i in 0..len {
 //                        new[(i * inc) % len] = deck[i];
 //                    }
@@ -278,107 +338,81 @@ impl Day22 {
 //                    pos = (pos * inc) % len;
                     f = (f * inc as i64) % len as i64;
                     o = (o * inc) % len;
-//                    eprintln!("inc {}: {} -> {}", inc, trace, trace * inc as i64 % 119315717514047i64);
+                    log::trace!("inc {}: {} -> {}", inc, trace, trace * inc as i64 % 119315717514047i64);
                     trace = trace * inc as i64 % 119315717514047i64;
                 }
             }
-//            eprintln!("f {} o {}", f, o);
+            log::trace!("f {} o {}", f, o);
         });
-        eprintln!("trace {}", trace);
+        log::debug!("trace {}", trace);
 //        eprintln!("deck {:?}", deck);
 //        Ok(deck.iter().position(|&x| x == n).unwrap())
         let f = f as i128;
         let o = o as i128;
         let len = len as i128;
         let r = ((n as i128 * f) % len + len + o) % len;
-        eprintln!("f {} o {} -> {}", f, o, r);
+        log::debug!("f {} o {} -> {}", f, o, r);
 //        Ok(pos)
-        // An invariant over the shuffle
-        let x0 = (modinverse(len + 1 - f, len).unwrap() * o) % len;
-        // Check the invariant
-        let x1 = (x0 * f + o) % len;
-        eprintln!("x0 {} x1 {}", x0, x1);
-        let nf = power(f as i64, rep as i64, len as usize) as i128;
-        let no = (len + 1 - nf) * x0 % len;
-        eprintln!("f {} o {} nf {} no {}", f, o, nf, no);
-        // Check the invariant over all shuffles
-        let x2 = (x0 * nf + no) % len;
-        eprintln!("x2 {}", x2);
+        let (nf, no) = repeat(f, o, rep, len);
+        log::debug!("f {} o {} nf {} no {}", f, o, nf, no);
         let r = (n as i128 * nf + no) % len;
         Ok(r as usize)
     }
 
-    fn part2_impl(self: &Self, input: &mut dyn io::Read, len: usize, rep: usize, n: usize) -> BoxResult<usize> {
-        let reader = io::BufReader::new(input);
-        lazy_static! {
-            static ref CUT: Regex = Regex::new("cut (.+)").unwrap();
-            static ref INCR: Regex = Regex::new("deal with increment (.+)").unwrap();
-        }
+    pub fn part2_impl(self: &Self, techniques: &[Technique], len: usize, rep: usize, n: usize)
+        -> error::Result<usize> {
         let (mut f, mut o) = (1i64, 0);
-        reader.lines().collect::<Vec<_>>().into_iter().rev().for_each(|l| {
-            let l = l.unwrap();
-            if l == String::from("deal into new stack") {
-//                eprintln!("new");
+        for &t in techniques.iter().rev() {
+            if t == Technique::NewStack {
+                log::trace!("new");
                 f = (len as i64 - f) % len as i64;
                 o = len - o - 1;
             } else {
-                if let Some(cap) = CUT.captures(&l) {
-//                    eprintln!("cut {:?}", cap);
-                    let cut: i64 = cap.get(1).unwrap().as_str().parse().unwrap();
-                    let cut = if cut < 0 { (len as i64 + cut) } else { cut } as usize;
-                    let rest = len - cut;
+                if let Technique::Cut(cut) = t {
+                    let cut = cut.rem_euclid(len as i64) as usize;
                     o = (o + cut) % len;
-                } else if let Some(cap) = INCR.captures(&l) {
-//                    eprintln!("incr {:?}", cap);
-                    let inc: usize = cap.get(1).unwrap().as_str().parse().unwrap();
-                    f = ((f as i128 * modinverse(inc as i64, len as i64).unwrap() as i128) % len as i128) as i64;
-                    o = ((o as u128 * modinverse(inc as i64, len as i64).unwrap() as u128) % len as u128) as usize;
+                } else if let Technique::Increment(inc) = t {
+                    let inv = modinverse(inc as i64, len as i64).ok_or_else(|| Error::no_solution(
+                        &format!("increment {} does not shuffle a deck of {}", inc, len)))?;
+                    f = ((f as i128 * inv as i128) % len as i128) as i64;
+                    o = ((o as u128 * inv as u128) % len as u128) as usize;
                 }
             }
-//            eprintln!("f {} o {}", f, o);
-        });
-        let f = f as i128;
-        let o = o as i128;
+            log::trace!("f {} o {}", f, o);
+        }
         let len = len as i128;
-        // An invariant over the shuffle
-        let x0 = (modinverse(len + 1 - f, len).unwrap() * o) % len;
-        // Check the invariant
-        let x1 = (x0 * f + o) % len;
-        eprintln!("x0 {} x1 {}", x0, x1);
-        // Compute the parameters for the repeated shuffle
-        let nf = power(f as i64, rep as i64, len as usize) as i128;
-        let no = (len + 1 - nf) * x0 % len;
-        eprintln!("f {} o {} nf {} no {}", f, o, nf, no);
-        // Check the invariant over the repeated shuffle
-        let x2 = (x0 * nf + no) % len;
-        eprintln!("x2 {}", x2);
+        let (nf, no) = repeat(f as i128, o as i128, rep, len);
+        log::debug!("f {} o {} nf {} no {}", f, o, nf, no);
         let r = (n as i128 * nf + no) % len;
         Ok(r as usize)
     }
 }
 
-fn power(a: i64, b: i64, m: usize) -> i64 {
-    let m = m as u64;
-    let mut r = 1.to_modulo(m);
-    let mut b = b;
-    let mut a = a.to_modulo(m);
-    while b > 0 {
-        if b & 1 != 0 {
-            r = r * a;
-        };
-        b = b >> 1;
-        a = a * a;
+/// The shuffle `x -> f * x + o` done `n` times, by squaring, which unlike the
+/// closed form needs no inverse and so works for any size of deck.
+fn repeat(f: i128, o: i128, n: usize, len: i128) -> (i128, i128) {
+    let (mut rf, mut ro) = (1 % len, 0);
+    let (mut f, mut o, mut n) = (f % len, o % len, n);
+    while n > 0 {
+        if n & 1 != 0 {
+            rf = rf * f % len;
+            ro = (ro * f + o) % len;
+        }
+        o = (o * f + o) % len;
+        f = f * f % len;
+        n >>= 1;
     }
-    r.remainder()
+    (rf, ro)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::generate;
 
     fn test1(s: &str, len: usize, n: usize, rep: usize, x: usize) {
         assert_eq!(
-            Day22 {}.part1_impl(&mut s.as_bytes(), len, rep, n).unwrap(),
+            Day22 {}.part1_impl(&Day22 {}.parse(&mut s.as_bytes()).unwrap(), len, rep, n).unwrap(),
             x);
     }
 
@@ -407,7 +441,7 @@ cut -1
 
     fn test2(s: &str, len: usize, n: usize, rep: usize, x: usize) {
         assert_eq!(
-            Day22 {}.part2_impl(&mut s.as_bytes(), len, rep, n).unwrap(),
+            Day22 {}.part2_impl(&Day22 {}.parse(&mut s.as_bytes()).unwrap(), len, rep, n).unwrap(),
             x);
     }
 
@@ -432,5 +466,41 @@ deal with increment 9
 deal with increment 3
 cut -1
 ", 10, 1, 1, 2);
+        let techniques = Day22 {}.parse(&mut "deal with increment 4\n".as_bytes()).unwrap();
+        assert!(Day22 {}.part2_impl(&techniques, 10, 1, 0).is_err());
+    }
+
+    /// The deck after the shuffle, done card by card.
+    fn shuffle(techniques: &[Technique], deck: Vec<usize>) -> Vec<usize> {
+        techniques.iter().fold(deck, |deck, &t| {
+            let len = deck.len();
+            match t {
+                Technique::NewStack => deck.into_iter().rev().collect(),
+                Technique::Cut(cut) => {
+                    let cut = cut.rem_euclid(len as i64) as usize;
+                    [&deck[cut..], &deck[..cut]].concat()
+                },
+                Technique::Increment(inc) => {
+                    let mut new = vec![0; len];
+                    for (i, &card) in deck.iter().enumerate() { new[i * inc % len] = card; }
+                    new
+                },
+            }
+        })
+    }
+
+    #[test]
+    fn properties() {
+        let day = Day22 {};
+        generate::check(10, |rng| {
+            let techniques = day.parse(&mut day.generate_impl(rng, 20).as_bytes()).unwrap();
+            let (len, rep) = (10007, rng.range(1, 3) as usize);
+            let deck = (0..rep).fold((0..len).collect(), |deck, _| shuffle(&techniques, deck));
+            let card = rng.below(len);
+            assert_eq!(day.part1_impl(&techniques, len, rep, card).unwrap(),
+                       deck.iter().position(|&c| c == card).unwrap());
+            let position = rng.below(len);
+            assert_eq!(day.part2_impl(&techniques, len, rep, position).unwrap(), deck[position]);
+        });
     }
 }
@@ -1,15 +1,11 @@
-use simple_error::bail;
 use closure::closure;
 use evmap;
-use std::error;
 use std::io;
-use std::io::BufRead;
 use std::sync::{Arc, Mutex};
 use std::sync::mpsc;
-use std::thread;
 use crate::day;
-
-pub type BoxResult<T> = Result<T, Box<dyn error::Error>>;
+use crate::error::{self, Error, Fault};
+use crate::parse;
 
 struct Intcode {
     p: Vec<i64>,
@@ -22,113 +18,114 @@ impl Intcode {
     fn op(&self, c: i64) -> i64 { c % 100 }
 
     fn get(&mut self, a: usize) -> i64 {
-//        eprintln!("get {}", a);
+        log::trace!("get {}", a);
         if a >= self.p.len() { self.p.resize(a + 1, 0); }
         self.p[a]
     }
 
     fn put(&mut self, a: usize, v: i64) {
-//        eprintln!("put @{} {}", a, v);
+        log::trace!("put @{} {}", a, v);
         if a >= self.p.len() { self.p.resize(a + 1, 0); }
         self.p[a] = v;
     }
 
-    fn addr(&mut self, ip: usize, i: usize) -> usize {
+    fn addr(&mut self, ip: usize, i: usize) -> error::Result<usize> {
         let a = self.get(ip + i);
         let v = match self.get(ip) / vec![100, 1000, 10000][i - 1] % 10 {
             0 => a as usize,
             2 => (a  + self.base) as usize,
-            _ => 0, // XXX
+            mode => return Err(Error::intcode(ip, Fault::BadMode(mode))),
         };
-        v
+        Ok(v)
     }
 
-    fn val(&mut self, ip: usize, i: usize) -> i64 {
+    fn val(&mut self, ip: usize, i: usize) -> error::Result<i64> {
         let a = self.get(ip + i);
         let v = match self.get(ip) / vec![100, 1000, 10000][i - 1] % 10 {
             1 => a,
             _ => {
-                let addr = self.addr(ip, i);
+                let addr = self.addr(ip, i)?;
                 self.get(addr)
             },
         };
-//        eprintln!("{} {} {} {} {} {}", ip, self.get(ip), i, a, self.base, v);
-        v
+        log::trace!("{} {} {} {} {} {}", ip, self.get(ip), i, a, self.base, v);
+        Ok(v)
     }
 
     fn run(&mut self, sender: &mpsc::Sender<i64>, receiver: &mpsc::Receiver<i64>,
-        request: &mpsc::Sender<()>, ack: &mpsc::Receiver<()>) -> BoxResult<i64> {
+        request: &mpsc::Sender<()>, ack: &mpsc::Receiver<()>) -> error::Result<i64> {
         let mut ip = 0;
         let mut o = None;
+        let cancel = day::Cancel::current();
         while { let op = self.get(ip); self.op(op) != 99 } {
-//            eprintln!("{}: {} {} {} {}", ip, self.p[ip], self.p[ip + 1], self.p[ip + 2], self.p[ip + 3]);
+            cancel.check()?;
+            log::trace!("{}: {} {} {} {}", ip, self.p[ip], self.p[ip + 1], self.p[ip + 2], self.p[ip + 3]);
             match self.op(self.p[ip]) {
                 1 => {
-                    let a = self.val(ip, 1);
-                    let b = self.val( ip, 2);
-                    let c = self.addr(ip, 3);
+                    let a = self.val(ip, 1)?;
+                    let b = self.val(ip, 2)?;
+                    let c = self.addr(ip, 3)?;
                     self.put(c, a + b);
                     ip += 4;
                 },
                 2 => {
-                    let a = self.val(ip, 1);
-                    let b = self.val(ip, 2);
-                    let c = self.addr(ip, 3);
+                    let a = self.val(ip, 1)?;
+                    let b = self.val(ip, 2)?;
+                    let c = self.addr(ip, 3)?;
                     self.put(c, a * b);
                     ip += 4;
                 },
                 3 => {
-                    let a = self.addr(ip, 1);
-//                    eprintln!(">recv {}", a);
+                    let a = self.addr(ip, 1)?;
+                    log::trace!(">recv {}", a);
                     request.send(())?;
-                    self.put(a as usize, receiver.recv()?);
-//                    eprintln!("<recv {}", self.get(a as usize));
+                    self.put(a as usize, day::recv(receiver)?);
+                    log::trace!("<recv {}", self.get(a));
                     ip += 2;
                 },
                 4 => {
-                    let a = self.val(ip, 1);
+                    let a = self.val(ip, 1)?;
                     o = Some(a);
-//                    eprintln!(">send {}", a);
+                    log::trace!(">send {}", a);
                     sender.send(a)?;
-//                    eprintln!("<send");
-                    ack.recv()?;
-//                    eprintln!("<<send");
+                    log::trace!("<send");
+                    day::recv(ack)?;
+                    log::trace!("<<send");
                     ip += 2;
                 },
                 5 => {
-                    let a = self.val(ip, 1);
-                    let b = self.val(ip, 2) as usize;
+                    let a = self.val(ip, 1)?;
+                    let b = self.val(ip, 2)? as usize;
                     ip = if a != 0 { b } else { ip + 3 };
                 },
                 6 => {
-                    let a = self.val(ip, 1);
-                    let b = self.val(ip, 2) as usize;
+                    let a = self.val(ip, 1)?;
+                    let b = self.val(ip, 2)? as usize;
                     ip = if a == 0 { b } else { ip + 3 };
                 },
                 7 => {
-                    let a = self.val(ip, 1);
-                    let b = self.val(ip, 2);
-                    let c = self.addr(ip, 3);
+                    let a = self.val(ip, 1)?;
+                    let b = self.val(ip, 2)?;
+                    let c = self.addr(ip, 3)?;
                     self.put(c, if a < b { 1 } else { 0 });
                     ip += 4;
                 },
                 8 => {
-                    let a = self.val(ip, 1);
-                    let b = self.val(ip, 2);
-                    let c = self.addr(ip,3);
+                    let a = self.val(ip, 1)?;
+                    let b = self.val(ip, 2)?;
+                    let c = self.addr(ip, 3)?;
                     self.put(c, if a == b { 1 } else { 0 });
                     ip += 4;
                 },
                 9 => {
-                    self.base += self.val(ip, 1);
-//                    eprintln!("<base {}", self.base);
+                    self.base += self.val(ip, 1)?;
+                    log::trace!("<base {}", self.base);
                     ip += 2;
                 }
-                _ => bail!("unknown opcode {}: {}", ip, self.op(self.p[ip])),
+                op => return Err(Error::intcode(ip, Fault::UnknownOpcode(op))),
             };
         }
-        if o.is_none() { bail!("no output"); }
-        Ok(o.unwrap())
+        o.ok_or_else(|| Error::intcode(ip, Fault::NoOutput))
     }
 
     #[allow(dead_code)]
@@ -198,31 +195,43 @@ impl Intcode {
 
 pub struct Day23 {}
 
+inventory::submit! { day::Registration(&Day23 {}) }
+
 impl day::Day for Day23 {
     fn tag(&self) -> &str { "23" }
 
-    fn part1(&self, input: &dyn Fn() -> Box<dyn io::Read>) {
-        let reader = io::BufReader::new(input());
-        let p = reader.split(b',')
-            .map(|v| String::from_utf8(v.unwrap()).unwrap())
-            .map(|s| s.trim_end().parse::<i64>().unwrap())
-            .collect::<Vec<_>>();
-        println!("{:?}", self.part2_impl(p));
+    fn title(&self) -> &str { "Category Six" }
+
+    fn parts(&self) -> [&str; 2] {
+        ["Y of the first packet sent to address 255",
+         "first Y delivered by the NAT twice in a row"]
     }
+}
+
+impl day::Puzzle for Day23 {
+    type Input = Vec<i64>;
 
-    fn part2(&self, input: &dyn Fn() -> Box<dyn io::Read>) {
-        let reader = io::BufReader::new(input());
-        let p = reader.split(b',')
-            .map(|v| String::from_utf8(v.unwrap()).unwrap())
-            .map(|s| s.trim_end().parse::<i64>().unwrap())
-            .collect::<Vec<_>>();
-//        println!("{:?}", self.part1_impl(p, "NOT A J\nNOT B T\nAND D T\nAND H T\nOR T J\nNOT C T\nAND D T\nAND H T\nOR T J\nRUN\n"));
+    fn parse_input(&self, input: &mut dyn io::Read) -> error::Result<Vec<i64>> {
+        self.parse(input)
+    }
+
+    fn part1(&self, p: &Vec<i64>) -> day::Answer {
+        self.part1_impl(p).into()
+    }
+
+    fn part2(&self, p: &Vec<i64>) -> day::Answer {
+        self.part2_impl(p).into()
     }
 }
 
 impl Day23 {
-    fn part1_impl(self: &Self, p: Vec<i64>)
-        -> BoxResult<i64> {
+    /// The Intcode program.
+    pub fn parse(self: &Self, input: &mut dyn io::Read) -> error::Result<Vec<i64>> {
+        parse::numbers(input, "an integer")
+    }
+
+    pub fn part1_impl(self: &Self, p: &[i64])
+        -> error::Result<i64> {
         let config: Vec<_> = (0i64..50).map(|cpu| {
             let (input_sender, input_receiver) = mpsc::channel::<i64>();
             let (output_sender, output_receiver) = mpsc::channel::<i64>();
@@ -230,24 +239,26 @@ impl Day23 {
             let (ack_sender, ack_receiver) = mpsc::channel::<()>();
             let (start_sender, start_receiver) = mpsc::channel::<()>();
             let p = p.to_vec();
-            let thread = thread::spawn(closure!(|| {
+            let thread = day::spawn(closure!(|| {
                 while start_receiver.recv().is_ok() {
-                    eprintln!("starting {}", cpu);
+                    log::debug!("starting {}", cpu);
                     let mut ic = Intcode::new(&p);
-                    ic.run(&output_sender, &input_receiver, &request_sender, &ack_receiver)
-                        .unwrap_or(0);
-//                eprintln!("cpu stopped");
+                    ic.run(&output_sender, &input_receiver, &request_sender, &ack_receiver)?;
+                log::trace!("cpu stopped");
                 }
-//            eprintln!("really stopped");
+            log::trace!("really stopped");
+            Ok(())
             }));
             start_sender.send(()).unwrap();
-            request_receiver.recv().unwrap();
-            eprintln!("{} started, configuring...", cpu);
-            input_sender.send(cpu).unwrap();
+            // A machine that stops before it asks for its address has its relay say why.
+            if request_receiver.recv().is_ok() {
+                log::debug!("{} started, configuring...", cpu);
+                let _ = input_sender.send(cpu);
+            }
             (cpu, thread, request_receiver, input_sender, output_receiver, ack_sender)
         }).collect();
 
-        let (mux_in_sender, mux_in_receiver) = mpsc::channel::<(i64, i64, i64)>();
+        let (mux_in_sender, mux_in_receiver) = mpsc::channel::<error::Result<(i64, i64, i64)>>();
 
         let config: Vec<_> = config.into_iter().map(closure!(ref mux_in_sender
             |(cpu, thread, request_receiver, input_sender, output_receiver,
@@ -255,60 +266,62 @@ impl Day23 {
                 let mux_in_sender = mux_in_sender.clone();
                 let (mux_out_sender, mux_out_receiver) = mpsc::channel::<(i64, i64)>();
                 let input_sender_clone = input_sender.clone();
-                thread::spawn(move || {
-                    loop {
-                        eprintln!("awaiting output from {}", cpu);
-                        let dst = output_receiver.recv().unwrap();
-                        ack_sender.send(()).unwrap();
-                        eprintln!("{} wants to send to {}", cpu, dst);
-                        let x = output_receiver.recv().unwrap();
-                        ack_sender.send(()).unwrap();
-                        eprintln!("{} got {}", cpu, x);
-                        let y = output_receiver.recv().unwrap();
-                        ack_sender.send(()).unwrap();
-                        eprintln!("{} is sending ({}, {}) to {}", cpu, x, y, dst);
-                        mux_in_sender.send((dst, x, y));
+                day::spawn(move || {
+                    let relay = || loop {
+                        log::trace!("awaiting output from {}", cpu);
+                        let dst = day::recv(&output_receiver)?;
+                        ack_sender.send(())?;
+                        log::trace!("{} wants to send to {}", cpu, dst);
+                        let x = day::recv(&output_receiver)?;
+                        ack_sender.send(())?;
+                        log::trace!("{} got {}", cpu, x);
+                        let y = day::recv(&output_receiver)?;
+                        ack_sender.send(())?;
+                        log::trace!("{} is sending ({}, {}) to {}", cpu, x, y, dst);
+                        if mux_in_sender.send(Ok((dst, x, y))).is_err() { return Ok(()); }
+                    };
+                    if let Err(e) = day::or_join(relay(), thread) {
+                        let _ = mux_in_sender.send(Err(e));
                     }
                 });
-                thread::spawn(move || {
+                day::spawn(move || -> error::Result<()> {
                     loop {
-//                        eprintln!("{} is polling", cpu);
-                        request_receiver.recv().unwrap();
+                        log::trace!("{} is polling", cpu);
+                        day::recv(&request_receiver)?;
                         match mux_out_receiver.try_recv() {
                             Ok((x, y)) => {
-                                input_sender.send(x);
-                                request_receiver.recv().unwrap();
-                                input_sender.send(y);
+                                input_sender.send(x)?;
+                                day::recv(&request_receiver)?;
+                                input_sender.send(y)?;
                             },
                             _ => {
-//                                eprintln!("{} got nothing", cpu);
-                                input_sender_clone.send(-1).unwrap();
+                                log::trace!("{} got nothing", cpu);
+                                input_sender_clone.send(-1)?;
                             },
                         };
                     }
                 });
-                (cpu, thread, mux_out_sender)
+                (cpu, mux_out_sender)
             })).collect();
 
         let mut r = 0;
         loop {
-            let (dst, x, y) = mux_in_receiver.recv().unwrap();
+            let (dst, x, y) = day::recv(&mux_in_receiver)??;
             if dst != 255 {
-                let (_, _, mux_out_sender) = &config[dst as usize];
+                let (_, mux_out_sender) = &config[dst as usize];
                 mux_out_sender.send((x, y));
-                eprintln!("sent ({}, {}) to {}", x, y, dst);
+                log::debug!("sent ({}, {}) to {}", x, y, dst);
             } else {
                 r = y;
                 break;
             }
         }
-        for (_, thread, _) in config.into_iter() { thread.join(); }
 
         Ok(r)
     }
 
-    fn part2_impl(self: &Self, p: Vec<i64>)
-                  -> BoxResult<i64> {
+    pub fn part2_impl(self: &Self, p: &[i64])
+                  -> error::Result<i64> {
         let config: Vec<_> = (0i64..50).map(|cpu| {
             let (input_sender, input_receiver) = mpsc::channel::<i64>();
             let (output_sender, output_receiver) = mpsc::channel::<i64>();
@@ -316,24 +329,26 @@ impl Day23 {
             let (ack_sender, ack_receiver) = mpsc::channel::<()>();
             let (start_sender, start_receiver) = mpsc::channel::<()>();
             let p = p.to_vec();
-            let thread = thread::spawn(closure!(|| {
+            let thread = day::spawn(closure!(|| {
                 while start_receiver.recv().is_ok() {
-                    eprintln!("starting {}", cpu);
+                    log::debug!("starting {}", cpu);
                     let mut ic = Intcode::new(&p);
-                    ic.run(&output_sender, &input_receiver, &request_sender, &ack_receiver)
-                        .unwrap_or(0);
-//                eprintln!("cpu stopped");
+                    ic.run(&output_sender, &input_receiver, &request_sender, &ack_receiver)?;
+                log::trace!("cpu stopped");
                 }
-//            eprintln!("really stopped");
+            log::trace!("really stopped");
+            Ok(())
             }));
             start_sender.send(()).unwrap();
-            request_receiver.recv().unwrap();
-            eprintln!("{} started, configuring...", cpu);
-            input_sender.send(cpu).unwrap();
+            // A machine that stops before it asks for its address has its relay say why.
+            if request_receiver.recv().is_ok() {
+                log::debug!("{} started, configuring...", cpu);
+                let _ = input_sender.send(cpu);
+            }
             (cpu, thread, request_receiver, input_sender, output_receiver, ack_sender)
         }).collect();
 
-        let (mux_in_sender, mux_in_receiver) = mpsc::channel::<(i64, i64, i64)>();
+        let (mux_in_sender, mux_in_receiver) = mpsc::channel::<error::Result<(i64, i64, i64)>>();
         let mut idle = Arc::new(Mutex::new(0i64));
 
         let config: Vec<_> = config.into_iter().map(closure!(ref mux_in_sender, ref mut idle
@@ -341,94 +356,103 @@ impl Day23 {
                  ack_sender)| {
                 let mux_in_sender = mux_in_sender.clone();
                 let (mux_out_sender, mux_out_receiver) = mpsc::channel::<(i64, i64)>();
-                thread::spawn(move || {
-                    loop {
-//                        eprintln!("awaiting output from {}", cpu);
-                        let dst = output_receiver.recv().unwrap();
-                        ack_sender.send(()).unwrap();
-//                        eprintln!("{} wants to send to {}", cpu, dst);
-                        let x = output_receiver.recv().unwrap();
-                        ack_sender.send(()).unwrap();
-//                        eprintln!("{} got {}", cpu, x);
-                        let y = output_receiver.recv().unwrap();
-                        ack_sender.send(()).unwrap();
-//                        eprintln!("{} is sending ({}, {}) to {}", cpu, x, y, dst);
-                        mux_in_sender.send((dst, x, y));
+                day::spawn(move || {
+                    let relay = || loop {
+                        log::trace!("awaiting output from {}", cpu);
+                        let dst = day::recv(&output_receiver)?;
+                        ack_sender.send(())?;
+                        log::trace!("{} wants to send to {}", cpu, dst);
+                        let x = day::recv(&output_receiver)?;
+                        ack_sender.send(())?;
+                        log::trace!("{} got {}", cpu, x);
+                        let y = day::recv(&output_receiver)?;
+                        ack_sender.send(())?;
+                        log::trace!("{} is sending ({}, {}) to {}", cpu, x, y, dst);
+                        if mux_in_sender.send(Ok((dst, x, y))).is_err() { return Ok(()); }
+                    };
+                    if let Err(e) = day::or_join(relay(), thread) {
+                        let _ = mux_in_sender.send(Err(e));
                     }
                 });
                 let idle = Arc::clone(&idle);
-                thread::spawn(closure!(|| {
+                day::spawn(closure!(|| -> error::Result<()> {
                 let mut idle_counter = 0;
                     loop {
-//                        eprintln!("{} is polling", cpu);
-                        request_receiver.recv().unwrap();
+                        log::trace!("{} is polling", cpu);
+                        day::recv(&request_receiver)?;
                         match mux_out_receiver.try_recv() {
                             Ok((x, y)) => {
-                                input_sender.send(x);
-                                request_receiver.recv().unwrap();
-                                input_sender.send(y);
+                                input_sender.send(x)?;
+                                day::recv(&request_receiver)?;
+                                input_sender.send(y)?;
                                 *idle.lock().unwrap() &= !(1 << cpu);
                                 idle_counter = 0;
                             },
                             _ => {
-//                                eprintln!("{} got nothing", cpu);
-                                input_sender.send(-1).unwrap();
+                                log::trace!("{} got nothing", cpu);
+                                input_sender.send(-1)?;
                                 if idle_counter == 200000 {
                                     let mut idle = idle.lock().unwrap();
                                     *idle |= 1 << cpu;
-                                    eprintln!("idling! mask: {:b}", *idle);
+                                    log::debug!("idling! mask: {:b}", *idle);
                                 };
                                 idle_counter += 1;
                             },
                         };
                     }
                 }));
-                (cpu, thread, mux_out_sender)
+                (cpu, mux_out_sender)
             })).collect();
 
         let (nat_sender, nat_receiver) = mpsc::channel::<(i64, i64)>();
-        let (_, _, mux_out_sender_0) = &config[0];
+        let (result_sender, result_receiver) = mpsc::channel::<i64>();
+        let (_, mux_out_sender_0) = &config[0];
         let mux_out_sender_0 = mux_out_sender_0.clone();
         let idle = Arc::clone(&idle);
-        thread::spawn(move || {
+        day::spawn(move || {
+            let cancel = day::Cancel::current();
             let mut last = None;
             let mut last_last = None;
             let mut wait_for_unidle = 0;
-            loop {
+            while !cancel.is_cancelled() {
                 let d = nat_receiver.try_recv();
                 if wait_for_unidle == 0 && *idle.lock().unwrap() == 0x3ffffffffffff && last != None {
-                    eprintln!("all idle, sending {:?} to 0", last);
+                    log::debug!("all idle, sending {:?} to 0", last);
                     mux_out_sender_0.send(last.unwrap());
                     if last == last_last {
-                        println!("TWICE in a row: {:?}", last);
+                        result_sender.send(last.unwrap().1).unwrap();
+                        break;
                     };
                     last_last = last;
                     wait_for_unidle = 500000;
                 } else {
                     if wait_for_unidle > 0 {
-//                        eprintln!("unidle {}", wait_for_unidle);
+                        log::trace!("unidle {}", wait_for_unidle);
                         wait_for_unidle -= 1;
                     }
                 }
                 if d.is_ok() {
-                    eprintln!("nat received {:?}", d);
+                    log::debug!("nat received {:?}", d);
                     last = Some(d.unwrap());
                 }
             }
         });
 
-        let mut r = 0;
+        let r;
         loop {
-            let (dst, x, y) = mux_in_receiver.recv().unwrap();
+            if let Ok(y) = result_receiver.try_recv() {
+                r = y;
+                break;
+            }
+            let (dst, x, y) = day::recv(&mux_in_receiver)??;
             if dst != 255 {
-                let (_, _, mux_out_sender) = &config[dst as usize];
+                let (_, mux_out_sender) = &config[dst as usize];
                 mux_out_sender.send((x, y));
-//                eprintln!("sent ({}, {}) to {}", x, y, dst);
+                log::trace!("sent ({}, {}) to {}", x, y, dst);
             } else {
                 nat_sender.send((x, y));
             }
         }
-//        for (_, thread, _) in config.into_iter() { thread.join(); }
 
         Ok(r)
     }
@@ -2,10 +2,11 @@ use itertools::Itertools;
 use itertools::FoldWhile::{Continue, Done};
 //use modular::*;
 use std::collections::{HashMap, HashSet};
-use std::error;
 use std::io;
-use std::io::Read;
-use crate::day;
+use crate::day::{self, Day};
+use crate::error;
+use crate::generate::Rng;
+use crate::parse;
 
 use std::fmt;
 use std::ops::{Add, Mul, Sub};
@@ -207,28 +208,70 @@ impl fmt::Display for Modulo {
 //    }
 //}
 
-pub type BoxResult<T> = Result<T, Box<dyn error::Error>>;
-
 pub struct Day24 {}
 
+inventory::submit! { day::Registration(&Day24 {}) }
+
 impl day::Day for Day24 {
     fn tag(&self) -> &str { "24" }
 
-    fn part1(&self, input: &dyn Fn() -> Box<dyn io::Read>) {
-        println!("{:?}", self.part1_impl(&mut *input()));
+    fn title(&self) -> &str { "Planet of Discord" }
+
+    fn parts(&self) -> [&str; 2] {
+        ["biodiversity rating of the first repeated layout",
+         "bugs present after the minutes"]
+    }
+
+    fn params(&self) -> &[day::Param] {
+        &[day::Param { name: "minutes", default: 200, min: 0, max: i64::MAX,
+                      help: "minutes to simulate in part 2" }]
+    }
+
+    fn generate(&self, rng: &mut Rng, size: Option<usize>) -> Option<String> {
+        Some(self.generate_impl(rng, size.unwrap_or(8)))
+    }
+}
+
+impl day::Puzzle for Day24 {
+    type Input = Vec<u8>;
+
+    fn parse_input(&self, input: &mut dyn io::Read) -> error::Result<Vec<u8>> {
+        self.parse(input)
     }
 
-    fn part2(&self, input: &dyn Fn() -> Box<dyn io::Read>) {
-        println!("{:?}", self.part2_impl(&mut *input(), 200));
+    fn part1(&self, bugs: &Vec<u8>) -> day::Answer {
+        self.part1_impl(bugs).into()
+    }
+
+    fn part2(&self, bugs: &Vec<u8>) -> day::Answer {
+        let minutes = self.param("minutes") as usize;
+        self.part2_impl(bugs, minutes).into()
     }
 }
 
 impl Day24 {
-    fn part1_impl(self: &Self, input: &mut dyn io::Read) -> BoxResult<usize> {
-        let reader = io::BufReader::new(input);
-        let init: Vec<_> = reader.bytes()
-            .map(|b| match b.unwrap() as char { '.' => 0, '#' => 1, _ => 2, })
-            .filter(|&b| b != 2).collect();
+    /// A grid with `size` bugs (at most 24), none in the middle, where part 2
+    /// has the smaller grid.
+    pub fn generate_impl(self: &Self, rng: &mut Rng, size: usize) -> String {
+        let mut tiles = vec!['.'; 24];
+        tiles.iter_mut().take(size.min(24)).for_each(|t| *t = '#');
+        rng.shuffle(&mut tiles);
+        tiles.insert(12, '.');
+        tiles.chunks(5).map(|row| row.iter().collect::<String>() + "\n").collect()
+    }
+
+    /// The grid, 1 for a bug and 0 for an empty tile, row after row.
+    pub fn parse(self: &Self, input: &mut dyn io::Read) -> error::Result<Vec<u8>> {
+        let mut bugs = vec![];
+        for l in parse::lines(input)? {
+            l.only("#.", "a bug (#) or an empty tile (.)")?;
+            bugs.extend(l.text.bytes().map(|b| (b == b'#') as u8));
+        }
+        Ok(bugs)
+    }
+
+    pub fn part1_impl(self: &Self, bugs: &[u8]) -> error::Result<usize> {
+        let init: Vec<_> = bugs.iter().map(|&b| b as usize).collect();
         let (s, _) = (0..)
             .fold_while((init, HashSet::new()),  |(s, seen), _| {
                 if seen.contains(&s) { Done((s, seen)) }
@@ -250,12 +293,9 @@ impl Day24 {
         Ok(s.into_iter().enumerate().map(|(i, b)| b << i).sum())
     }
 
-    fn part2_impl(self: &Self, input: &mut dyn io::Read, n: usize) -> BoxResult<usize> {
-        let reader = io::BufReader::new(input);
+    pub fn part2_impl(self: &Self, bugs: &[u8], n: usize) -> error::Result<usize> {
         let mut init = HashMap::new();
-        init.insert(0, reader.bytes()
-            .map(|b| match b.unwrap() as char { '.' => 0, '#' => 1, _ => 2, })
-            .filter(|&b| b != 2).collect::<Vec<_>>());
+        init.insert(0, bugs.iter().map(|&b| b as i32).collect::<Vec<_>>());
         let len = 25;
         let z = vec![0; len];
         let (s, _) = (0..n).fold((init, 0),  |(sp, dim), _| {
@@ -282,19 +322,19 @@ impl Day24 {
                 nsp.insert(d, ns);
             }
             let mut dim = dim;
-//            eprintln!("dim {} nsp {:?}", dim, nsp);
+            log::trace!("dim {} nsp {:?}", dim, nsp);
             if nsp.get(&-(dim + 1)) != Some(&z)
                 || nsp.get(&(dim + 1)) != Some(&z) {
                 dim += 1;
             };
             (nsp, dim)
         });
-//        eprintln!("{:?}", s);
+        log::trace!("{:?}", s);
         let v = s.iter();
         let m: Vec<i32> = v.map(|(d, v)| {
             let i = v.iter();
             let s = i.sum();
-//            eprintln!("{} {:?} {}", d, v, s);
+            log::trace!("{} {:?} {}", d, v, s);
             s
         }).collect();
         let s2: i32 = m.iter().sum();
@@ -306,10 +346,11 @@ impl Day24 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::generate;
 
     fn test1(s: &str, x: usize) {
         assert_eq!(
-            Day24 {}.part1_impl(&mut s.as_bytes()).unwrap(),
+            Day24 {}.part1_impl(&Day24 {}.parse(&mut s.as_bytes()).unwrap()).unwrap(),
             x);
     }
 
@@ -324,7 +365,7 @@ mod tests {
 
     fn test2(s: &str, x: usize) {
         assert_eq!(
-            Day24 {}.part2_impl(&mut s.as_bytes(), 10).unwrap(),
+            Day24 {}.part2_impl(&Day24 {}.parse(&mut s.as_bytes()).unwrap(), 10).unwrap(),
             x);
     }
 
@@ -336,4 +377,16 @@ mod tests {
 ..#..
 #....", 99);
     }
+
+    #[test]
+    fn properties() {
+        let day = Day24 {};
+        generate::check(20, |rng| {
+            let size = rng.below(25);
+            let bugs = day.parse(&mut day.generate_impl(rng, size).as_bytes()).unwrap();
+            assert_eq!(bugs.len(), 25);
+            assert_eq!(day.part2_impl(&bugs, 0).unwrap(), size.min(24));
+            assert!(day.part1_impl(&bugs).unwrap() < 1 << 25);
+        });
+    }
 }
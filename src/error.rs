@@ -0,0 +1,163 @@
+use std::error;
+use std::fmt;
+use std::io;
+use std::sync::mpsc;
+use std::time::Duration;
+use crate::day;
+use crate::parse::ParseError;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Where an error happened: the day and part running when it was made, and
+/// the instruction pointer of a faulting Intcode machine.
+///
+/// The part is unknown while the input is parsed, all of it outside of `day::run`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Context {
+    pub day: Option<String>,
+    pub part: Option<usize>,
+    pub ip: Option<usize>,
+}
+
+impl Context {
+    /// The context of the day running on this thread.
+    pub fn current() -> Self {
+        let (day, part) = day::running().map_or((None, None), |(day, part)| (Some(day), part));
+        Context { day, part, ip: None }
+    }
+
+    /// The context of the day running on this thread, at an instruction.
+    pub fn at(ip: usize) -> Self { Context { ip: Some(ip), ..Context::current() } }
+}
+
+impl fmt::Display for Context {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut parts = vec![];
+        if let Some(day) = &self.day { parts.push(format!("day {}", day)); }
+        if let Some(part) = self.part { parts.push(format!("part {}", part)); }
+        if let Some(ip) = self.ip { parts.push(format!("ip {}", ip)); }
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+/// What went wrong with an Intcode program.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Fault {
+    UnknownOpcode(i64),
+    BadMode(i64),
+    /// The other end of the machine's input or output went away.
+    ChannelClosed,
+    /// The program halted without any output.
+    NoOutput,
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Fault::UnknownOpcode(op) => write!(f, "unknown opcode {}", op),
+            Fault::BadMode(mode) => write!(f, "bad parameter mode {}", mode),
+            Fault::ChannelClosed => write!(f, "channel closed"),
+            Fault::NoOutput => write!(f, "no output"),
+        }
+    }
+}
+
+/// Why a day could not be solved.
+///
+/// The messages leave out the day and part, which whoever reports the error
+/// knows, but every kind keeps them in its context.
+#[derive(Debug)]
+pub enum Error {
+    Io(Context, io::Error),
+    Parse(Context, Box<ParseError>),
+    /// Input that parses, but that the day cannot solve from.
+    Input(Context, String),
+    Intcode(Context, Fault),
+    /// The puzzle has no answer for the input.
+    NoSolution(Context, String),
+    /// The part was cancelled, after the time it was given if it had a timeout.
+    Timeout(Context, Option<Duration>),
+    Panic(Context, String),
+}
+
+impl Error {
+    pub fn input(what: &str) -> Self { Error::Input(Context::current(), what.to_string()) }
+
+    pub fn intcode(ip: usize, fault: Fault) -> Self { Error::Intcode(Context::at(ip), fault) }
+
+    pub fn no_solution(what: &str) -> Self {
+        Error::NoSolution(Context::current(), what.to_string())
+    }
+
+    pub fn context(&self) -> &Context {
+        match self {
+            Error::Io(context, _) | Error::Parse(context, _) | Error::Input(context, _)
+            | Error::Intcode(context, _) | Error::NoSolution(context, _)
+            | Error::Timeout(context, _) | Error::Panic(context, _) => context,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(_, e) => write!(f, "{}", e),
+            Error::Parse(_, e) => write!(f, "{}", e),
+            Error::Input(_, what) | Error::NoSolution(_, what) => write!(f, "{}", what),
+            Error::Intcode(context, fault) => match context.ip {
+                Some(ip) => write!(f, "{} at {}", fault, ip),
+                None => write!(f, "{}", fault),
+            },
+            Error::Timeout(_, Some(timeout)) => write!(f, "timed out after {:?}", timeout),
+            Error::Timeout(_, None) => write!(f, "cancelled"),
+            Error::Panic(_, message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Io(_, e) => Some(e),
+            Error::Parse(_, e) => Some(e.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self { Error::Io(Context::current(), e) }
+}
+
+impl From<ParseError> for Error {
+    fn from(e: ParseError) -> Self { Error::Parse(Context::current(), Box::new(e)) }
+}
+
+impl<T> From<mpsc::SendError<T>> for Error {
+    fn from(_: mpsc::SendError<T>) -> Self {
+        Error::Intcode(Context::current(), Fault::ChannelClosed)
+    }
+}
+
+impl From<mpsc::RecvError> for Error {
+    fn from(_: mpsc::RecvError) -> Self {
+        Error::Intcode(Context::current(), Fault::ChannelClosed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages() {
+        let context = Context { day: Some(String::from("2019/09")), part: Some(2), ip: Some(7) };
+        assert_eq!(context.to_string(), "day 2019/09, part 2, ip 7");
+        assert_eq!(Error::Intcode(context, Fault::UnknownOpcode(42)).to_string(),
+                   "unknown opcode 42 at 7");
+        assert_eq!(Error::from(mpsc::RecvError).to_string(), "channel closed");
+        assert_eq!(Error::no_solution("no path").context(), &Context::default());
+        assert_eq!(Error::Timeout(Context::default(), Some(Duration::from_millis(5))).to_string(),
+                   "timed out after 5ms");
+    }
+}
@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+use std::io;
+use std::rc::Rc;
+use std::time::Duration;
+use crate::day::{self, Answer, Day};
+use crate::input::Source;
+use crate::verify;
+
+/// Where the examples are, in files like `2019/07.txt`.
+pub const DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/examples");
+
+/// An example from a puzzle's text: an input, the parameter values it needs
+/// and the answers it gives.
+///
+/// A day's examples are kept in a file of sections, an example starting with
+/// a `== <name>` line and its parts with `-- input`, `-- params` (one
+/// `<name>=<value>` per line), `-- part 1` and `-- part 2` lines, followed by
+/// the input, the values or the expected answer.  What comes before the first
+/// example is a comment, and blank lines ending a section are ignored.
+#[derive(Debug, Default, PartialEq)]
+pub struct Example {
+    pub name: String,
+    pub input: String,
+    pub params: HashMap<String, i64>,
+    pub expected: [Option<Vec<String>>; 2],
+}
+
+pub fn parse(s: &str) -> Result<Vec<Example>, String> {
+    let mut examples: Vec<Example> = vec![];
+    let mut section: Option<(usize, &str, Vec<&str>)> = None;
+    for (n, line) in s.lines().enumerate().chain(std::iter::once((usize::MAX, "== "))) {
+        let header = line.strip_prefix("== ").map(|name| (None, name))
+            .or_else(|| line.strip_prefix("-- ").map(|name| (Some(name.trim()), "")));
+        let (kind, name) = match header {
+            Some(header) => header,
+            None => {
+                if let Some((_, _, lines)) = &mut section { lines.push(line); }
+                continue;
+            },
+        };
+        if let Some((at, kind, mut lines)) = section.take() {
+            while lines.last().is_some_and(|line| line.trim().is_empty()) { lines.pop(); }
+            let example = examples.last_mut().ok_or_else(|| format!("line {}: no example", at))?;
+            match kind {
+                "input" => example.input = lines.iter().map(|line| format!("{}\n", line)).collect(),
+                "params" => for line in lines {
+                    let value = line.split_once('=')
+                        .and_then(|(name, value)| Some((name.trim(), value.trim().parse().ok()?)));
+                    let (name, value) = value.ok_or_else(||
+                        format!("line {}: expected \"<name>=<value>\", not \"{}\"", at, line))?;
+                    example.params.insert(name.to_string(), value);
+                },
+                "part 1" | "part 2" => {
+                    let part = if kind == "part 1" { 0 } else { 1 };
+                    example.expected[part] = Some(lines.iter().map(|l| l.to_string()).collect());
+                },
+                _ => return Err(format!("line {}: unknown section \"{}\"", at, kind)),
+            }
+        }
+        match kind {
+            Some(kind) => section = Some((n + 1, kind, vec![])),
+            None if n != usize::MAX =>
+                examples.push(Example { name: name.trim().to_string(), ..Example::default() }),
+            None => (),
+        }
+    }
+    Ok(examples)
+}
+
+/// The examples of a day from a directory of example files, none if it has no file.
+pub fn load(dir: &str, day: &dyn Day) -> Result<Vec<Example>, String> {
+    let input = match Source::Directory(dir.into()).input(day.year(), day.tag()) {
+        Ok(input) => input,
+        Err(_) => return Ok(vec![]),
+    };
+    let bytes = input.read().map_err(|e| format!("{}: {}", input.name, e))?;
+    let examples = parse(&String::from_utf8_lossy(&bytes))
+        .map_err(|e| format!("{}: {}", input.name, e))?;
+    for example in &examples {
+        for (name, &value) in &example.params {
+            day::check_param(day, name, value)
+                .map_err(|e| format!("{}: example {}: {}", input.name, example.name, e))?;
+        }
+    }
+    Ok(examples)
+}
+
+/// Runs the given parts of an example that have an expected answer.
+pub fn run(day: &dyn Day, example: &Example, parts: &[usize], timeout: Option<Duration>)
+    -> Vec<(usize, Answer, verify::Outcome)> {
+    let bytes: Rc<[u8]> = example.input.as_bytes().into();
+    let open = move || Box::new(io::Cursor::new(bytes.clone())) as Box<dyn io::Read>;
+    let (name, params) = (&example.name, &example.params);
+    let parsed = day::parse(day, &open, name, params, timeout);
+    parts.iter().filter(|&&part| example.expected[part - 1].is_some()).map(|&part| {
+        let answer = match &parsed {
+            Ok(parsed) => day::solve(day, part, parsed, name, params, timeout),
+            Err(e) => Answer::Error(e.to_string()),
+        };
+        let outcome = verify::check(example.expected[part - 1].as_ref(), &answer);
+        (part, answer, outcome)
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn examples() {
+        let s = "The examples of day 12.\n\
+                 == 1\n-- input\n<x=-1, y=0, z=2>\n\n-- params\nsteps = 10\n-- part 1\n179\n\n\
+                 == art\n-- input\n#.\n-- part 2\n .X\nX. \n";
+        let examples = parse(s).unwrap();
+        assert_eq!(examples.len(), 2);
+        assert_eq!((examples[0].name.as_str(), examples[0].input.as_str()),
+                   ("1", "<x=-1, y=0, z=2>\n"));
+        assert_eq!(examples[0].params.get("steps"), Some(&10));
+        assert_eq!(examples[0].expected, [Some(vec![String::from("179")]), None]);
+        assert_eq!(examples[1].expected[1], Some(vec![String::from(" .X"), String::from("X. ")]));
+        assert!(parse("-- input\n1\n").is_err());
+        assert_eq!(parse("== 1\n-- params\nsteps\n").err(),
+                   Some(String::from("line 2: expected \"<name>=<value>\", not \"steps\"")));
+        assert!(parse("== 1\n-- output\n1\n").is_err());
+    }
+}
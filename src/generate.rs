@@ -0,0 +1,97 @@
+use crate::day::Day;
+
+/// A small xorshift random number generator, so that generated inputs only
+/// depend on the seed.
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // Spread the seed over all the bits, xorshift stays at 0 from 0.
+        let mut rng = Rng(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1);
+        for _ in 0..8 { rng.next_u64(); }
+        rng
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// A number in `0..n`.
+    pub fn below(&mut self, n: usize) -> usize {
+        assert!(n > 0);
+        (self.next_u64() % n as u64) as usize
+    }
+
+    /// A number in `lo..=hi`.
+    pub fn range(&mut self, lo: i64, hi: i64) -> i64 {
+        lo + self.below((hi - lo + 1) as usize) as i64
+    }
+
+    /// True once in `n` times.
+    pub fn one_in(&mut self, n: usize) -> bool { self.below(n) == 0 }
+
+    pub fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T { &items[self.below(items.len())] }
+
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() { items.swap(i, self.below(i + 1)); }
+    }
+
+    /// A name of `len` upper case letters.
+    pub fn name(&mut self, len: usize) -> String {
+        (0..len).map(|_| (b'A' + self.below(26) as u8) as char).collect()
+    }
+}
+
+/// A random input for a day, of a size that depends on the day, e.g. the
+/// number of moons, or its default size.
+pub fn generate(day: &dyn Day, seed: u64, size: Option<usize>) -> Option<String> {
+    day.generate(&mut Rng::new(seed), size)
+}
+
+/// Checks a property with `cases` random number generators, seeded 1, 2 and
+/// so on, and tells the seed of the first case it does not hold for.
+///
+/// `PROPERTY_CASES` in the environment overrides the number of cases and
+/// `PROPERTY_SEED` checks just the given seed.
+#[cfg(test)]
+pub fn check(cases: u64, property: impl Fn(&mut Rng)) {
+    let env = |name| std::env::var(name).ok().and_then(|v| v.parse::<u64>().ok());
+    let seeds = match (env("PROPERTY_SEED"), env("PROPERTY_CASES")) {
+        (Some(seed), _) => seed..=seed,
+        (None, cases_env) => 1..=cases_env.unwrap_or(cases),
+    };
+    for seed in seeds {
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(||
+            property(&mut Rng::new(seed))));
+        if result.is_err() { panic!("property does not hold for PROPERTY_SEED={}", seed); }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rng() {
+        let (mut a, mut b) = (Rng::new(1), Rng::new(1));
+        assert_eq!((0..10).map(|_| a.next_u64()).collect::<Vec<_>>(),
+                   (0..10).map(|_| b.next_u64()).collect::<Vec<_>>());
+        assert_ne!(Rng::new(1).next_u64(), Rng::new(2).next_u64());
+        assert_ne!(Rng::new(0).next_u64(), 0);
+        let mut rng = Rng::new(7);
+        assert!((0..1000).map(|_| rng.range(-3, 3)).all(|n| (-3..=3).contains(&n)));
+        let mut items = (0..20).collect::<Vec<_>>();
+        rng.shuffle(&mut items);
+        items.sort();
+        assert_eq!(items, (0..20).collect::<Vec<_>>());
+    }
+
+    #[test]
+    #[should_panic(expected = "PROPERTY_SEED=")]
+    fn failing() {
+        check(10, |rng| assert!(rng.below(4) != 0));
+    }
+}
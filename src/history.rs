@@ -0,0 +1,226 @@
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::process::Command;
+use std::time::SystemTime;
+use crate::bench::{self, Baseline, Stats, Timing};
+
+/// The significance level of `compare`.
+pub const ALPHA: f64 = 0.01;
+
+/// A benchmark run, as appended to the history file: a `# <date> <commit>`
+/// line followed by the timings in the format of `bench::save`.
+pub struct Run {
+    pub date: String,
+    pub commit: String,
+    pub timings: Baseline,
+}
+
+/// The commit being benchmarked, with a `+` when there are uncommitted
+/// changes, or `unknown` outside of git.
+pub fn commit() -> String {
+    let git = |args: &[&str]| Command::new("git").args(args).output().ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string());
+    match git(&["rev-parse", "--short", "HEAD"]) {
+        Some(commit) if git(&["status", "--porcelain", "--untracked-files=no"])
+            .is_some_and(|s| !s.is_empty()) => commit + "+",
+        Some(commit) => commit,
+        None => String::from("unknown"),
+    }
+}
+
+/// The time in UTC, as in `2019-12-01T05:00:00Z`.
+pub fn date(time: SystemTime) -> String {
+    let secs = time.duration_since(SystemTime::UNIX_EPOCH).map_or(0, |d| d.as_secs()) as i64;
+    let (days, secs) = (secs.div_euclid(86400), secs.rem_euclid(86400));
+    // From days since the epoch to the proleptic Gregorian calendar, by eras of 400 years.
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+            year, month, day, secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+pub fn append(path: &str, date: &str, commit: &str, timings: &[Timing]) -> io::Result<()> {
+    let mut file = io::BufWriter::new(fs::OpenOptions::new().create(true).append(true).open(path)?);
+    writeln!(file, "# {} {}", date, commit)?;
+    bench::write(&mut file, timings)?;
+    file.flush()
+}
+
+/// Reads the runs in the history file, oldest first.
+pub fn load(path: &str) -> io::Result<Vec<Run>> {
+    let s = fs::read_to_string(path)?;
+    let mut runs = vec![];
+    let mut header: Option<(usize, &str)> = None;
+    let mut lines = vec![];
+    for (n, line) in s.lines().enumerate().chain(std::iter::once((usize::MAX, "#"))) {
+        if !line.starts_with('#') {
+            if header.is_none() {
+                let e = format!("{}: line {}: timing outside a run", path, n + 1);
+                return Err(io::Error::new(io::ErrorKind::InvalidData, e));
+            }
+            lines.push(line.to_string());
+            continue;
+        }
+        if let Some((first, header)) = header {
+            let mut f = header.trim_start_matches('#').split_whitespace();
+            let (date, commit) = (f.next().unwrap_or("?"), f.next().unwrap_or("unknown"));
+            runs.push(Run {
+                date: date.to_string(), commit: commit.to_string(),
+                timings: Baseline::read(path, first + 2, &lines)?,
+            });
+            lines.clear();
+        }
+        header = Some((n, line));
+    }
+    Ok(runs)
+}
+
+/// The natural logarithm of the gamma function, by Lanczos' approximation.
+fn ln_gamma(x: f64) -> f64 {
+    const G: [f64; 9] = [
+        0.999_999_999_999_809_9, 676.520_368_121_885_1, -1_259.139_216_722_402_8,
+        771.323_428_777_653_1, -176.615_029_162_140_6, 12.507_343_278_686_905,
+        -0.138_571_095_265_720_12, 9.984_369_578_019_572e-6, 1.505_632_735_149_311_6e-7,
+    ];
+    if x < 0.5 {
+        return (std::f64::consts::PI / (std::f64::consts::PI * x).sin()).ln() - ln_gamma(1.0 - x);
+    }
+    let x = x - 1.0;
+    let t = x + 7.5;
+    let sum = (1..9).fold(G[0], |sum, i| sum + G[i] / (x + i as f64));
+    0.5 * (2.0 * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + sum.ln()
+}
+
+/// The continued fraction of the incomplete beta function, by Lentz's method.
+fn beta_fraction(a: f64, b: f64, x: f64) -> f64 {
+    let tiny = 1e-300;
+    let mut c = 1.0;
+    let mut d = 1.0 - (a + b) * x / (a + 1.0);
+    if d.abs() < tiny { d = tiny; }
+    d = 1.0 / d;
+    let mut h = d;
+    for m in 1..300 {
+        let m = m as f64;
+        for numerator in [m * (b - m) * x / ((a + 2.0 * m - 1.0) * (a + 2.0 * m)),
+                          -(a + m) * (a + b + m) * x / ((a + 2.0 * m) * (a + 2.0 * m + 1.0))] {
+            d = 1.0 + numerator * d;
+            if d.abs() < tiny { d = tiny; }
+            c = 1.0 + numerator / c;
+            if c.abs() < tiny { c = tiny; }
+            d = 1.0 / d;
+            h *= d * c;
+        }
+        if (d * c - 1.0).abs() < 1e-15 { break; }
+    }
+    h
+}
+
+/// The regularized incomplete beta function I_x(a, b).
+fn incomplete_beta(a: f64, b: f64, x: f64) -> f64 {
+    if x <= 0.0 { return 0.0; }
+    if x >= 1.0 { return 1.0; }
+    let front = (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1.0 - x).ln())
+        .exp();
+    if x < (a + 1.0) / (a + b + 2.0) {
+        front * beta_fraction(a, b, x) / a
+    } else {
+        1.0 - front * beta_fraction(b, a, 1.0 - x) / b
+    }
+}
+
+/// Welch's t-test of whether two timings have different means: the two sided
+/// p-value, or `None` with fewer than two samples on either side.
+pub fn welch(old: &Stats, new: &Stats) -> Option<f64> {
+    if old.n < 2 || new.n < 2 { return None; }
+    let (v1, v2) = (old.stddev.powi(2) / old.n as f64, new.stddev.powi(2) / new.n as f64);
+    if v1 + v2 == 0.0 { return Some(if old.mean == new.mean { 1.0 } else { 0.0 }); }
+    let t = (new.mean - old.mean) / (v1 + v2).sqrt();
+    let df = (v1 + v2).powi(2)
+        / (v1.powi(2) / (old.n - 1) as f64 + v2.powi(2) / (new.n - 1) as f64);
+    Some(incomplete_beta(df / 2.0, 0.5, df / (df + t * t)))
+}
+
+/// How the mean of a part's phase changed between two runs.
+pub struct Change {
+    pub tag: String,
+    pub part: usize,
+    pub phase: String,
+    pub old: f64,
+    pub new: f64,
+    pub p: Option<f64>,
+}
+
+impl Change {
+    pub fn is_significant(&self) -> bool { self.p.is_some_and(|p| p < ALPHA) }
+
+    pub fn is_regression(&self) -> bool { self.is_significant() && self.new > self.old }
+}
+
+/// The changes of the timings both runs have.
+pub fn compare(old: &Baseline, new: &Baseline) -> Vec<Change> {
+    new.keys().into_iter().filter_map(|(tag, part, phase)| {
+        let (o, n) = (old.get(tag, part, phase)?, new.get(tag, part, phase)?);
+        Some(Change {
+            tag: tag.to_string(), part, phase: phase.to_string(), old: o.mean, new: n.mean,
+            p: welch(o, n),
+        })
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn stats(n: usize, mean: f64, stddev: f64) -> Stats {
+        Stats { n, min: mean, median: mean, mean, stddev }
+    }
+
+    #[test]
+    fn welch() {
+        // The two sided 5% critical value of t with 10 degrees of freedom is 2.228.
+        let p = incomplete_beta(5.0, 0.5, 10.0 / (10.0 + 2.228 * 2.228));
+        assert!((p - 0.05).abs() < 1e-4, "{}", p);
+        let p = super::welch(&stats(10, 100.0, 5.0), &stats(10, 120.0, 5.0)).unwrap();
+        assert!(p < 1e-6, "{}", p);
+        let p = super::welch(&stats(10, 100.0, 5.0), &stats(10, 101.0, 5.0)).unwrap();
+        assert!(p > 0.5, "{}", p);
+        assert_eq!(super::welch(&stats(1, 100.0, 0.0), &stats(10, 200.0, 5.0)), None);
+    }
+
+    #[test]
+    fn history() {
+        assert_eq!(date(SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000)),
+                   "2001-09-09T01:46:40Z");
+        assert_eq!(date(SystemTime::UNIX_EPOCH + Duration::from_secs(1_575_176_400)),
+                   "2019-12-01T05:00:00Z");
+        let path = std::env::temp_dir().join(format!("history-{}", std::process::id()));
+        let path = path.to_str().unwrap();
+        let timing = |mean| Timing {
+            tag: String::from("07"), part: 1, read: stats(10, 1000.0, 10.0),
+            parse: stats(10, 2000.0, 10.0), solve: stats(10, mean, 10.0),
+            memory: Default::default(),
+        };
+        append(path, "2019-12-01T05:00:00Z", "abc1234", &[timing(1e6)]).unwrap();
+        append(path, "2019-12-02T05:00:00Z", "def5678+", &[timing(2e6)]).unwrap();
+        let runs = load(path).unwrap();
+        fs::remove_file(path).unwrap();
+        assert_eq!(runs.len(), 2);
+        assert_eq!((runs[1].date.as_str(), runs[1].commit.as_str()),
+                   ("2019-12-02T05:00:00Z", "def5678+"));
+        let changes = compare(&runs[0].timings, &runs[1].timings);
+        assert_eq!(changes.len(), 3);
+        assert!(!changes[0].is_significant() && changes[0].phase == "parse");
+        assert!(!changes[1].is_significant() && changes[1].phase == "read");
+        assert!(changes[2].is_regression() && changes[2].phase == "solve");
+    }
+}
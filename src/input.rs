@@ -0,0 +1,175 @@
+use flate2::read::GzDecoder;
+use std::fs;
+use std::io;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+use crate::day;
+
+/// Where the days' inputs come from, as given on the command line.
+///
+/// `-` is stdin, a path containing `{day}` is a pattern like
+/// `inputs/{year}/day{day}.txt` and a directory holds files named like `07`,
+/// `day07` or `day07.txt`.  Anything else is a prefix the day's tag is appended
+/// to.  The inputs of a year are looked for in a directory named after it,
+/// e.g. `input/2018/07`, those of 2019 without it first.  Files compressed with
+/// gzip are decompressed, and a missing file is looked for with a `.gz` suffix
+/// as well.
+pub enum Source {
+    /// The same input for every day, read from stdin or given inline.
+    Bytes(String, Arc<[u8]>),
+    Pattern(String),
+    Directory(PathBuf),
+    Prefix(String),
+}
+
+/// The input of one day.
+pub struct Input {
+    pub name: String,
+    data: Data,
+}
+
+enum Data {
+    Bytes(Arc<[u8]>),
+    File(PathBuf, bool),
+}
+
+fn gzipped(path: &Path) -> io::Result<bool> {
+    let mut magic = [0; 2];
+    let n = fs::File::open(path)?.read(&mut magic)?;
+    Ok(n == 2 && magic == [0x1f, 0x8b])
+}
+
+fn with_gz(path: &Path) -> PathBuf {
+    let mut s = path.as_os_str().to_owned();
+    s.push(".gz");
+    PathBuf::from(s)
+}
+
+impl Source {
+    pub fn new(s: &str) -> io::Result<Self> {
+        Ok(if s == "-" {
+            let mut bytes = vec![];
+            io::stdin().read_to_end(&mut bytes)?;
+            Source::Bytes(String::from("<stdin>"), bytes.into())
+        } else if s.contains("{day}") {
+            Source::Pattern(s.to_string())
+        } else if Path::new(s).is_dir() {
+            Source::Directory(PathBuf::from(s))
+        } else {
+            Source::Prefix(s.to_string())
+        })
+    }
+
+    pub fn text(s: &str) -> Self {
+        Source::Bytes(String::from("<text>"), s.as_bytes().into())
+    }
+
+    fn candidates(&self, year: u32, tag: &str) -> Vec<PathBuf> {
+        let names = |dir: &Path| [tag.to_string(), format!("day{}", tag),
+                                  format!("day{}.txt", tag), format!("{}.txt", tag)]
+            .iter().map(|name| dir.join(name)).collect::<Vec<_>>();
+        let mut candidates = match self {
+            Source::Bytes(..) => return vec![],
+            Source::Pattern(pattern) => return vec![PathBuf::from(
+                pattern.replace("{year}", &year.to_string()).replace("{day}", tag))],
+            Source::Directory(dir) => names(&dir.join(year.to_string())),
+            Source::Prefix(prefix) => vec![PathBuf::from(format!("{}{}/{}", prefix, year, tag))],
+        };
+        if year == day::YEAR {
+            let unqualified = match self {
+                Source::Directory(dir) => names(dir),
+                Source::Prefix(prefix) => vec![PathBuf::from(format!("{}{}", prefix, tag))],
+                _ => vec![],
+            };
+            candidates.splice(0..0, unqualified);
+        }
+        candidates
+    }
+
+    /// The input of the day with the given year and tag, or why there is none.
+    pub fn input(&self, year: u32, tag: &str) -> Result<Input, String> {
+        if let Source::Bytes(name, bytes) = self {
+            return Ok(Input { name: name.clone(), data: Data::Bytes(bytes.clone()) });
+        }
+        let candidates = self.candidates(year, tag);
+        let path = candidates.iter().flat_map(|path| vec![path.clone(), with_gz(path)])
+            .find(|path| path.is_file())
+            .unwrap_or_else(|| candidates[0].clone());
+        let name = path.display().to_string();
+        match gzipped(&path) {
+            Ok(gzip) => Ok(Input { name, data: Data::File(path, gzip) }),
+            Err(e) => Err(format!("{}: {}", name, e)),
+        }
+    }
+}
+
+impl Input {
+    pub fn open(&self) -> io::Result<Box<dyn io::Read>> {
+        Ok(match &self.data {
+            Data::Bytes(bytes) => Box::new(io::Cursor::new(bytes.clone())),
+            Data::File(path, false) => Box::new(io::BufReader::new(fs::File::open(path)?)),
+            Data::File(path, true) =>
+                Box::new(GzDecoder::new(io::BufReader::new(fs::File::open(path)?))),
+        })
+    }
+
+    /// When the input's file was last modified, `None` for other inputs.
+    pub fn modified(&self) -> Option<SystemTime> {
+        match &self.data {
+            Data::Bytes(_) => None,
+            Data::File(path, _) => fs::metadata(path).and_then(|m| m.modified()).ok(),
+        }
+    }
+
+    /// The whole input, decompressed.
+    pub fn read(&self) -> io::Result<Vec<u8>> {
+        let mut bytes = vec![];
+        self.open()?.read_to_end(&mut bytes)?;
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use std::io::Write;
+
+    #[test]
+    fn sources() {
+        let dir = std::env::temp_dir().join(format!("aoc2019-input-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("day07.txt"), "3,0,99\n").unwrap();
+        let mut gz = GzEncoder::new(fs::File::create(dir.join("12.gz")).unwrap(),
+                                    flate2::Compression::default());
+        gz.write_all(b"<x=1, y=2, z=3>\n").unwrap();
+        gz.finish().unwrap();
+        let read = |source: &Source, tag| source.input(2019, tag).and_then(|i|
+            i.read().map(|b| String::from_utf8(b).unwrap()).map_err(|e| e.to_string()));
+        let directory = Source::new(dir.to_str().unwrap()).unwrap();
+        assert_eq!(read(&directory, "07").unwrap(), "3,0,99\n");
+        assert_eq!(read(&directory, "12").unwrap(), "<x=1, y=2, z=3>\n");
+        assert!(directory.input(2019, "08").is_err());
+        let pattern = Source::new(dir.join("day{day}.txt").to_str().unwrap()).unwrap();
+        assert_eq!(read(&pattern, "07").unwrap(), "3,0,99\n");
+        let prefix = Source::new(&format!("{}/", dir.display())).unwrap();
+        assert!(matches!(prefix, Source::Directory(_)));
+        assert_eq!(read(&Source::Prefix(format!("{}/", dir.display())), "12").unwrap(),
+                   "<x=1, y=2, z=3>\n");
+        assert_eq!(read(&Source::text("1\n2\n"), "01").unwrap(), "1\n2\n");
+        assert!(directory.input(2019, "07").unwrap().modified().is_some());
+        assert!(Source::text("").input(2019, "07").unwrap().modified().is_none());
+        fs::create_dir_all(dir.join("2018")).unwrap();
+        fs::write(dir.join("2018").join("07"), "1\n").unwrap();
+        assert!(directory.input(2017, "07").is_err());
+        let year = |source: &Source| source.input(2018, "07").and_then(|i|
+            i.read().map(|b| String::from_utf8(b).unwrap()).map_err(|e| e.to_string()));
+        assert_eq!(year(&directory).unwrap(), "1\n");
+        assert_eq!(year(&Source::Prefix(format!("{}/", dir.display()))).unwrap(), "1\n");
+        assert_eq!(year(&Source::new(dir.join("{year}/{day}").to_str().unwrap()).unwrap())
+                       .unwrap(), "1\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
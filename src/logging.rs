@@ -0,0 +1,57 @@
+use log::LevelFilter;
+
+/// The level logged at with `-v` given n times.
+pub fn level(verbose: usize) -> LevelFilter {
+    match verbose {
+        0 => LevelFilter::Warn,
+        1 => LevelFilter::Info,
+        2 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    }
+}
+
+/// Expands the targets of a `RUST_LOG` style spec, so `23=trace` and
+/// `day23=trace` mean the module of day 23.
+pub fn expand(spec: &str) -> String {
+    spec.split(',').map(|directive| {
+        let (target, level) = match directive.split_once('=') {
+            Some((target, level)) => (target, Some(level)),
+            None => (directive, None),
+        };
+        let tag = target.strip_prefix("day").unwrap_or(target);
+        let day = !tag.is_empty() && tag.len() <= 2 && tag.chars().all(|c| c.is_ascii_digit());
+        let target = if day {
+            format!("{}::day{:0>2}", env!("CARGO_CRATE_NAME"), tag)
+        } else {
+            target.to_string()
+        };
+        match level {
+            Some(level) => format!("{}={}", target, level),
+            None => target,
+        }
+    }).collect::<Vec<_>>().join(",")
+}
+
+/// Logs to stderr at the level `-v` asks for, with `RUST_LOG` and then `--log`
+/// refining it per module.
+pub fn init(verbose: usize, spec: Option<&str>) {
+    let mut builder = env_logger::Builder::new();
+    builder.filter_level(level(verbose)).format_timestamp(None);
+    if let Ok(env) = std::env::var("RUST_LOG") { builder.parse_filters(&expand(&env)); }
+    if let Some(spec) = spec { builder.parse_filters(&expand(spec)); }
+    builder.init();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn specs() {
+        assert_eq!(expand("23=trace,day7=debug,info,adventofcode2019::day=warn"),
+                   "adventofcode2019::day23=trace,adventofcode2019::day07=debug,info,\
+                    adventofcode2019::day=warn");
+        assert_eq!(expand("debug"), "debug");
+        assert_eq!(level(2), LevelFilter::Debug);
+    }
+}
@@ -1,66 +1,401 @@
-#[macro_use]
-extern crate closure;
-#[macro_use]
-extern crate lazy_static;
-//#[macro_use]
-extern crate simple_error;
-
-mod day;
-mod day01;
-mod day02;
-mod day03;
-mod day04;
-mod day05;
-mod day06;
-mod day07;
-mod day08;
-mod day09;
-mod day10;
-mod day11;
-mod day12;
-mod day13;
-mod day14;
-mod day15;
-mod day16;
-mod day17;
-mod day18;
-mod day19;
-mod day20;
-mod day21;
-mod day22;
-mod day23;
-mod day24;
-mod day25;
-
+use adventofcode2019::{bench, cache, cli, config, day, examples, generate, history, input, logging,
+                       memory, output, parallel, repl, verify, watch};
 use std::env;
-use std::fs;
 use std::io;
+use std::process;
+use std::time::{Duration, Instant, SystemTime};
+
+/// A day to run, the parts of it to run and its input.
+type Job<'a> = (&'a dyn day::Day, Vec<usize>, Result<input::Input, String>);
+
+/// How a part was solved.
+struct Solved {
+    part: usize,
+    answer: day::Answer,
+    duration: Duration,
+    cached: bool,
+    memory: Option<memory::Usage>,
+}
+
+#[global_allocator]
+static ALLOCATOR: memory::Counting = memory::Counting;
 
 fn main() {
-    let args = env::args().collect::<Vec<_>>();
-    let prefix = &args[1];
-    let days: Vec<Box<dyn day::Day>> = vec![
-        Box::new(day01::Day01 {}), Box::new(day02::Day02 {}),
-        Box::new(day03::Day03 {}), Box::new(day04::Day04 {}),
-        Box::new(day05::Day05 {}), Box::new(day06::Day06 {}),
-        Box::new(day07::Day07 {}), Box::new(day08::Day08 {}),
-        Box::new(day09::Day09 {}), Box::new(day10::Day10 {}),
-        Box::new(day11::Day11 {}), Box::new(day12::Day12 {}),
-        Box::new(day13::Day13 {}), Box::new(day14::Day14 {}),
-        Box::new(day15::Day15 {}), Box::new(day16::Day16 {}),
-        Box::new(day17::Day17 {}), Box::new(day18::Day18 {}),
-        Box::new(day19::Day19 {}), Box::new(day20::Day20 {}),
-        Box::new(day21::Day21 {}), Box::new(day22::Day22 {}),
-        Box::new(day23::Day23 {}), Box::new(day24::Day24 {}),
-        Box::new(day25::Day25 {}),
-    ];
-    let inputs = days.iter().map(|day| format!("{}{}", prefix, day.tag()));
-    for day in days.iter().zip(inputs).rev() {
-        if args.len() > 2 && &args[2] != day.0.tag() { continue; }
-        let input: Box<dyn Fn() -> Box<dyn io::Read>>
-            = Box::new(|| Box::new(fs::File::open(&day.1).unwrap()));
-        println!("= {} =", day.0.tag());
-        day.0.part1(&input);
-        day.0.part2(&input);
+    let options = cli::Options::parse(env::args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("{}\nrun with --help for usage", e);
+        process::exit(2);
+    });
+    if options.help {
+        println!("{}", cli::USAGE);
+        return;
+    }
+    logging::init(options.verbose, options.log.as_deref());
+    let days = day::registry().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(2);
+    });
+    if options.list {
+        for day in &days {
+            println!("{:>7}  {}", day.id(), day.title());
+            for (i, part) in day.parts().iter().enumerate() {
+                if !part.is_empty() { println!("           {}: {}", i + 1, part); }
+            }
+            for param in day.params() {
+                println!("           {}.{}={}  {}",
+                         day.id(), param.name, param.default, param.help);
+            }
+        }
+        return;
+    }
+    if let Some(tag) = &options.generate {
+        let day = days.iter().find(|d| d.id() == *tag).unwrap_or_else(|| {
+            eprintln!("no day {}", tag);
+            process::exit(2);
+        });
+        match generate::generate(*day, options.seed, options.size) {
+            Some(input) => print!("{}", input),
+            None => {
+                eprintln!("day {} has no generator", tag);
+                process::exit(2);
+            },
+        }
+        return;
+    }
+    if let Some(commit) = &options.compare {
+        if !compare(&options, commit.as_deref()) { process::exit(1); }
+        return;
+    }
+    if options.examples {
+        if !examples(&options, &days) { process::exit(1); }
+        return;
+    }
+    let config = parameters(&options, &days).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(2);
+    });
+    if options.repl {
+        let source = match (&options.text, options.input.as_str()) {
+            (Some(text), _) => Some(input::Source::text(text)),
+            (None, "") => None,
+            (None, path) => Some(input::Source::new(path).unwrap_or_else(|e| {
+                eprintln!("{}: {}", path, e);
+                process::exit(2);
+            })),
+        };
+        let mut session = repl::Session::new(&days, source, &config, options.timeout);
+        println!("type help for the commands");
+        repl::run(&mut session, io::stdin().lock(), io::stdout())
+            .unwrap_or_else(|e| eprintln!("{}", e));
+        return;
+    }
+    let expected = options.verify.as_ref().map(|path|
+        verify::Expected::load(path).unwrap_or_else(|e| {
+            eprintln!("{}: {}", path, e);
+            process::exit(2);
+        }));
+    let baseline = options.baseline.as_ref().map(|path|
+        bench::Baseline::load(path).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            process::exit(2);
+        }));
+    let cache = options.cache.as_ref().map(|path|
+        cache::Cache::load(path).unwrap_or_else(|e| {
+            eprintln!("{}: {}", path, e);
+            process::exit(2);
+        }));
+    let source = match &options.text {
+        Some(text) => input::Source::text(text),
+        None => input::Source::new(&options.input).unwrap_or_else(|e| {
+            eprintln!("{}: {}", options.input, e);
+            process::exit(2);
+        }),
+    };
+    let selected = days.iter().rev()
+        .map(|day| (*day, options.selection.parts(day.year(), day.tag().parse().unwrap_or(0))))
+        .filter(|(_, parts)| !parts.is_empty())
+        .map(|(day, parts)| (day, parts, source.input(day.year(), day.tag())))
+        .collect::<Vec<_>>();
+    if let Some(iterations) = options.bench {
+        let mut failures = vec![];
+        bench::header();
+        let mut timings = vec![];
+        for (day, parts, input) in &selected {
+            for &part in parts {
+                let timing = input.as_ref().map_err(|e| io::Error::other(e.clone()))
+                    .and_then(|input| bench::run(*day, part, input, &config.get(&day.id()),
+                                                 options.warmup, iterations, options.timeout));
+                match timing {
+                    Ok(timing) => {
+                        bench::print(&timing, baseline.as_ref());
+                        if options.memory { bench::print_memory(&timing); }
+                        timings.push(timing);
+                    },
+                    Err(e) => {
+                        failures.push(format!("{}:{}", day.id(), part));
+                        println!("{:>7} {:>4} error: {}", day.id(), part, e);
+                    },
+                }
+            }
+        }
+        if let Some(path) = &options.save {
+            bench::save(path, &timings).unwrap_or_else(|e| eprintln!("{}: {}", path, e));
+        }
+        if let (Some(path), false) = (&options.history, timings.is_empty()) {
+            history::append(path, &history::date(SystemTime::now()), &history::commit(), &timings)
+                .unwrap_or_else(|e| eprintln!("{}: {}", path, e));
+        }
+        summary(selected.iter().map(|(_, parts, _)| parts.len()).sum(), &failures, 0, 0, false);
+        if !failures.is_empty() { process::exit(1); }
+        return;
+    }
+    if !options.watch {
+        if !run(&options, &config, cache.as_ref(), expected.as_ref(), &selected) {
+            process::exit(1);
+        }
+        return;
     }
-}
\ No newline at end of file
+    if let input::Source::Bytes(name, _) = &source {
+        eprintln!("--watch needs input files, not {}", name);
+        process::exit(2);
+    }
+    let poll = || selected.iter()
+        .map(|(day, _, _)| watch::stamp(&source.input(day.year(), day.tag())))
+        .collect::<Vec<_>>();
+    let mut stamps = poll();
+    let mut changed = (0..selected.len()).collect::<Vec<_>>();
+    loop {
+        let jobs = changed.iter().map(|&i| {
+            let (day, parts, _) = &selected[i];
+            (*day, parts.clone(), source.input(day.year(), day.tag()))
+        }).collect::<Vec<_>>();
+        print!("{}", watch::CLEAR);
+        let start = Instant::now();
+        run(&options, &config, cache.as_ref(), expected.as_ref(), &jobs);
+        println!("ran in {:.2?}, watching the inputs for changes", start.elapsed());
+        changed = watch::wait(&mut stamps, poll, watch::INTERVAL);
+    }
+}
+
+/// Solves the parts of the jobs and reports their answers, returning whether
+/// none of them failed.
+fn run(options: &cli::Options, config: &config::Config, cache: Option<&cache::Cache>,
+       expected: Option<&verify::Expected>, selected: &[Job]) -> bool {
+    let jobs = options.jobs;
+    let solve = |(day, parts, input): &Job| {
+        let start = Instant::now();
+        let input = match input {
+            Ok(input) => input,
+            Err(e) => {
+                let error = day::Answer::Error(e.clone());
+                return parts.iter().map(|&part| Solved {
+                    part, answer: error.clone(), duration: Duration::ZERO, cached: false,
+                    memory: None,
+                }).collect();
+            },
+        };
+        let open: Box<dyn Fn() -> Box<dyn io::Read>> = Box::new(move || input.open().unwrap());
+        let params = config.get(&day.id());
+        let bytes = cache.as_ref().and_then(|_| input.read().ok());
+        // Parsed by the first part that is not cached, whose time includes parsing.
+        let mut parsed = None;
+        let answers = parts.iter().map(|&part| {
+            let key = bytes.as_ref().map(|bytes| cache::key(*day, part, bytes, &params));
+            let cached = key.filter(|_| !options.recompute && !options.memory)
+                .and_then(|key| cache.as_ref()?.get(key));
+            if let Some(answer) = cached {
+                let duration = Duration::ZERO;
+                return Solved { part, answer, duration, cached: true, memory: None };
+            }
+            let start = Instant::now();
+            let (name, timeout) = (&input.name, options.timeout);
+            let mut run = || match parsed
+                .get_or_insert_with(|| day::parse(*day, &open, name, &params, timeout)) {
+                Ok(parsed) => day::solve(*day, part, parsed, name, &params, timeout),
+                Err(e) => day::Answer::Error(e.to_string()),
+            };
+            let (answer, memory) = if options.memory {
+                let (answer, usage) = memory::measure(run);
+                (answer, Some(usage))
+            } else {
+                (run(), None)
+            };
+            let duration = start.elapsed();
+            if let (Some(cache), Some(key)) = (&cache, key) { cache.put(key, &answer); }
+            Solved { part, answer, duration, cached: false, memory }
+        }).collect::<Vec<_>>();
+        if jobs > 1 { log::info!("{} done in {:.2?}", day.id(), start.elapsed()); }
+        answers
+    };
+    let parts = selected.iter().map(|(_, parts, _)| parts.len()).sum();
+    let text = options.format == output::Format::Text;
+    let mut writer = output::Writer::new(options.format, parts);
+    let mut failures = vec![];
+    let mut skipped = 0;
+    let mut hits = 0;
+    if text && expected.is_some() { println!("    day part result"); }
+    let mut report = |(day, _, _): &Job, answers: Vec<Solved>| {
+        if text && expected.is_none() && options.watch {
+            let total = answers.iter().map(|solved| solved.duration).sum::<Duration>();
+            println!("= {} = {:.2?}", day.id(), total);
+        } else if text && expected.is_none() {
+            println!("= {} =", day.id());
+        }
+        let id = day.id();
+        for Solved { part, answer, duration, cached, memory } in answers {
+            let (status, message) = match &expected {
+                None => match &answer {
+                    day::Answer::Error(e) => (output::Status::Error, Some(e.clone())),
+                    _ => (output::Status::Ok, None),
+                },
+                Some(expected) => match verify::check(expected.get(&id, part), &answer) {
+                    verify::Outcome::Pass => (output::Status::Pass, None),
+                    verify::Outcome::Missing => (output::Status::Skip, None),
+                    verify::Outcome::Fail(diff) => (output::Status::Fail, Some(diff.join("\n"))),
+                },
+            };
+            if status.is_failure() { failures.push(format!("{}:{}", id, part)); }
+            if status == output::Status::Skip { skipped += 1; }
+            if cached { hits += 1; }
+            if !text {
+                writer.write(&output::Record {
+                    tag: &id, part, answer: &answer, status, message, duration, cached,
+                    memory,
+                });
+                continue;
+            }
+            let from = if cached { " (cached)" } else { "" };
+            match status {
+                output::Status::Ok | output::Status::Error => println!("{}{}", answer, from),
+                output::Status::Pass => println!("{:>7} {:>4} pass{}", id, part, from),
+                output::Status::Skip =>
+                    println!("{:>7} {:>4} skip (no expected answer){}", id, part, from),
+                output::Status::Fail => {
+                    println!("{:>7} {:>4} FAIL{}", id, part, from);
+                    for line in message.unwrap_or_default().lines() {
+                        println!("               {}", line);
+                    }
+                },
+            }
+            if let Some(usage) = memory {
+                println!("(part {} in {:.2?}, {})", part, duration, usage);
+            }
+        }
+    };
+    if jobs > 1 {
+        parallel::run(selected, jobs, solve, &mut report);
+    } else {
+        for day in selected { report(day, solve(day)); }
+    }
+    writer.finish();
+    if let (Some(cache), Some(path)) = (&cache, &options.cache) {
+        cache.save(path).unwrap_or_else(|e| eprintln!("{}: {}", path, e));
+    }
+    summary(parts, &failures, skipped, hits, !text);
+    failures.is_empty()
+}
+
+/// Compares the last benchmark run in the history with an earlier one or the
+/// baseline, returning whether nothing got significantly slower.
+fn compare(options: &cli::Options, commit: Option<&str>) -> bool {
+    let path = options.history.as_deref().unwrap_or(".history");
+    let mut runs = history::load(path).unwrap_or_else(|e| {
+        eprintln!("{}: {}", path, e);
+        process::exit(2);
+    });
+    let last = runs.pop().unwrap_or_else(|| {
+        eprintln!("{}: no benchmark runs", path);
+        process::exit(2);
+    });
+    let (name, old) = match (&options.baseline, commit) {
+        (Some(file), _) => (file.clone(), bench::Baseline::load(file).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            process::exit(2);
+        })),
+        (None, commit) => {
+            let run = runs.into_iter().rev()
+                .find(|run| commit.is_none_or(|c| run.commit.starts_with(c)))
+                .unwrap_or_else(|| {
+                    eprintln!("{}: no run {}to compare with", path,
+                              commit.map(|c| format!("of {} ", c)).unwrap_or_default());
+                    process::exit(2);
+                });
+            (format!("{} ({})", run.commit, run.date), run.timings)
+        },
+    };
+    println!("{} ({}) against {}", last.commit, last.date, name);
+    println!("    day part phase       old        new   change        p");
+    let changes = history::compare(&old, &last.timings);
+    for change in &changes {
+        let p = change.p.map_or_else(|| String::from("-"), |p| format!("{:.4}", p));
+        let flag = match (change.is_significant(), change.is_regression()) {
+            (true, true) => "  SLOWER",
+            (true, false) => "  faster",
+            _ => "",
+        };
+        println!("{:>7} {:>4} {:<5} {:>10} {:>10} {:>+7.1}% {:>8}{}",
+                 change.tag, change.part, change.phase, bench::human(change.old),
+                 bench::human(change.new), (change.new / change.old - 1.0) * 100.0, p, flag);
+    }
+    let slower = changes.iter().filter(|change| change.is_regression())
+        .map(|change| format!("{}:{} {}", change.tag, change.part, change.phase))
+        .collect::<Vec<_>>();
+    let mut s = format!("{} compared, {} significantly slower", changes.len(), slower.len());
+    if !slower.is_empty() { s += &format!(" ({})", slower.join(", ")); }
+    println!("{}", s);
+    slower.is_empty()
+}
+
+/// Checks the selected days' answers to the examples from their puzzles,
+/// returning whether they were all right.
+fn examples(options: &cli::Options, days: &[&dyn day::Day]) -> bool {
+    let mut failures = vec![];
+    let mut parts = 0;
+    println!("    day part result");
+    for day in days {
+        let selected = options.selection.parts(day.year(), day.tag().parse().unwrap_or(0));
+        if selected.is_empty() { continue; }
+        let examples = examples::load(examples::DIR, *day).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            failures.push(day.id());
+            vec![]
+        });
+        for example in &examples {
+            for (part, _, outcome) in examples::run(*day, example, &selected, options.timeout) {
+                parts += 1;
+                match outcome {
+                    verify::Outcome::Pass =>
+                        println!("{:>7} {:>4} pass (example {})", day.id(), part, example.name),
+                    verify::Outcome::Fail(diff) => {
+                        println!("{:>7} {:>4} FAIL (example {})", day.id(), part, example.name);
+                        for line in diff { println!("               {}", line); }
+                        failures.push(format!("{}:{} example {}", day.id(), part, example.name));
+                    },
+                    verify::Outcome::Missing => (),
+                }
+            }
+        }
+    }
+    summary(parts, &failures, 0, 0, false);
+    failures.is_empty()
+}
+
+/// The parameters from the config file, overridden by those on the command line.
+fn parameters(options: &cli::Options, days: &[&dyn day::Day]) -> Result<config::Config, String> {
+    let mut config = match &options.config {
+        Some(path) => config::Config::load(path).map_err(|e| format!("{}: {}", path, e))?,
+        None => config::Config::default(),
+    };
+    for param in &options.params { config.set(param)?; }
+    config.check(days)?;
+    Ok(config)
+}
+
+/// Prints how the run went, on stderr when stdout has machine readable output.
+fn summary(parts: usize, failures: &[String], skipped: usize, cached: usize, stderr: bool) {
+    let mut s = format!("{} part{}, {} failed",
+                        parts, if parts == 1 { "" } else { "s" }, failures.len());
+    if !failures.is_empty() { s += &format!(" ({})", failures.join(", ")); }
+    if skipped > 0 { s += &format!(", {} without an expected answer", skipped); }
+    if cached > 0 { s += &format!(", {} from the cache", cached); }
+    if stderr { eprintln!("{}", s); } else { println!("{}", s); }
+}
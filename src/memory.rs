@@ -0,0 +1,113 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The system allocator, counting what is allocated so that `measure` can
+/// tell.  The binary installs it as the global allocator.
+pub struct Counting;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+fn grown(size: usize) {
+    let current = CURRENT.fetch_add(size, Ordering::Relaxed) + size;
+    PEAK.fetch_max(current, Ordering::Relaxed);
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let p = System.alloc(layout);
+        if !p.is_null() {
+            ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+            grown(layout.size());
+        }
+        p
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let p = System.alloc_zeroed(layout);
+        if !p.is_null() {
+            ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+            grown(layout.size());
+        }
+        p
+    }
+
+    unsafe fn dealloc(&self, p: *mut u8, layout: Layout) {
+        System.dealloc(p, layout);
+        CURRENT.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, p: *mut u8, layout: Layout, size: usize) -> *mut u8 {
+        let q = System.realloc(p, layout, size);
+        if !q.is_null() {
+            ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+            if size > layout.size() {
+                grown(size - layout.size());
+            } else {
+                CURRENT.fetch_sub(layout.size() - size, Ordering::Relaxed);
+            }
+        }
+        q
+    }
+}
+
+/// The heap used while measuring: the most in use at once, beyond what was in
+/// use before, and how many times memory was allocated or reallocated.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Usage {
+    pub peak: usize,
+    pub allocations: usize,
+}
+
+/// Runs `f` and measures its heap usage, which is all zero unless `Counting`
+/// is the global allocator.  Every thread counts, so measure one thing at a time.
+pub fn measure<T>(f: impl FnOnce() -> T) -> (T, Usage) {
+    let base = CURRENT.load(Ordering::Relaxed);
+    PEAK.store(base, Ordering::Relaxed);
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let t = f();
+    let usage = Usage {
+        peak: PEAK.load(Ordering::Relaxed).saturating_sub(base),
+        allocations: ALLOCATIONS.load(Ordering::Relaxed) - allocations,
+    };
+    (t, usage)
+}
+
+pub fn human(bytes: usize) -> String {
+    let b = bytes as f64;
+    if b >= (1 << 30) as f64 { format!("{:.2} GiB", b / (1 << 30) as f64) }
+    else if b >= (1 << 20) as f64 { format!("{:.2} MiB", b / (1 << 20) as f64) }
+    else if b >= (1 << 10) as f64 { format!("{:.2} KiB", b / (1 << 10) as f64) }
+    else { format!("{} B", bytes) }
+}
+
+impl std::fmt::Display for Usage {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "peak {}, {} allocation{}", human(self.peak), self.allocations,
+               if self.allocations == 1 { "" } else { "s" })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[global_allocator]
+    static ALLOCATOR: Counting = Counting;
+
+    #[test]
+    fn measure() {
+        let (v, usage) = super::measure(|| {
+            let mut v = vec![0u8; 1 << 20];
+            v.extend_from_slice(&[1; 1 << 20]);
+            drop(vec![0u8; 1 << 10]);
+            v.len()
+        });
+        assert_eq!(v, 2 << 20);
+        assert!(usage.peak >= 2 << 20, "{:?}", usage);
+        assert!(usage.allocations >= 3, "{:?}", usage);
+        assert_eq!(human(1536), "1.50 KiB");
+        assert_eq!(Usage { peak: 10, allocations: 1 }.to_string(), "peak 10 B, 1 allocation");
+    }
+}
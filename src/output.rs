@@ -0,0 +1,231 @@
+use std::time::Duration;
+use crate::day::Answer;
+use crate::memory::Usage;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Text,
+    Json,
+    Tap,
+    Csv,
+}
+
+impl Format {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "text" => Some(Format::Text),
+            "json" => Some(Format::Json),
+            "tap" => Some(Format::Tap),
+            "csv" => Some(Format::Csv),
+            _ => None,
+        }
+    }
+}
+
+/// How a part went: `Ok` and `Error` on a plain run, `Pass`, `Fail` and `Skip`
+/// when checking against expected answers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Status {
+    Ok,
+    Error,
+    Pass,
+    Fail,
+    Skip,
+}
+
+impl Status {
+    pub fn name(&self) -> &str {
+        match self {
+            Status::Ok => "ok",
+            Status::Error => "error",
+            Status::Pass => "pass",
+            Status::Fail => "fail",
+            Status::Skip => "skip",
+        }
+    }
+
+    pub fn is_failure(&self) -> bool { matches!(self, Status::Error | Status::Fail) }
+}
+
+/// The result of one part, as written in the machine readable formats.
+pub struct Record<'a> {
+    pub tag: &'a str,
+    pub part: usize,
+    pub answer: &'a Answer,
+    pub status: Status,
+    /// The error, or the difference from the expected answer.
+    pub message: Option<String>,
+    pub duration: Duration,
+    /// Whether the answer came from the cache instead of being computed.
+    pub cached: bool,
+    /// The heap usage, with `--memory`.
+    pub memory: Option<Usage>,
+}
+
+fn ms(d: Duration) -> f64 { d.as_secs_f64() * 1e3 }
+
+fn json_string(s: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+pub fn json(r: &Record) -> String {
+    let answer = match r.answer {
+        Answer::None | Answer::Error(_) => String::from("null"),
+        Answer::Number(n) => n.to_string(),
+        Answer::Text(s) => json_string(s),
+        Answer::Lines(lines) =>
+            format!("[{}]", lines.iter().map(|l| json_string(l)).collect::<Vec<_>>().join(", ")),
+    };
+    let message = r.message.as_ref().map_or_else(|| String::from("null"), |m| json_string(m));
+    let memory = r.memory.map_or_else(String::new, |m|
+        format!(", \"peak_bytes\": {}, \"allocations\": {}", m.peak, m.allocations));
+    format!("{{\"day\": {}, \"part\": {}, \"status\": {}, \"answer\": {}, \"error\": {}, \
+             \"duration_ms\": {:.3}, \"cached\": {}{}}}",
+            json_string(r.tag), r.part, json_string(r.status.name()), answer, message,
+            ms(r.duration), r.cached, memory)
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+pub const CSV_HEADER: &str =
+    "day,part,status,answer,error,duration_ms,cached,peak_bytes,allocations";
+
+pub fn csv(r: &Record) -> String {
+    let answer = match r.answer {
+        Answer::None | Answer::Error(_) => String::new(),
+        Answer::Lines(lines) => lines.join("\n"),
+        a => a.to_string(),
+    };
+    let memory = r.memory.map_or_else(|| String::from(","),
+                                      |m| format!("{},{}", m.peak, m.allocations));
+    format!("{},{},{},{},{},{:.3},{},{}", r.tag, r.part, r.status.name(), csv_field(&answer),
+            csv_field(r.message.as_deref().unwrap_or("")), ms(r.duration), r.cached, memory)
+}
+
+/// A TAP test line for the n:th part, with the answer and any error as diagnostics.
+pub fn tap(n: usize, r: &Record) -> String {
+    let ok = if r.status.is_failure() { "not ok" } else { "ok" };
+    let mut lines = vec![match (r.status, r.answer) {
+        (Status::Skip, _) =>
+            format!("{} {} - {}:{} # SKIP no expected answer", ok, n, r.tag, r.part),
+        (_, Answer::Number(_)) | (_, Answer::Text(_)) =>
+            format!("{} {} - {}:{} {}", ok, n, r.tag, r.part, r.answer),
+        _ => format!("{} {} - {}:{}", ok, n, r.tag, r.part),
+    }];
+    lines.push(String::from("  ---"));
+    if let Answer::Lines(answer) = r.answer {
+        lines.push(String::from("  answer: |"));
+        lines.extend(answer.iter().map(|l| format!("    {}", l)));
+    }
+    if let Some(message) = &r.message {
+        lines.push(String::from("  message: |"));
+        lines.extend(message.lines().map(|l| format!("    {}", l)));
+    }
+    lines.push(format!("  duration_ms: {:.3}", ms(r.duration)));
+    if r.cached { lines.push(String::from("  cached: true")); }
+    if let Some(m) = r.memory {
+        lines.push(format!("  peak_bytes: {}", m.peak));
+        lines.push(format!("  allocations: {}", m.allocations));
+    }
+    lines.push(String::from("  ..."));
+    lines.join("\n")
+}
+
+/// Writes the records to stdout as they come, in one of the machine readable formats.
+pub struct Writer {
+    format: Format,
+    written: usize,
+}
+
+impl Writer {
+    /// Starts the output of `parts` records.
+    pub fn new(format: Format, parts: usize) -> Self {
+        match format {
+            Format::Json => println!("["),
+            Format::Tap => println!("TAP version 13\n1..{}", parts),
+            Format::Csv => println!("{}", CSV_HEADER),
+            Format::Text => (),
+        }
+        Writer { format, written: 0 }
+    }
+
+    pub fn write(&mut self, record: &Record) {
+        self.written += 1;
+        match self.format {
+            Format::Json => {
+                if self.written > 1 { println!(","); }
+                print!("  {}", json(record));
+            },
+            Format::Tap => println!("{}", tap(self.written, record)),
+            Format::Csv => println!("{}", csv(record)),
+            Format::Text => (),
+        }
+    }
+
+    pub fn finish(&self) {
+        if self.format == Format::Json {
+            if self.written > 0 { println!(); }
+            println!("]");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record<'a>(answer: &'a Answer, status: Status, message: Option<&str>) -> Record<'a> {
+        Record {
+            tag: "08", part: 2, answer, status, message: message.map(String::from),
+            duration: Duration::from_micros(1500), cached: false, memory: None,
+        }
+    }
+
+    #[test]
+    fn formats() {
+        let art = Answer::Lines(vec![String::from(" .X"), String::from("\"X\",")]);
+        let r = record(&art, Status::Ok, None);
+        assert_eq!(json(&r), "{\"day\": \"08\", \"part\": 2, \"status\": \"ok\", \
+                              \"answer\": [\" .X\", \"\\\"X\\\",\"], \"error\": null, \
+                              \"duration_ms\": 1.500, \"cached\": false}");
+        assert_eq!(csv(&r), "08,2,ok,\" .X\n\"\"X\"\",\",,1.500,false,,");
+        assert_eq!(tap(3, &r), "ok 3 - 08:2\n  ---\n  answer: |\n     .X\n    \"X\",\n  \
+                                duration_ms: 1.500\n  ...");
+        let error = Answer::Error(String::from("no solution"));
+        let r = record(&error, Status::Error, Some("no solution"));
+        assert_eq!(csv(&r), "08,2,error,,no solution,1.500,false,,");
+        assert!(json(&r).contains("\"answer\": null, \"error\": \"no solution\""));
+        assert!(tap(1, &r).starts_with("not ok 1 - 08:2\n"));
+        let r = record(&Answer::Number(43210), Status::Skip, None);
+        assert!(tap(1, &r).starts_with("ok 1 - 08:2 # SKIP"));
+        let r = Record { cached: true, ..record(&Answer::Number(43210), Status::Ok, None) };
+        assert!(tap(1, &r).contains("\n  cached: true\n"));
+        assert!(json(&r).ends_with("\"cached\": true}"));
+        let memory = Some(Usage { peak: 4096, allocations: 12 });
+        let r = Record { memory, ..record(&Answer::Number(43210), Status::Ok, None) };
+        assert!(json(&r).ends_with("\"peak_bytes\": 4096, \"allocations\": 12}"));
+        assert!(csv(&r).ends_with(",false,4096,12"));
+        assert!(tap(1, &r).contains("\n  peak_bytes: 4096\n  allocations: 12\n"));
+        assert_eq!(Format::parse("tap"), Some(Format::Tap));
+        assert_eq!(Format::parse("xml"), None);
+    }
+}
@@ -0,0 +1,56 @@
+use std::collections::BTreeMap;
+use std::sync::{mpsc, Mutex};
+use std::thread;
+
+/// Runs `solve` for every job on `jobs` worker threads and hands the results
+/// to `report` in job order.
+///
+/// Results that complete out of order are held back until all earlier jobs
+/// are done, so the output does not depend on the scheduling.
+pub fn run<J, T, F, R>(items: &[J], jobs: usize, solve: F, mut report: R)
+    where J: Sync, T: Send, F: Fn(&J) -> T + Sync, R: FnMut(&J, T) {
+    let queue = Mutex::new(items.iter().enumerate());
+    let (sender, receiver) = mpsc::channel::<(usize, T)>();
+    thread::scope(|scope| {
+        for _ in 0..jobs.max(1) {
+            let sender = sender.clone();
+            let (queue, solve) = (&queue, &solve);
+            scope.spawn(move || loop {
+                let next = queue.lock().unwrap().next();
+                let (i, item) = match next { Some(next) => next, None => break };
+                if sender.send((i, solve(item))).is_err() { break; }
+            });
+        }
+        drop(sender);
+        let mut pending = BTreeMap::new();
+        let mut next = 0;
+        for (i, result) in receiver.iter() {
+            pending.insert(i, result);
+            while let Some(result) = pending.remove(&next) {
+                report(&items[next], result);
+                next += 1;
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use crate::day;
+
+    #[test]
+    fn ordered() {
+        let items = (0..8u64).collect::<Vec<_>>();
+        let mut seen = vec![];
+        run(&items, 4, |&n| {
+            thread::sleep(Duration::from_millis(40 - 5 * n));
+            vec![day::Answer::Number(n as i64)]
+        }, |&n, answers| {
+            assert_eq!(answers, vec![day::Answer::Number(n as i64)]);
+            seen.push(n);
+        });
+        assert_eq!(seen, items);
+    }
+}
@@ -0,0 +1,130 @@
+use regex::Captures;
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+use crate::day;
+use crate::error;
+
+/// Where and why a day's input could not be parsed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    /// The input being read, unknown outside of `day::run`.
+    pub file: Option<String>,
+    pub line: usize,
+    pub column: usize,
+    pub text: String,
+    pub expected: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(file) = &self.file { write!(f, "{}: ", file)?; }
+        write!(f, "line {}, column {}: expected {}, found \"{}\"",
+               self.line, self.column, self.expected, self.text)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// A line of input, numbered from 1.
+pub struct Line {
+    pub number: usize,
+    pub text: String,
+}
+
+impl Line {
+    /// An error about `s`, which should be a slice of the line's text.
+    pub fn error(&self, s: &str, expected: &str) -> ParseError {
+        let start = (s.as_ptr() as usize).checked_sub(self.text.as_ptr() as usize)
+            .filter(|&offset| offset + s.len() <= self.text.len())
+            .unwrap_or(0);
+        ParseError {
+            file: day::input_name(), line: self.number,
+            column: self.text[..start].chars().count() + 1,
+            text: s.to_string(), expected: expected.to_string(),
+        }
+    }
+
+    /// An error about the whole line.
+    pub fn expected(&self, expected: &str) -> ParseError { self.error(&self.text, expected) }
+
+    /// Parses `s`, a slice of the line's text.
+    pub fn field<T: FromStr>(&self, s: &str, expected: &str) -> Result<T, ParseError> {
+        s.parse().map_err(|_| self.error(s, expected))
+    }
+
+    /// Parses the i:th group captured from the line.
+    pub fn capture<T: FromStr>(&self, cap: &Captures, i: usize, expected: &str)
+        -> Result<T, ParseError> {
+        match cap.get(i) {
+            Some(m) => self.field(m.as_str(), expected),
+            None => Err(self.expected(expected)),
+        }
+    }
+
+    /// Checks that every character of the line is one of `allowed`.
+    pub fn only(&self, allowed: &str, expected: &str) -> Result<(), ParseError> {
+        match self.text.char_indices().find(|&(_, c)| !allowed.contains(c)) {
+            Some((i, c)) => Err(self.error(&self.text[i..i + c.len_utf8()], expected)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The lines of the input, without the blank ones at its end.
+pub fn lines(input: &mut dyn io::Read) -> error::Result<Vec<Line>> {
+    let mut bytes = vec![];
+    input.read_to_end(&mut bytes)?;
+    let mut lines = vec![];
+    for (i, line) in bytes.split(|&b| b == b'\n').enumerate() {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        let text = match String::from_utf8(line.to_vec()) {
+            Ok(text) => text,
+            Err(e) => {
+                let valid = String::from_utf8_lossy(&line[..e.utf8_error().valid_up_to()]);
+                return Err(ParseError {
+                    file: day::input_name(), line: i + 1, column: valid.chars().count() + 1,
+                    text: String::from_utf8_lossy(&line[valid.len()..]).chars().take(1).collect(),
+                    expected: String::from("UTF-8 text"),
+                }.into());
+            },
+        };
+        lines.push(Line { number: i + 1, text });
+    }
+    while lines.last().is_some_and(|l| l.text.trim().is_empty()) { lines.pop(); }
+    Ok(lines)
+}
+
+/// Comma separated numbers, like an Intcode program.
+pub fn numbers<T: FromStr>(input: &mut dyn io::Read, expected: &str) -> error::Result<Vec<T>> {
+    let mut numbers = vec![];
+    for line in lines(input)? {
+        for s in line.text.split(',') {
+            numbers.push(line.field(s.trim(), expected)?);
+        }
+    }
+    Ok(numbers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors() {
+        assert_eq!(numbers::<i64>(&mut "1,-2,\n3\n\n".as_bytes(), "an integer").unwrap_err()
+                       .to_string(),
+                   "line 1, column 6: expected an integer, found \"\"");
+        assert_eq!(numbers::<i64>(&mut "1, 2,3\n\n".as_bytes(), "an integer").unwrap(),
+                   vec![1, 2, 3]);
+        let lines = lines(&mut "#.#\r\n#x.\n".as_bytes()).unwrap();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[1].only("#.", "a wall or a passage"), Err(ParseError {
+            file: None, line: 2, column: 2, text: String::from("x"),
+            expected: String::from("a wall or a passage"),
+        }));
+        assert!(lines[0].only("#.", "").is_ok());
+        let e = super::lines(&mut &b"ok\n\xc3\xa5\xff"[..]).err().unwrap();
+        assert_eq!(e.to_string(), "line 2, column 2: expected UTF-8 text, found \"\u{fffd}\"");
+    }
+}
@@ -0,0 +1,280 @@
+use std::collections::HashMap;
+use std::io;
+use std::io::{BufRead, Write};
+use std::time::{Duration, Instant};
+use crate::config::Config;
+use crate::day::{self, Day};
+use crate::generate;
+use crate::input::{Input, Source};
+
+pub const HELP: &str = "\
+days                        list the days
+day <day>                   pick a day, e.g. 22 or 2019/22, with its input if there is one
+input <file>                read the day's input from a file
+paste                       paste the day's input, ending with a line with just a dot
+generate [<size>] [<seed>]  use a random input, for the days that have a generator
+params                      show the day's parameters and their values
+set <name>=<value>          set a parameter, e.g. set deck=10
+reset [<name>]              set a parameter, or all of them, back to the default
+run [1|2]                   run the parts, or one of them, showing answers and timings
+help                        show this text
+quit                        leave, as does the end of the input";
+
+/// An interactive session: the day picked, its input and the parameter values
+/// its parts run with.
+pub struct Session<'a> {
+    days: &'a [&'static dyn Day],
+    source: Option<Source>,
+    config: &'a Config,
+    timeout: Option<Duration>,
+    day: Option<&'static dyn Day>,
+    input: Option<Result<Input, String>>,
+    params: HashMap<String, i64>,
+    /// The input being pasted.
+    paste: Option<String>,
+}
+
+impl<'a> Session<'a> {
+    /// A session over the given days, taking their inputs from `source` and
+    /// the parameters to start with from `config`.
+    pub fn new(days: &'a [&'static dyn Day], source: Option<Source>, config: &'a Config,
+               timeout: Option<Duration>) -> Self {
+        Session {
+            days, source, config, timeout, day: None, input: None, params: HashMap::new(),
+            paste: None,
+        }
+    }
+
+    pub fn prompt(&self) -> String {
+        match (&self.paste, self.day) {
+            (Some(_), _) => String::new(),
+            (None, Some(day)) => format!("{}> ", day.id()),
+            (None, None) => String::from("> "),
+        }
+    }
+
+    /// Carries out a command, returning what to show, or `None` to quit.
+    pub fn command(&mut self, line: &str) -> Option<String> {
+        if let Some(text) = &mut self.paste {
+            if line.trim_end() != "." {
+                text.push_str(line);
+                text.push('\n');
+                return Some(String::new());
+            }
+            let text = self.paste.take().unwrap_or_default();
+            self.input = Some(Source::text(&text).input(day::YEAR, ""));
+            return Some(format!("{} lines", text.lines().count()));
+        }
+        let mut words = line.split_whitespace();
+        let command = words.next().unwrap_or("");
+        let args = words.collect::<Vec<_>>();
+        let result = match command {
+            "" => Ok(String::new()),
+            "quit" | "exit" => return None,
+            "help" | "?" => Ok(HELP.to_string()),
+            "days" => Ok(self.days.iter().map(|day| format!("{:>7}  {}", day.id(), day.title()))
+                .collect::<Vec<_>>().join("\n")),
+            "day" => self.pick(&args),
+            "input" => self.file(&args),
+            "paste" => {
+                self.paste = Some(String::new());
+                Ok(String::from("paste the input, then a line with just a dot"))
+            },
+            "generate" => self.generate(&args),
+            "params" => self.show_params(),
+            "set" => self.set(&args),
+            "reset" => self.reset(&args),
+            "run" => self.run(&args),
+            _ => Err(format!("unknown command \"{}\", try help", command)),
+        };
+        Some(result.unwrap_or_else(|e| format!("error: {}", e)))
+    }
+
+    fn day(&self) -> Result<&'static dyn Day, String> {
+        self.day.ok_or_else(|| String::from("no day picked, try day <day>"))
+    }
+
+    fn pick(&mut self, args: &[&str]) -> Result<String, String> {
+        let arg = match args {
+            [arg] => *arg,
+            _ => return Err(String::from("usage: day <day>")),
+        };
+        let tag = day::qualify(arg);
+        let (year, d) = tag.split_once('/').unwrap_or(("", ""));
+        let id = match d.parse::<u32>() {
+            Ok(d) => format!("{}/{:02}", year, d),
+            Err(_) => tag.clone(),
+        };
+        let day = *self.days.iter().find(|day| day.id() == id)
+            .ok_or_else(|| format!("no day {}", arg))?;
+        self.day = Some(day);
+        self.params = self.config.get(&id);
+        self.input = self.source.as_ref().map(|source| source.input(day.year(), day.tag()));
+        let mut s = format!("{}  {}", id, day.title());
+        match &self.input {
+            Some(Ok(input)) => s += &format!("\ninput: {}", input.name),
+            Some(Err(e)) => s += &format!("\nno input: {}", e),
+            None => s += "\nno input yet, try input, paste or generate",
+        }
+        Ok(s)
+    }
+
+    fn file(&mut self, args: &[&str]) -> Result<String, String> {
+        let day = self.day()?;
+        let path = match args {
+            [path] => *path,
+            _ => return Err(String::from("usage: input <file>")),
+        };
+        let input = Source::Pattern(path.to_string()).input(day.year(), day.tag())?;
+        let lines = input.read().map_err(|e| format!("{}: {}", input.name, e))?
+            .split(|&b| b == b'\n').filter(|line| !line.is_empty()).count();
+        let s = format!("{}: {} lines", input.name, lines);
+        self.input = Some(Ok(input));
+        Ok(s)
+    }
+
+    fn generate(&mut self, args: &[&str]) -> Result<String, String> {
+        let day = self.day()?;
+        let number = |i: usize| args.get(i).map(|arg| arg.parse::<u64>()
+            .map_err(|_| format!("not a number: {}", arg))).transpose();
+        let (size, seed) = (number(0)?, number(1)?.unwrap_or(1));
+        let text = generate::generate(day, seed, size.map(|size| size as usize))
+            .ok_or_else(|| format!("day {} has no generator", day.id()))?;
+        self.input = Some(Source::text(&text).input(day.year(), day.tag()));
+        Ok(format!("{} lines from seed {}", text.lines().count(), seed))
+    }
+
+    fn show_params(&self) -> Result<String, String> {
+        let day = self.day()?;
+        if day.params().is_empty() { return Ok(format!("day {} has no parameters", day.id())); }
+        Ok(day.params().iter().map(|param| match self.params.get(param.name) {
+            Some(value) => format!("{}={} (default {})  {}",
+                                   param.name, value, param.default, param.help),
+            None => format!("{}={}  {}", param.name, param.default, param.help),
+        }).collect::<Vec<_>>().join("\n"))
+    }
+
+    fn set(&mut self, args: &[&str]) -> Result<String, String> {
+        let day = self.day()?;
+        let joined = args.join("=");
+        let (name, value) = joined.split_once('=')
+            .ok_or_else(|| String::from("usage: set <name>=<value>"))?;
+        let value = value.trim_start_matches('=');
+        let value = value.parse::<i64>().map_err(|_| format!("not a number: {}", value))?;
+        day::check_param(day, name, value)?;
+        self.params.insert(name.to_string(), value);
+        Ok(String::new())
+    }
+
+    fn reset(&mut self, args: &[&str]) -> Result<String, String> {
+        self.day()?;
+        match args {
+            [] => self.params.clear(),
+            [name] => { self.params.remove(*name); },
+            _ => return Err(String::from("usage: reset [<name>]")),
+        }
+        Ok(String::new())
+    }
+
+    fn run(&mut self, args: &[&str]) -> Result<String, String> {
+        let day = self.day()?;
+        let parts = match args {
+            [] => vec![1, 2],
+            ["1"] => vec![1],
+            ["2"] => vec![2],
+            _ => return Err(String::from("usage: run [1|2]")),
+        };
+        let input = match &self.input {
+            Some(Ok(input)) => input,
+            Some(Err(e)) => return Err(e.clone()),
+            None => return Err(String::from("no input, try input, paste or generate")),
+        };
+        let bytes = input.read().map_err(|e| format!("{}: {}", input.name, e))?;
+        let open = || Box::new(io::Cursor::new(bytes.clone())) as Box<dyn io::Read>;
+        let start = Instant::now();
+        let parsed = day::parse(day, &open, &input.name, &self.params, self.timeout)
+            .map_err(|e| e.to_string())?;
+        let mut lines = vec![format!("parsed in {:.2?}", start.elapsed())];
+        lines.extend(parts.iter().filter(|&&part| !day.parts()[part - 1].is_empty()).map(|&part| {
+            let start = Instant::now();
+            let answer = day::solve(day, part, &parsed, &input.name, &self.params, self.timeout);
+            format!("part {} ({:.2?}): {}", part, start.elapsed(), answer)
+        }));
+        Ok(lines.join("\n"))
+    }
+}
+
+/// Reads commands until `quit` or the end of the input, prompting for each.
+pub fn run(session: &mut Session, input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+    let mut lines = input.lines();
+    loop {
+        write!(output, "{}", session.prompt())?;
+        output.flush()?;
+        let line = match lines.next() {
+            Some(line) => line?,
+            None => return writeln!(output),
+        };
+        match session.command(&line) {
+            Some(s) if s.is_empty() => (),
+            Some(s) => writeln!(output, "{}", s)?,
+            None => return Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn session() {
+        let days = day::registry().unwrap();
+        let config = Config::default();
+        let mut session = Session::new(&days, None, &config, None);
+        assert!(session.command("run").unwrap().starts_with("error: no day picked"));
+        assert!(session.command("day 12").unwrap().starts_with("2019/12  The N-Body Problem"));
+        assert_eq!(session.prompt(), "2019/12> ");
+        assert_eq!(session.command("paste").unwrap(),
+                   "paste the input, then a line with just a dot");
+        for line in &["<x=-1, y=0, z=2>", "<x=2, y=-10, z=-7>", "<x=4, y=-8, z=8>"] {
+            assert_eq!(session.command(line).unwrap(), "");
+        }
+        session.command("<x=3, y=5, z=-1>");
+        assert_eq!(session.command(".").unwrap(), "4 lines");
+        assert_eq!(session.command("set steps=10").unwrap(), "");
+        assert!(session.command("params").unwrap().starts_with("steps=10 (default 1000)"));
+        let run = session.command("run 1").unwrap();
+        assert!(run.starts_with("parsed in ") && run.contains("\npart 1 (")
+                && run.ends_with("): 179"), "{}", run);
+        assert!(session.command("run").unwrap().ends_with("): 2772"));
+        session.command("reset");
+        assert!(session.command("params").unwrap().starts_with("steps=1000  "));
+        assert!(session.command("set stepz 10").unwrap().contains("no parameter \"stepz\""));
+        assert!(session.command("day 26").unwrap().starts_with("error: no day"));
+        assert!(session.command("frobnicate").unwrap().starts_with("error: unknown command"));
+        assert!(session.command("quit").is_none());
+    }
+
+    #[test]
+    fn commands() {
+        let days = day::registry().unwrap();
+        let config = Config::default();
+        let mut session = Session::new(&days, None, &config, None);
+        let mut output = vec![];
+        run(&mut session, "day 1\ngenerate 5\nrun 1\n".as_bytes(), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("2019/01> error: day 2019/01 has no generator\n"), "{}", output);
+        let mut output = vec![];
+        run(&mut session, "day 2019/6\ngenerate 5\nquit\nrun\n".as_bytes(), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.ends_with("2019/06> 5 lines from seed 1\n2019/06> "), "{}", output);
+        let path = std::env::temp_dir().join(format!("repl-{}", std::process::id()));
+        std::fs::write(&path, "12\n").unwrap();
+        session.command("day 1");
+        let loaded = session.command(&format!("input {}", path.display())).unwrap();
+        assert!(loaded.ends_with("1 lines"), "{}", loaded);
+        std::fs::remove_file(&path).unwrap();
+        let run = session.command("run").unwrap();
+        assert!(run.starts_with("error: ") && !run.contains("part 1"), "{}", run);
+    }
+}
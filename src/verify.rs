@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::io::BufRead;
+use crate::day::{self, Answer};
+
+/// Expected answers, keyed by day tag and part, one entry per output line.
+///
+/// The file holds lines of the form `<tag> <part> <answer>`, e.g. `07 1 43210`
+/// or `2018/07 1 43210` for a day of another year than 2019.
+/// Multi-line answers repeat the key on every line, blank lines and lines
+/// starting with `#` are ignored.
+pub struct Expected {
+    answers: HashMap<(String, usize), Vec<String>>,
+}
+
+impl Expected {
+    pub fn load(path: &str) -> io::Result<Self> {
+        Self::read(io::BufReader::new(fs::File::open(path)?))
+    }
+
+    pub fn read(reader: impl BufRead) -> io::Result<Self> {
+        let mut answers = HashMap::new();
+        for (n, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() || line.starts_with('#') { continue; }
+            let mut split = line.splitn(3, ' ');
+            let tag = split.next().unwrap_or("");
+            let part = split.next().and_then(|p| p.parse::<usize>().ok());
+            match part {
+                Some(part) => answers.entry((day::qualify(tag), part))
+                    .or_insert_with(Vec::new)
+                    .push(split.next().unwrap_or("").to_string()),
+                None => return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("line {}: expected \"<tag> <part> <answer>\"", n + 1))),
+            }
+        }
+        Ok(Expected { answers })
+    }
+
+    pub fn get(&self, tag: &str, part: usize) -> Option<&Vec<String>> {
+        self.answers.get(&(day::qualify(tag), part))
+    }
+}
+
+pub enum Outcome {
+    Pass,
+    Fail(Vec<String>),
+    Missing,
+}
+
+fn lines(answer: &Answer) -> Vec<String> {
+    match answer {
+        Answer::None => vec![],
+        Answer::Lines(lines) => lines.clone(),
+        _ => vec![answer.to_string()],
+    }
+}
+
+/// Compares an answer with the expected lines, returning a line diff on mismatch.
+pub fn check(expected: Option<&Vec<String>>, answer: &Answer) -> Outcome {
+    let expected = match expected {
+        Some(expected) => expected,
+        None => return Outcome::Missing,
+    };
+    let actual = lines(answer);
+    let same = |a: &String, b: &String| a.trim_end() == b.trim_end();
+    if !answer.is_error() && expected.len() == actual.len()
+        && expected.iter().zip(actual.iter()).all(|(a, b)| same(a, b)) {
+        return Outcome::Pass;
+    }
+    let mut diff = vec![];
+    for i in 0..expected.len().max(actual.len()) {
+        match (expected.get(i), actual.get(i)) {
+            (Some(e), Some(a)) if same(e, a) => diff.push(format!("  {}", e)),
+            (e, a) => {
+                if let Some(e) = e { diff.push(format!("- {}", e)); }
+                if let Some(a) = a { diff.push(format!("+ {}", a)); }
+            },
+        }
+    }
+    Outcome::Fail(diff)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expected(s: &str) -> Expected { Expected::read(s.as_bytes()).unwrap() }
+
+    #[test]
+    fn pass_and_fail() {
+        let e = expected("# comment\n01 1 3342\n08 2  .X\n08 2 X. \n2018/01 1 3\n");
+        assert!(matches!(check(e.get("01", 1), &Answer::Number(3342)), Outcome::Pass));
+        assert!(matches!(check(e.get("01", 2), &Answer::Number(3342)), Outcome::Missing));
+        assert!(matches!(check(e.get("2019/01", 1), &Answer::Number(3342)), Outcome::Pass));
+        assert!(matches!(check(e.get("2018/01", 1), &Answer::Number(3)), Outcome::Pass));
+        let art = Answer::Lines(vec![String::from(" .X"), String::from("X.")]);
+        assert!(matches!(check(e.get("08", 2), &art), Outcome::Pass));
+        match check(e.get("01", 1), &Answer::Number(3343)) {
+            Outcome::Fail(diff) => assert_eq!(diff, vec!["- 3342", "+ 3343"]),
+            _ => panic!("expected a failure"),
+        }
+    }
+
+    #[test]
+    fn bad_line() {
+        assert!(Expected::read("01 x 3342\n".as_bytes()).is_err());
+    }
+}
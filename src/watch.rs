@@ -0,0 +1,63 @@
+use std::thread;
+use std::time::{Duration, SystemTime};
+use crate::input::Input;
+
+/// Clears the terminal and moves the cursor to its top left corner.
+pub const CLEAR: &str = "\x1b[2J\x1b[H";
+
+/// How often the inputs are looked at.
+pub const INTERVAL: Duration = Duration::from_millis(500);
+
+/// What tells whether a day's input changed: which file it is and when it
+/// was last modified, or why there is none.
+pub type Stamp = Result<(String, Option<SystemTime>), String>;
+
+pub fn stamp(input: &Result<Input, String>) -> Stamp {
+    input.as_ref().map(|input| (input.name.clone(), input.modified())).map_err(Clone::clone)
+}
+
+/// Waits for some of the stamps `poll` gives to differ from `stamps`, and for
+/// them to settle, so that a file being written is read once it is done.
+/// Returns the indices of those that changed.
+pub fn wait(stamps: &mut Vec<Stamp>, poll: impl Fn() -> Vec<Stamp>, interval: Duration)
+    -> Vec<usize> {
+    let mut current = loop {
+        let current = poll();
+        if current != *stamps { break current; }
+        thread::sleep(interval);
+    };
+    loop {
+        thread::sleep(interval);
+        let next = poll();
+        if next == current { break; }
+        current = next;
+    }
+    let changed = (0..current.len()).filter(|&i| stamps.get(i) != current.get(i)).collect();
+    *stamps = current;
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    #[test]
+    fn changes() {
+        let at = |secs|
+            Ok((String::from("07"), Some(SystemTime::UNIX_EPOCH + Duration::from_secs(secs))));
+        let mut stamps = vec![at(1), Err(String::from("no input"))];
+        let polls = Cell::new(0);
+        let poll = || {
+            polls.set(polls.get() + 1);
+            match polls.get() {
+                1 | 2 => vec![at(1), Err(String::from("no input"))],
+                3 => vec![at(2), Err(String::from("no input"))],
+                _ => vec![at(3), Err(String::from("no input"))],
+            }
+        };
+        assert_eq!(wait(&mut stamps, poll, Duration::ZERO), vec![0]);
+        assert_eq!(stamps[0], at(3));
+        assert_eq!(polls.get(), 5);
+    }
+}
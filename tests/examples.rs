@@ -0,0 +1,24 @@
+use adventofcode2019::{day, examples, verify};
+use std::time::Duration;
+
+/// Runs every registered day on the examples from its puzzle's text.
+#[test]
+fn examples() {
+    let mut failures = vec![];
+    let mut checked = 0;
+    for day in day::registry().unwrap() {
+        let examples = examples::load(examples::DIR, day).unwrap();
+        for example in &examples {
+            for (part, answer, outcome) in
+                examples::run(day, example, &[1, 2], Some(Duration::from_secs(60))) {
+                checked += 1;
+                if let verify::Outcome::Fail(diff) = outcome {
+                    failures.push(format!("{} part {} example {}: {}\n{}",
+                                          day.id(), part, example.name, answer, diff.join("\n")));
+                }
+            }
+        }
+    }
+    assert!(checked > 0);
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}